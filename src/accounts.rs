@@ -0,0 +1,249 @@
+use crate::{
+    config::Config, fanout::FetchPlan, http_client, parse_response, proxy::endpoint, render_accounts, Account, Args, BOLD,
+    GREEN, RED, RESET,
+};
+use anyhow::{bail, Context, Result};
+use chrono::Utc;
+use clap::{Args as ClapArgs, Subcommand};
+use reqwest::{Method, Url};
+use std::{
+    io::{self, BufRead, IsTerminal, Write},
+    time::{Duration, Instant},
+};
+
+#[derive(ClapArgs, Debug)]
+pub struct AccountsArgs {
+    #[command(subcommand)]
+    pub command: AccountsCommand,
+
+    /// Don't ask for confirmation
+    #[arg(short, long, global = true)]
+    pub yes: bool,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum AccountsCommand {
+    /// Register an account with the proxy
+    Add {
+        email: String,
+        /// OAuth refresh token for the account, if the proxy expects one
+        #[arg(long)]
+        refresh_token: Option<String>,
+    },
+    /// Remove an account from the proxy
+    Remove { email: String },
+    /// Let the proxy use an account it has been told to skip
+    Enable { email: String },
+    /// Keep the proxy from using an account without removing it
+    Disable { email: String },
+    /// Ask the proxy to re-check invalid accounts (or just EMAIL) and wait for them to recover
+    Refresh {
+        email: Option<String>,
+        /// Stop waiting for recovery after this long
+        #[arg(long, default_value = "60s", value_parser = humantime::parse_duration)]
+        timeout: Duration,
+    },
+}
+
+/// How often `accounts refresh` re-reads account state while waiting
+const REFRESH_POLL: Duration = Duration::from_secs(2);
+
+/// `base/segment` with `segment` (usually an email) percent-encoded as one path segment
+fn account_url(base: &Url, segment: &str) -> Url {
+    let mut url = base.clone();
+    if let Ok(mut segments) = url.path_segments_mut() {
+        segments.pop_if_empty().push(segment);
+    }
+    url
+}
+
+/// Asks `question` on the terminal; without one, only `--yes` can approve
+fn confirm(question: &str, yes: bool) -> Result<bool> {
+    if yes {
+        return Ok(true);
+    }
+    let stdin = io::stdin();
+    if !stdin.is_terminal() {
+        bail!("{} Refusing without a terminal; pass --yes to confirm", question);
+    }
+    eprint!("{} [y/N] ", question);
+    io::stderr().flush().ok();
+    let mut answer = String::new();
+    stdin.lock().read_line(&mut answer).context("Failed to read answer")?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+/// Sends a management request, turning non-2xx replies into errors that include the proxy's message
+pub async fn send(
+    client: &reqwest::Client,
+    method: Method,
+    url: Url,
+    body: Option<serde_json::Value>,
+) -> Result<()> {
+    let mut request = client.request(method.clone(), url.clone());
+    if let Some(body) = body {
+        request = request.json(&body);
+    }
+    let response = request
+        .send()
+        .await
+        .with_context(|| format!("Failed to connect to {}", url))?;
+    let status = response.status();
+    if status.is_success() {
+        return Ok(());
+    }
+    let text = response.text().await.unwrap_or_default();
+    let message = serde_json::from_str::<serde_json::Value>(&text)
+        .ok()
+        .and_then(|json| json.get("error").and_then(|e| e.as_str()).map(str::to_string))
+        .unwrap_or(text);
+    bail!("{} {} failed with {}: {}", method, url, status, message.trim())
+}
+
+/// Emails of the accounts the proxy currently flags as invalid
+async fn invalid_accounts(client: &reqwest::Client, args: &Args, config: &Config) -> Result<Vec<String>> {
+    let text = FetchPlan::new(args, config).fetch(client, &args.url).await?;
+    let data = parse_response(&text, config)?;
+    Ok(data
+        .accounts
+        .into_iter()
+        .filter(|a| a.is_invalid.unwrap_or(false))
+        .map(|a| a.email.to_string())
+        .collect())
+}
+
+/// `accounts refresh`: triggers revalidation, then polls until the accounts are valid or `timeout` passes
+async fn refresh(
+    client: &reqwest::Client,
+    args: &Args,
+    config: &Config,
+    base: &Url,
+    email: Option<&str>,
+    timeout: Duration,
+) -> Result<()> {
+    let mut pending = match email {
+        Some(email) => {
+            send(client, Method::POST, account_url(&account_url(base, email), "refresh"), None).await?;
+            vec![email.to_string()]
+        }
+        None => {
+            let invalid = invalid_accounts(client, args, config).await?;
+            if invalid.is_empty() {
+                println!("No invalid accounts");
+                return Ok(());
+            }
+            send(client, Method::POST, account_url(base, "refresh"), None).await?;
+            invalid
+        }
+    };
+    println!("Re-checking {}...", pending.join(", "));
+
+    let deadline = Instant::now() + timeout;
+    let mut recovered = Vec::new();
+    loop {
+        let invalid = invalid_accounts(client, args, config).await?;
+        pending.retain(|email| {
+            let still_invalid = invalid.contains(email);
+            if !still_invalid {
+                println!("{}{}Recovered{} {}", BOLD, GREEN, RESET, email);
+                recovered.push(email.clone());
+            }
+            still_invalid
+        });
+        if pending.is_empty() || Instant::now() >= deadline {
+            break;
+        }
+        tokio::time::sleep(REFRESH_POLL.min(deadline.saturating_duration_since(Instant::now()))).await;
+    }
+
+    for email in &pending {
+        println!("{}{}Still invalid{} {}", BOLD, RED, RESET, email);
+    }
+    if !pending.is_empty() {
+        bail!(
+            "{} of {} account{} still invalid after {}",
+            pending.len(),
+            pending.len() + recovered.len(),
+            if pending.len() + recovered.len() == 1 { "" } else { "s" },
+            humantime::format_duration(timeout)
+        );
+    }
+    Ok(())
+}
+
+/// `accounts enable` and `accounts disable`: switches the account and
+/// checks that the proxy reports the new state. Either is undone as easily,
+/// so neither asks for confirmation.
+async fn set_enabled(
+    client: &reqwest::Client,
+    args: &Args,
+    config: &Config,
+    base: &Url,
+    email: &str,
+    enabled: bool,
+) -> Result<()> {
+    let body = serde_json::json!({ "enabled": enabled });
+    send(client, Method::PATCH, account_url(base, email), Some(body)).await?;
+
+    let text = FetchPlan::new(args, config).fetch(client, &args.url).await?;
+    let data = parse_response(&text, config)?;
+    let Some(account) = data.accounts.iter().find(|a| a.email == email) else {
+        bail!("The proxy accepted the change but doesn't list {}", email);
+    };
+    if account.enabled.unwrap_or(true) != enabled {
+        bail!(
+            "The proxy accepted the change but still reports {} as {}",
+            email,
+            if enabled { "disabled" } else { "enabled" }
+        );
+    }
+    println!("{}{}{} {}", BOLD, if enabled { "Enabled" } else { "Disabled" }, RESET, email);
+    Ok(())
+}
+
+/// The account table as the proxy now reports it
+pub async fn print_roster(client: &reqwest::Client, args: &Args, config: &Config) -> Result<()> {
+    let text = FetchPlan::new(args, config).fetch(client, &args.url).await?;
+    let data = parse_response(&text, config)?;
+    let mut out = String::new();
+    let accounts: Vec<&Account> = data.accounts.iter().collect();
+    render_accounts(&mut out, &accounts, Utc::now(), config).context("Failed to render accounts")?;
+    println!();
+    print!("{}", out);
+    Ok(())
+}
+
+/// `accounts` subcommand
+pub async fn run(args: &Args, config: &Config, accounts_args: &AccountsArgs) -> Result<()> {
+    let client = http_client(args)?;
+    let base = endpoint(&args.url, &config.proxy.accounts_path)?;
+
+    match accounts_args.command {
+        AccountsCommand::Add { ref email, ref refresh_token } => {
+            if !confirm(&format!("Add {} to the proxy at {}?", email, base), accounts_args.yes)? {
+                return Ok(());
+            }
+            let mut body = serde_json::json!({ "email": email });
+            if let Some(token) = refresh_token {
+                body["refreshToken"] = serde_json::Value::String(token.clone());
+            }
+            send(&client, Method::POST, base, Some(body)).await?;
+            println!("{}Added{} {}", BOLD, RESET, email);
+        }
+        AccountsCommand::Remove { ref email } => {
+            if !confirm(&format!("Remove {} from the proxy?", email), accounts_args.yes)? {
+                return Ok(());
+            }
+            send(&client, Method::DELETE, account_url(&base, email), None).await?;
+            println!("{}Removed{} {}", BOLD, RESET, email);
+        }
+        AccountsCommand::Enable { ref email } => set_enabled(&client, args, config, &base, email, true).await?,
+        AccountsCommand::Disable { ref email } => set_enabled(&client, args, config, &base, email, false).await?,
+        AccountsCommand::Refresh { ref email, timeout } => {
+            // Revalidation changes nothing on the proxy's roster; no confirmation needed
+            return refresh(&client, args, config, &base, email.as_deref(), timeout).await;
+        }
+    }
+
+    print_roster(&client, args, config).await
+}
@@ -0,0 +1,113 @@
+use crate::{
+    intern::Name,
+    config::Config, get_short_email, locale::Percent, model_available, ApiResponse, HumanDuration, BOLD, GREEN, INVERSE,
+    RED, RESET, YELLOW,
+};
+use chrono::{DateTime, Local, Utc};
+use std::{
+    collections::HashSet,
+    fmt::{self, Write as _},
+};
+
+pub const BELL: char = '\x07';
+
+/// `--watch-model` state: which watched models currently have no available account
+pub struct ModelAlarm {
+    models: Vec<(String, bool)>,
+    flash: bool,
+}
+
+impl ModelAlarm {
+    pub fn new(models: &[String], flash: bool) -> Self {
+        ModelAlarm {
+            models: models.iter().map(|m| (m.clone(), false)).collect(),
+            flash,
+        }
+    }
+
+    /// Updates exhaustion state from `data` and writes a banner line per watched
+    /// model, ringing the bell for every model that just ran out or recovered
+    pub fn render(&mut self, out: &mut String, data: &ApiResponse) -> fmt::Result {
+        for (model, exhausted) in &mut self.models {
+            let available = data.accounts.iter().filter(|a| model_available(a, model)).count();
+            let now_exhausted = available == 0;
+            let changed = now_exhausted != *exhausted;
+            *exhausted = now_exhausted;
+
+            if changed {
+                out.push(BELL);
+            }
+            if now_exhausted {
+                let highlight = if self.flash { INVERSE } else { "" };
+                writeln!(
+                    out,
+                    "{}{}{}ALERT: {} has no available accounts{}",
+                    BOLD, RED, highlight, model, RESET
+                )?;
+            } else if changed {
+                writeln!(
+                    out,
+                    "{}{}Capacity returned: {} ({} available){}",
+                    BOLD, GREEN, model, available, RESET
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `budgets` in the config: which accounts are below their budget before the
+/// end of the local workday (`thresholds.budget_until`)
+#[derive(Default)]
+pub struct BudgetAlarm {
+    over: HashSet<Name>,
+}
+
+impl BudgetAlarm {
+    /// Writes a banner line per account under its budget by its lowest
+    /// remaining fraction, ringing the bell for every account that just fell
+    /// under. After the workday ends the budgets no longer apply.
+    pub fn render(&mut self, out: &mut String, data: &ApiResponse, config: &Config, now: DateTime<Utc>) -> fmt::Result {
+        if config.budgets.is_empty() {
+            return Ok(());
+        }
+        let local = now.with_timezone(&Local);
+        let until = config.thresholds.budget_until;
+        let left = until.signed_duration_since(local.time());
+        for account in &data.accounts {
+            let Some(budget) = config.budget_for(&account.email) else {
+                continue;
+            };
+            let usable = !account.is_invalid.unwrap_or(false) && account.enabled.unwrap_or(true);
+            let lowest = account
+                .limits
+                .iter()
+                .flatten()
+                .map(|(_, quota)| quota.remaining_fraction)
+                .min_by(f64::total_cmp);
+            let under = match lowest {
+                Some(fraction) if usable && left > chrono::Duration::zero() && fraction < budget => fraction,
+                _ => {
+                    self.over.remove(&account.email);
+                    continue;
+                }
+            };
+            if self.over.insert(account.email.clone()) {
+                out.push(BELL);
+            }
+            writeln!(
+                out,
+                "{}{}BUDGET: {} is at {} with {} to go until {} (budget {}){}",
+                BOLD,
+                YELLOW,
+                get_short_email(&account.email),
+                Percent(under),
+                HumanDuration(left),
+                until.format("%H:%M"),
+                Percent(budget),
+                RESET
+            )?;
+        }
+        Ok(())
+    }
+}
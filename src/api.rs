@@ -0,0 +1,134 @@
+use crate::{deserialize_at_path, error::FetchError, ApiResponse};
+use clap::ValueEnum;
+use serde::Deserialize;
+
+/// The shapes of the account-limits response across proxy versions, each
+/// decoded by its own adapter into the one [`ApiResponse`] the tables use
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiVersion {
+    /// Tell the version from each response's shape
+    #[default]
+    Auto,
+    /// The document as a JSON string in `{"result": "..."}`
+    V1,
+    /// The document as plain JSON
+    V2,
+}
+
+/// What a v1 response wraps its document in
+#[derive(Debug, Deserialize)]
+struct Envelope {
+    result: String,
+}
+
+impl ApiVersion {
+    /// The version a response is in: v1 when it's an object whose `result`
+    /// is a string, v2 otherwise
+    pub fn detect(text: &str) -> ApiVersion {
+        match serde_json::from_str::<Envelope>(text) {
+            Ok(_) => ApiVersion::V1,
+            Err(_) => ApiVersion::V2,
+        }
+    }
+
+    pub fn decode(self, text: &str) -> Result<ApiResponse, FetchError> {
+        match self {
+            ApiVersion::Auto => ApiVersion::detect(text).decode(text),
+            ApiVersion::V1 => {
+                let envelope: Envelope = deserialize_at_path(text, "Failed to parse v1 response")?;
+                let mut data: ApiResponse = deserialize_at_path(&envelope.result, "Failed to parse inner JSON")?;
+                data.wrapped = true;
+                Ok(data)
+            }
+            ApiVersion::V2 => deserialize_at_path(text, "Failed to parse JSON"),
+        }
+    }
+
+    /// `document`, plain JSON, in this version's shape; auto keeps it plain
+    pub fn encode(self, document: String) -> String {
+        match self {
+            ApiVersion::V1 => serde_json::json!({ "result": document }).to_string(),
+            ApiVersion::Auto | ApiVersion::V2 => document,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOCUMENT: &str = r#"{
+        "timestamp": "10/14/2026, 01:08:16 PM",
+        "models": ["gemini-3-flash"],
+        "accounts": [{
+            "email": "alice@example.com",
+            "enabled": true,
+            "limits": {"gemini-3-flash": {"remainingFraction": 0.5, "resetTime": null}}
+        }]
+    }"#;
+
+    fn wrapped() -> String {
+        serde_json::json!({ "result": DOCUMENT }).to_string()
+    }
+
+    #[test]
+    fn detects_the_wrapper_by_a_string_result() {
+        assert_eq!(ApiVersion::detect(&wrapped()), ApiVersion::V1);
+        assert_eq!(ApiVersion::detect(DOCUMENT), ApiVersion::V2);
+        // A `result` that's already an object is not the v1 wrapper
+        assert_eq!(ApiVersion::detect(&format!(r#"{{"result": {}}}"#, DOCUMENT)), ApiVersion::V2);
+        assert_eq!(ApiVersion::detect("not json"), ApiVersion::V2);
+    }
+
+    #[test]
+    fn decodes_each_version() {
+        let v1 = ApiVersion::V1.decode(&wrapped()).unwrap();
+        assert!(v1.wrapped);
+        assert_eq!(v1.accounts[0].email, "alice@example.com");
+        assert_eq!(v1.models, ["gemini-3-flash"]);
+
+        let v2 = ApiVersion::V2.decode(DOCUMENT).unwrap();
+        assert!(!v2.wrapped);
+        assert_eq!(v2.accounts.len(), 1);
+    }
+
+    #[test]
+    fn auto_decodes_either_shape() {
+        assert!(ApiVersion::Auto.decode(&wrapped()).unwrap().wrapped);
+        assert!(!ApiVersion::Auto.decode(DOCUMENT).unwrap().wrapped);
+    }
+
+    #[test]
+    fn names_the_layer_that_failed() {
+        let error = ApiVersion::V1.decode(DOCUMENT).unwrap_err().to_string();
+        assert!(error.starts_with("Failed to parse v1 response"), "{}", error);
+
+        let error = ApiVersion::V1.decode(r#"{"result": "{\"accounts\": 1}"}"#).unwrap_err().to_string();
+        assert!(error.starts_with("Failed to parse inner JSON at accounts"), "{}", error);
+
+        let error = ApiVersion::V2.decode(&wrapped()).unwrap_err().to_string();
+        assert!(error.starts_with("Failed to parse JSON"), "{}", error);
+    }
+
+    #[test]
+    fn encodes_into_each_shape() {
+        let document = DOCUMENT.to_string();
+        assert_eq!(ApiVersion::V2.encode(document.clone()), document);
+        assert_eq!(ApiVersion::Auto.encode(document.clone()), document);
+
+        let encoded = ApiVersion::V1.encode(document.clone());
+        assert_eq!(ApiVersion::detect(&encoded), ApiVersion::V1);
+        let envelope: Envelope = serde_json::from_str(&encoded).unwrap();
+        assert_eq!(envelope.result, document);
+    }
+
+    #[test]
+    fn round_trips_through_the_wrapper() {
+        for version in [ApiVersion::V1, ApiVersion::V2, ApiVersion::Auto] {
+            let data = version.decode(&version.encode(DOCUMENT.to_string())).unwrap();
+            assert_eq!(data.accounts[0].email, "alice@example.com");
+            assert_eq!(data.wrapped, version == ApiVersion::V1);
+        }
+    }
+}
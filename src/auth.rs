@@ -0,0 +1,70 @@
+use anyhow::{bail, Context, Result};
+use clap::{Args as ClapArgs, Subcommand};
+use std::io::{self, BufRead, IsTerminal, Write};
+
+/// Keychain service name; the entry's user is the profile name
+const SERVICE: &str = "ag-quota";
+/// Entry used without `--profile`
+const DEFAULT_PROFILE: &str = "default";
+
+#[derive(ClapArgs, Debug)]
+pub struct AuthArgs {
+    #[command(subcommand)]
+    pub command: AuthCommand,
+
+    /// Keychain entry to use instead of --profile's, e.g. xmpp:bot@example.com for the [xmpp] password
+    #[arg(long, global = true)]
+    pub entry: Option<String>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum AuthCommand {
+    /// Store a bearer token for --profile (read from stdin)
+    Set,
+    /// Remove the stored token for --profile
+    Delete,
+}
+
+fn entry(profile: Option<&str>) -> Result<keyring::Entry> {
+    keyring::Entry::new(SERVICE, profile.unwrap_or(DEFAULT_PROFILE)).context("Failed to open the system keyring")
+}
+
+/// Token stored for `profile`; a missing entry or unavailable keychain just means none
+pub fn stored_token(profile: Option<&str>) -> Option<String> {
+    entry(profile).ok()?.get_password().ok()
+}
+
+/// Secret stored under an `--entry` name, such as `xmpp:<jid>`
+#[cfg_attr(not(feature = "xmpp"), allow(dead_code))]
+pub fn stored_secret(name: &str) -> Option<String> {
+    entry(Some(name)).ok()?.get_password().ok()
+}
+
+/// `auth` subcommand
+pub fn run(args: &AuthArgs, profile: Option<&str>) -> Result<()> {
+    let profile = args.entry.as_deref().or(profile);
+    let name = profile.unwrap_or(DEFAULT_PROFILE);
+    match args.command {
+        AuthCommand::Set => {
+            let stdin = io::stdin();
+            if stdin.is_terminal() {
+                eprint!("Token for profile '{}': ", name);
+                io::stderr().flush().ok();
+            }
+            let mut token = String::new();
+            stdin.lock().read_line(&mut token).context("Failed to read token")?;
+            let token = token.trim();
+            if token.is_empty() {
+                bail!("No token given");
+            }
+            entry(profile)?.set_password(token).context("Failed to store token in the system keyring")?;
+            eprintln!("Stored token for profile '{}'", name);
+        }
+        AuthCommand::Delete => match entry(profile)?.delete_credential() {
+            Ok(()) => eprintln!("Removed token for profile '{}'", name),
+            Err(keyring::Error::NoEntry) => eprintln!("No stored token for profile '{}'", name),
+            Err(e) => return Err(e).context("Failed to remove token from the system keyring"),
+        },
+    }
+    Ok(())
+}
@@ -0,0 +1,52 @@
+use crate::intern::Name;
+use crate::ApiResponse;
+use std::collections::HashMap;
+
+/// Fraction drops smaller than this are float noise, not a request
+const MIN_STEP: f64 = 1e-6;
+
+/// Infers "requests remaining" for quotas the proxy only reports as a fraction.
+///
+/// With a reported `limit` the estimate is `fraction * limit`. Otherwise the
+/// quota is assumed to move in fixed steps of one request, and the smallest
+/// drop seen between refreshes is taken as the size of one step.
+#[derive(Default)]
+pub struct CapacityEstimator {
+    /// Last seen fraction and smallest observed step per (account, model)
+    quotas: HashMap<(Name, Name), (f64, Option<f64>)>,
+}
+
+impl CapacityEstimator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Learns from `data` and fills `estimated_remaining` wherever the
+    /// proxy reported no count but one can be inferred
+    pub fn observe(&mut self, data: &mut ApiResponse) {
+        for account in &mut data.accounts {
+            let Some(ref mut limits) = account.limits else {
+                continue;
+            };
+            for (model, quota) in limits.iter_mut() {
+                let key = (account.email.clone(), model.clone());
+                let (last, step) = self.quotas.entry(key).or_insert((quota.remaining_fraction, None));
+                let drop = *last - quota.remaining_fraction;
+                // Increases are resets; they don't say anything about step size
+                if drop > MIN_STEP {
+                    *step = Some(step.map_or(drop, |s: f64| s.min(drop)));
+                }
+                *last = quota.remaining_fraction;
+
+                if quota.remaining.is_some() {
+                    continue;
+                }
+                quota.estimated_remaining = match (quota.limit, *step) {
+                    (Some(limit), _) => Some((quota.remaining_fraction * limit as f64).round() as u64),
+                    (None, Some(step)) => Some((quota.remaining_fraction / step).round() as u64),
+                    (None, None) => None,
+                };
+            }
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use crate::{intern::Name, get_account_status, Account, ApiResponse};
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Key for an account across refreshes: the same email behind two merged
+/// endpoints is two accounts
+fn account_id(account: &Account) -> String {
+    match account.origin {
+        Some(ref origin) => format!("{}/{}", origin, account.email),
+        None => account.email.to_string(),
+    }
+}
+
+/// Remembers the previous refresh so the tables can highlight what moved
+#[derive(Default)]
+pub struct ChangeMarker {
+    fractions: HashMap<(String, Name), f64>,
+    statuses: HashMap<String, &'static str>,
+}
+
+impl ChangeMarker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fills `changed_models` and `status_changed` of every account in `data`
+    /// by comparing with the previous refresh; the first one marks nothing
+    pub fn observe(&mut self, data: &mut ApiResponse) {
+        for account in &mut data.accounts {
+            let id = account_id(account);
+            let (status, _) = get_account_status(account);
+            account.status_changed = self
+                .statuses
+                .insert(id.clone(), status)
+                .map(|previous| previous != status)
+                .unwrap_or(false);
+
+            let Some(ref limits) = account.limits else {
+                continue;
+            };
+            let mut changed = HashSet::new();
+            for (model, quota) in limits {
+                let key = (id.clone(), model.clone());
+                if let Some(previous) = self.fractions.insert(key, quota.remaining_fraction) {
+                    if previous != quota.remaining_fraction {
+                        changed.insert(model.clone());
+                    }
+                }
+            }
+            account.changed_models = changed;
+        }
+    }
+}
+
+/// Tracks each account's lowest remaining fraction across the listed models,
+/// for the accounts table's Δ column
+pub struct QuotaDelta {
+    /// Compare with the oldest refresh within this long; the previous one if unset
+    window: Option<chrono::Duration>,
+    snapshots: VecDeque<(DateTime<Utc>, HashMap<String, f64>)>,
+}
+
+impl QuotaDelta {
+    pub fn new(window: Option<std::time::Duration>) -> Self {
+        QuotaDelta {
+            window: window.and_then(|w| chrono::Duration::from_std(w).ok()),
+            snapshots: VecDeque::new(),
+        }
+    }
+
+    /// Fills `quota_delta` of every account in `data` that the baseline
+    /// refresh also saw; the first refresh fills none
+    pub fn observe(&mut self, data: &mut ApiResponse, now: DateTime<Utc>) {
+        let current: HashMap<String, f64> = data
+            .accounts
+            .iter()
+            .filter_map(|account| {
+                let limits = account.limits.as_ref()?;
+                data.models
+                    .iter()
+                    .filter_map(|m| limits.get(m))
+                    .map(|q| q.remaining_fraction)
+                    .reduce(f64::min)
+                    .map(|min| (account_id(account), min))
+            })
+            .collect();
+
+        match self.window {
+            Some(window) => {
+                while self.snapshots.front().is_some_and(|(at, _)| *at < now - window) {
+                    self.snapshots.pop_front();
+                }
+            }
+            None => {
+                while self.snapshots.len() > 1 {
+                    self.snapshots.pop_front();
+                }
+            }
+        }
+        if let Some((_, baseline)) = self.snapshots.front() {
+            for account in &mut data.accounts {
+                let id = account_id(account);
+                account.quota_delta = match (current.get(&id), baseline.get(&id)) {
+                    (Some(latest), Some(then)) => Some(latest - then),
+                    _ => None,
+                };
+            }
+        }
+        self.snapshots.push_back((now, current));
+    }
+}
@@ -0,0 +1,100 @@
+use crate::{
+    apply_tag_filter, count_stats, endpoints::fetch_data, error, http_client, model_available, Args, RenderOptions,
+    Severity,
+};
+use anyhow::Result;
+use clap::Args as ClapArgs;
+use std::time::Duration;
+
+/// Nagios plugin exit statuses
+pub const EXIT_OK: i32 = 0;
+pub const EXIT_WARNING: i32 = 1;
+pub const EXIT_CRITICAL: i32 = 2;
+pub const EXIT_UNKNOWN: i32 = 3;
+
+#[derive(ClapArgs, Debug)]
+pub struct CheckArgs {
+    /// WARNING at or below this many available accounts [default: thresholds.available_warning]
+    #[arg(long, value_name = "N")]
+    pub warning: Option<usize>,
+
+    /// CRITICAL at or below this many available accounts [default: thresholds.available_critical]
+    #[arg(long, value_name = "N")]
+    pub critical: Option<usize>,
+
+    /// Count the accounts that can serve this model instead
+    #[arg(short, long)]
+    pub model: Option<String>,
+
+    /// UNKNOWN when the proxy hasn't answered after this long
+    #[arg(long, default_value = "10s", value_parser = humantime::parse_duration)]
+    pub timeout: Duration,
+}
+
+fn exit_code(severity: Severity) -> i32 {
+    match severity {
+        Severity::Ok => EXIT_OK,
+        Severity::Warning => EXIT_WARNING,
+        Severity::Critical => EXIT_CRITICAL,
+    }
+}
+
+/// `check` subcommand: one Nagios plugin line, such as
+/// `OK - 5 available | available=5;2:;1:;0;8 ...`, and its exit status. The
+/// thresholds become perfdata ranges, which alert below their lower bound.
+pub async fn run(args: &Args, opts: &RenderOptions, check_args: &CheckArgs) -> Result<i32> {
+    let client = http_client(args)?;
+    let fetched = tokio::time::timeout(check_args.timeout, fetch_data(&client, args, &opts.config, |_, _| Ok(()))).await;
+    let mut data = match fetched {
+        Ok(Ok(data)) => data,
+        Ok(Err(e)) => {
+            println!("UNKNOWN - {}", error::one_line(&e));
+            return Ok(EXIT_UNKNOWN);
+        }
+        Err(_) => {
+            println!("UNKNOWN - no answer from the proxy within {}", humantime::format_duration(check_args.timeout));
+            return Ok(EXIT_UNKNOWN);
+        }
+    };
+    apply_tag_filter(&mut data, opts);
+
+    let mut thresholds = opts.config.thresholds.clone();
+    thresholds.available_warning = check_args.warning.unwrap_or(thresholds.available_warning);
+    thresholds.available_critical = check_args.critical.unwrap_or(thresholds.available_critical);
+
+    let total = data.accounts.len();
+    let (available, rate_limited, invalid, disabled) = count_stats(&data.accounts);
+    let counted = match check_args.model {
+        Some(ref model) => data.accounts.iter().filter(|a| model_available(a, model)).count(),
+        None => available,
+    };
+    let severity = Severity::from_available(counted, &thresholds);
+
+    let mut text = match check_args.model {
+        Some(ref model) => format!("{} available for {}", counted, model),
+        None => format!("{} available", counted),
+    };
+    if check_args.model.is_none() {
+        for (n, what) in [(rate_limited, "rate-limited"), (invalid, "invalid"), (disabled, "disabled")] {
+            if n > 0 {
+                text.push_str(&format!(", {} {}", n, what));
+            }
+        }
+    }
+    // `N:` alerts below N, so at or below the threshold
+    let perfdata = format!(
+        "available={};{}:;{}:;0;{} rate_limited={};;;0;{} invalid={};;;0;{} disabled={};;;0;{}",
+        counted,
+        thresholds.available_warning + 1,
+        thresholds.available_critical + 1,
+        total,
+        rate_limited,
+        total,
+        invalid,
+        total,
+        disabled,
+        total
+    );
+    println!("{} - {} | {}", severity.label(), text, perfdata);
+    Ok(exit_code(severity))
+}
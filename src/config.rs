@@ -0,0 +1,526 @@
+use crate::{api::ApiVersion, expr::Expr, intern::Name, notify::Level};
+use anyhow::{Context, Result};
+use chrono::NaiveTime;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Settings loaded from `config.toml`; every section is optional
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub models: ModelsConfig,
+    /// Tags per account, keyed by full email or the part before `@`
+    pub tags: HashMap<String, Vec<String>>,
+    /// Remaining fraction each account should keep until `thresholds.budget_until`,
+    /// keyed like `tags`
+    pub budgets: HashMap<String, f64>,
+    pub thresholds: Thresholds,
+    /// Named settings selected with `--profile` or `AG_PROFILE`
+    #[serde(rename = "profile")]
+    pub profiles: HashMap<String, Profile>,
+    pub proxy: ProxyEndpoints,
+    /// Proxies fetched when neither `--url` nor the profile names one
+    #[serde(rename = "endpoint")]
+    pub endpoints: Vec<Endpoint>,
+    /// Extra accounts-table columns
+    #[serde(rename = "column")]
+    pub columns: Vec<Column>,
+    pub history: HistoryConfig,
+    pub forecast: ForecastConfig,
+    pub notify: NotifyConfig,
+    pub telegram: Option<TelegramConfig>,
+    pub gotify: Option<GotifyConfig>,
+    pub pushover: Option<PushoverConfig>,
+    pub xmpp: Option<XmppConfig>,
+    pub status: StatusLabels,
+}
+
+/// `[status]`: what tables and templates show for each account status in
+/// place of its name, e.g. `limited = "⏳"`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StatusLabels {
+    pub ok: Option<String>,
+    pub limited: Option<String>,
+    pub invalid: Option<String>,
+    pub disabled: Option<String>,
+    /// Credentials past their expiry
+    pub expired: Option<String>,
+}
+
+impl StatusLabels {
+    /// The label for `status`, or the status itself when none is configured
+    pub fn label<'a>(&'a self, status: &'a str) -> &'a str {
+        let label = match status {
+            "ok" => self.ok.as_deref(),
+            "limited" => self.limited.as_deref(),
+            "invalid" => self.invalid.as_deref(),
+            "disabled" => self.disabled.as_deref(),
+            "expired" => self.expired.as_deref(),
+            _ => None,
+        };
+        label.unwrap_or(status)
+    }
+}
+
+/// `[history]`: quota samples watch mode keeps in `history.jsonl`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HistoryConfig {
+    /// Samples older than this many days are dropped
+    pub retention_days: u64,
+    pub backend: HistoryBackend,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        HistoryConfig {
+            retention_days: 30,
+            backend: HistoryBackend::Jsonl,
+        }
+    }
+}
+
+/// Where the history is stored. There is only the flat file, which needs
+/// no database library, so `jsonl` is both the default and the one choice.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HistoryBackend {
+    /// One JSON sample per line in `history.jsonl`
+    #[default]
+    Jsonl,
+}
+
+/// `[forecast]`: exhaustion forecasts in the watch header
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ForecastConfig {
+    /// Days of history the usual hourly consumption is learned from
+    pub days: u64,
+    /// Alert with a red line and the terminal bell when a forecast exhaustion is
+    /// this many minutes away or less (0 disables)
+    pub alert_within_minutes: u64,
+}
+
+impl Default for ForecastConfig {
+    fn default() -> Self {
+        ForecastConfig {
+            days: 7,
+            alert_within_minutes: 0,
+        }
+    }
+}
+
+/// `[notify]`: chat notifications from watch mode
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NotifyConfig {
+    /// Local time of day the daily summary is sent, e.g. `"09:00"`
+    pub summary_at: NaiveTime,
+}
+
+impl Default for NotifyConfig {
+    fn default() -> Self {
+        NotifyConfig {
+            summary_at: NaiveTime::from_hms_opt(9, 0, 0).expect("valid time"),
+        }
+    }
+}
+
+/// `[telegram]`: a bot that watch mode sends state changes through, and
+/// that can answer `/status` in its chat
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TelegramConfig {
+    /// From @BotFather, e.g. `"123456:ABC-DEF..."`
+    pub bot_token: String,
+    /// The chat messages go to, and the only one whose commands are answered:
+    /// a numeric id or a `"@channelname"`
+    pub chat_id: ChatId,
+    /// Answer `/status` with the summary
+    #[serde(default)]
+    pub commands: bool,
+    /// Bot API server, for a self-hosted one
+    #[serde(default = "default_telegram_api")]
+    pub api_url: String,
+}
+
+fn default_telegram_api() -> String {
+    "https://api.telegram.org".to_string()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum ChatId {
+    Id(i64),
+    Name(String),
+}
+
+/// `[gotify]`: a Gotify server that watch mode pushes state changes to,
+/// with the worst change's level as the priority
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GotifyConfig {
+    /// e.g. `"https://gotify.example.com"`
+    pub url: String,
+    /// The application token
+    pub token: String,
+    /// Only changes to states at least this bad: `good` (all), `warning` or `danger`
+    #[serde(default)]
+    pub min_level: Level,
+}
+
+/// `[pushover]`: Pushover delivery of watch mode's state changes
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PushoverConfig {
+    /// The application's API token
+    pub token: String,
+    /// User or group key of the recipients
+    pub user: String,
+    #[serde(default)]
+    pub min_level: Level,
+    /// Send `danger` changes at emergency priority, repeated every
+    /// `retry_secs` until acknowledged or `expire_secs` pass
+    #[serde(default)]
+    pub emergency: bool,
+    #[serde(default = "default_pushover_retry")]
+    pub retry_secs: u64,
+    #[serde(default = "default_pushover_expire")]
+    pub expire_secs: u64,
+    #[serde(default = "default_pushover_api")]
+    pub api_url: String,
+}
+
+fn default_pushover_retry() -> u64 {
+    60
+}
+
+fn default_pushover_expire() -> u64 {
+    3600
+}
+
+fn default_pushover_api() -> String {
+    "https://api.pushover.net".to_string()
+}
+
+/// `[xmpp]`: a Jabber account that watch mode sends state changes from,
+/// with the `xmpp` feature
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "xmpp"), allow(dead_code))]
+pub struct XmppConfig {
+    /// The sending account, e.g. `"ag-quota@example.com"`
+    pub jid: String,
+    /// Its password; without one, the `keyring` feature looks up the entry
+    /// `xmpp:<jid>`
+    pub password: Option<String>,
+    /// Who gets the messages
+    pub recipient: String,
+    #[serde(default)]
+    pub min_level: Level,
+}
+
+/// `[proxy]`: paths of the proxy's own endpoints, relative to the `--url` host
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProxyEndpoints {
+    pub health_path: String,
+    pub version_path: String,
+    /// Management API for `accounts add` (POST), `accounts remove` (DELETE `<path>/<email>`)
+    /// and `accounts refresh` (POST `<path>/refresh` or `<path>/<email>/refresh`); its GET
+    /// lists the accounts for `--fetch per-account`
+    pub accounts_path: String,
+    /// One account's limits for `--fetch per-account`, `{id}` standing for its id or email
+    pub account_limits_path: String,
+    /// Response shape of the limits endpoint; `--api-version` overrides it
+    pub api_version: ApiVersion,
+    /// Hold responses to the schema exactly; `--strict` turns it on
+    pub strict: bool,
+}
+
+impl Default for ProxyEndpoints {
+    fn default() -> Self {
+        ProxyEndpoints {
+            health_path: "/health".to_string(),
+            version_path: "/version".to_string(),
+            accounts_path: "/accounts".to_string(),
+            account_limits_path: "/account/{id}/limits".to_string(),
+            api_version: ApiVersion::Auto,
+            strict: false,
+        }
+    }
+}
+
+/// `[[endpoint]]`: a proxy to fetch and the name it goes by in the Origin column
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Endpoint {
+    pub name: String,
+    pub url: String,
+}
+
+/// `[[column]]`: an accounts-table column computed per account, e.g.
+/// `expr = "quota('gemini-3-flash') * 100"`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Column {
+    pub name: String,
+    #[serde(deserialize_with = "crate::expr::deserialize_account")]
+    pub expr: Expr,
+}
+
+/// `[profile.NAME]`: per-environment defaults; command-line flags still win
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    pub url: Option<String>,
+    /// Several proxies merged into one view, instead of `url`
+    pub urls: Vec<String>,
+    /// Sent as `Authorization: Bearer <token>`
+    pub token: Option<String>,
+    /// Default `--tag` filter
+    pub tags: Vec<String>,
+}
+
+/// Levels that drive colors and the header severity badge
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Thresholds {
+    /// Quota cells below this remaining fraction are shown as a warning
+    pub quota_warning: f64,
+    /// Header severity is WARNING at or below this many available accounts
+    pub available_warning: usize,
+    /// Header severity is CRITICAL at or below this many available accounts
+    pub available_critical: usize,
+    /// Warn in the header when the proxy's clock differs from ours by more than this many seconds
+    pub clock_skew: u64,
+    /// Warn about account credentials expiring within this many hours
+    pub credentials_warning_hours: u64,
+    /// Local time of day until which `budgets` apply, e.g. `"18:00"`
+    pub budget_until: NaiveTime,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Thresholds {
+            quota_warning: 0.3,
+            available_warning: 1,
+            available_critical: 0,
+            clock_skew: 30,
+            credentials_warning_hours: 72,
+            budget_until: NaiveTime::from_hms_opt(18, 0, 0).expect("valid time"),
+        }
+    }
+}
+
+impl Config {
+    /// The profile called `name`, or an error listing the defined ones
+    pub fn profile(&self, name: &str) -> Result<&Profile> {
+        self.profiles.get(name).ok_or_else(|| {
+            let mut known: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+            known.sort_unstable();
+            if known.is_empty() {
+                anyhow::anyhow!("Unknown profile '{}': the config defines no [profile.*] sections", name)
+            } else {
+                anyhow::anyhow!("Unknown profile '{}' (defined: {})", name, known.join(", "))
+            }
+        })
+    }
+
+    /// The budget for `email`, matching either the full address or its local part
+    pub fn budget_for(&self, email: &str) -> Option<f64> {
+        let short = email.split('@').next().unwrap_or(email);
+        self.budgets.get(email).or_else(|| self.budgets.get(short)).copied()
+    }
+
+    /// Tags assigned to `email`, matching either the full address or its local part
+    pub fn tags_for<'a>(&'a self, email: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        let short = email.split('@').next().unwrap_or(email);
+        self.tags
+            .iter()
+            .filter(move |(key, _)| key.as_str() == email || key.as_str() == short)
+            .flat_map(|(_, tags)| tags.iter().map(String::as_str))
+    }
+}
+
+/// Client-side adjustments to the server's `models` list
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ModelsConfig {
+    /// Models to show even if the server omits them from its list
+    pub add: Vec<String>,
+    /// Glob patterns for models to drop entirely: from the list, every
+    /// account's limits and rate limits, and so from status and alerts
+    pub ignore: Vec<String>,
+    /// Also show any model that appears in an account's limits
+    pub include_unlisted: bool,
+    /// Glob patterns giving the display order; unmatched models follow in the server's order
+    pub order: Vec<String>,
+    /// Glob patterns for the models that matter: shown first and in bold, and
+    /// under the `primary` status policy the only ones whose rate limits make
+    /// an account `limited`
+    pub primary: Vec<String>,
+    /// When an account counts as limited; `--status-policy` overrides it
+    pub status_policy: StatusPolicy,
+    /// Model classes summarized in the header, in display order
+    #[serde(rename = "group")]
+    pub groups: Vec<ModelGroup>,
+}
+
+/// A named class of models, e.g. `pro` matching `["*-pro*", "claude-*"]`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ModelGroup {
+    pub name: String,
+    /// Glob patterns (`*` matches any run of characters)
+    #[serde(rename = "match")]
+    pub patterns: Vec<String>,
+}
+
+impl ModelGroup {
+    pub fn contains(&self, model: &str) -> bool {
+        self.patterns.iter().any(|p| glob_match(p, model))
+    }
+}
+
+/// Matches `text` against a pattern where `*` stands for any run of characters
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or("");
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts: Vec<&str> = parts.collect();
+    let Some(last) = parts.pop() else {
+        // No `*` at all: the prefix must be the whole text
+        return rest.is_empty();
+    };
+    for part in parts {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+impl Config {
+    /// Loads `path`, or the default location when `None`. A missing default
+    /// file yields the default config; a missing explicit file is an error.
+    pub fn load(path: Option<&Path>) -> Result<Config> {
+        let (path, explicit) = match path {
+            Some(p) => (p.to_path_buf(), true),
+            None => match default_path() {
+                Some(p) => (p, false),
+                None => return Ok(Config::default()),
+            },
+        };
+
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if !explicit && e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(Config::default())
+            }
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to read config {}", path.display()))
+            }
+        };
+
+        toml::from_str(&text).with_context(|| format!("Invalid config {}", path.display()))
+    }
+}
+
+/// `$XDG_CONFIG_HOME/ag-quota/config.toml`, falling back to `~/.config`
+pub fn default_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("ag-quota").join("config.toml"))
+}
+
+/// Local state (pick history and similar): `$XDG_DATA_HOME/ag-quota`,
+/// falling back to `~/.local/share/ag-quota`
+pub fn data_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("share"))
+        })
+        .or_else(|| std::env::var_os("LOCALAPPDATA").map(PathBuf::from))
+        .map(|dir| dir.join("ag-quota"))
+}
+
+fn config_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))
+}
+
+impl ModelsConfig {
+    /// Applies `add`, `include_unlisted` and `ignore` to the server's model list,
+    /// keeping the server's order and appending extra models after it.
+    pub fn apply<'a>(&self, models: &mut Vec<Name>, limits: impl Iterator<Item = &'a Name>) {
+        for model in &self.add {
+            if !models.iter().any(|m| m == model) {
+                models.push(Name::new(model));
+            }
+        }
+
+        if self.include_unlisted {
+            let mut unlisted: Vec<&Name> = limits.filter(|m| !models.contains(m)).collect();
+            unlisted.sort();
+            unlisted.dedup();
+            models.extend(unlisted.into_iter().cloned());
+        }
+
+        models.retain(|m| !self.is_ignored(m));
+        self.sort(models);
+    }
+
+    /// Primary models first, then by the first `order` pattern each matches
+    pub fn sort(&self, models: &mut [Name]) {
+        let rank = |model: &str| self.order.iter().position(|p| glob_match(p, model)).unwrap_or(self.order.len());
+        models.sort_by_key(|m| (!self.is_primary(m), rank(m)));
+    }
+
+    pub fn is_ignored(&self, model: &str) -> bool {
+        self.ignore.iter().any(|p| glob_match(p, model))
+    }
+
+    pub fn is_primary(&self, model: &str) -> bool {
+        self.primary.iter().any(|p| glob_match(p, model))
+    }
+
+    /// Whether an account with these rate limits, and quotas on `models`,
+    /// counts as limited under the status policy
+    pub fn is_limited<'a>(&self, rate_limits: impl Iterator<Item = (&'a Name, bool)>, models: &[&Name]) -> bool {
+        let limited: Vec<&Name> = rate_limits.filter(|(_, limited)| *limited).map(|(model, _)| model).collect();
+        match self.status_policy {
+            StatusPolicy::Any => !limited.is_empty(),
+            StatusPolicy::All if models.is_empty() => !limited.is_empty(),
+            StatusPolicy::All => models.iter().all(|m| limited.contains(m)),
+            StatusPolicy::Primary if self.primary.is_empty() => !limited.is_empty(),
+            StatusPolicy::Primary => limited.iter().any(|m| self.is_primary(m)),
+        }
+    }
+}
+
+/// When a rate-limited model makes its account `limited` in the status
+/// column and the header counts
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StatusPolicy {
+    /// Any model is rate-limited
+    Any,
+    /// Every model the account has a quota for is rate-limited
+    All,
+    /// Any of `models.primary` is rate-limited, or any model when none are set
+    #[default]
+    Primary,
+}
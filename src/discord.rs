@@ -0,0 +1,77 @@
+//! Discord webhook messages for the notifier: one embed per event, colored
+//! by how bad it is, with the account, model and reset time as fields
+
+use crate::{config::StatusLabels, count_stats, events::Event, get_short_email, locale::Percent, notify::Level, parse_reset, ApiResponse};
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+
+/// Discord rejects messages with more embeds than this
+pub const MAX_EMBEDS: usize = 10;
+
+fn color(level: Level) -> u32 {
+    match level {
+        Level::Good => 0x2ecc71,
+        Level::Warning => 0xf1c40f,
+        Level::Danger => 0xe74c3c,
+    }
+}
+
+/// `text` with the characters Discord markdown acts on escaped
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '*' | '_' | '~' | '`' | '|' | '\\') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+fn field(name: &str, value: String) -> Value {
+    json!({ "name": name, "value": value, "inline": true })
+}
+
+/// An embed per event, at most [`MAX_EMBEDS`] of them; reset times use
+/// Discord's timestamp markup, which each reader sees in their own zone
+pub fn events_message(events: &[Event], data: &ApiResponse, labels: &StatusLabels, now: DateTime<Utc>) -> Value {
+    let (available, _, _, _) = count_stats(&data.accounts);
+    let embeds: Vec<Value> = events
+        .iter()
+        .take(MAX_EMBEDS)
+        .map(|event| {
+            let name = escape(get_short_email(&event.account));
+            let (from, to) = (escape(labels.label(&event.from)), escape(labels.label(&event.to)));
+            let title = match event.model {
+                Some(ref model) => format!("{} {}: {} → {}", name, escape(model), from, to),
+                None => format!("{}: {} → {}", name, from, to),
+            };
+            let mut fields = vec![field("Account", escape(&event.account))];
+            if let Some(ref model) = event.model {
+                fields.push(field("Model", escape(model)));
+                let quota = data
+                    .accounts
+                    .iter()
+                    .find(|a| a.email == event.account)
+                    .and_then(|a| a.limits.as_ref()?.get(model.as_str()));
+                if let Some(quota) = quota {
+                    fields.push(field("Remaining", Percent(quota.remaining_fraction).to_string()));
+                    if let Some(at) = parse_reset(quota) {
+                        fields.push(field("Resets", format!("<t:{0}:R> (<t:{0}:t>)", at.timestamp())));
+                    }
+                }
+            }
+            json!({
+                "title": title,
+                "color": color(Level::of(&event.to)),
+                "fields": fields,
+                "timestamp": now.to_rfc3339(),
+            })
+        })
+        .collect();
+
+    json!({
+        "content": format!("AG quota: {}/{} accounts available", available, data.accounts.len()),
+        "embeds": embeds,
+    })
+}
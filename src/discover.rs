@@ -0,0 +1,49 @@
+use crate::{config::Config, parse_response};
+use anyhow::{bail, Result};
+use std::time::Duration;
+
+/// Ports Antigravity proxies commonly listen on, the usual one first
+const PORTS: [u16; 5] = [8040, 8080, 3000, 8000, 8787];
+const PATHS: [&str; 2] = ["/account-limits", "/api/account-limits"];
+
+/// A local port that isn't listening refuses at once; this only bounds hung ones
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+fn candidates() -> Vec<String> {
+    PORTS
+        .iter()
+        .flat_map(|port| PATHS.iter().map(move |path| format!("http://localhost:{}{}", port, path)))
+        .collect()
+}
+
+/// Whether `url` answers with something that parses as account limits
+async fn probe(client: reqwest::Client, url: String, config: Config) -> bool {
+    let response = match client.get(&url).timeout(PROBE_TIMEOUT).send().await {
+        Ok(response) if response.status().is_success() => response,
+        _ => return false,
+    };
+    match response.text().await {
+        Ok(text) => parse_response(&text, &config).is_ok(),
+        Err(_) => false,
+    }
+}
+
+/// Probes the common local ports and paths at once and returns the first
+/// candidate, in the order above, that serves account limits
+pub async fn discover(client: &reqwest::Client, config: &Config) -> Result<String> {
+    let candidates = candidates();
+    let probes: Vec<_> = candidates
+        .iter()
+        .map(|url| tokio::spawn(probe(client.clone(), url.clone(), config.clone())))
+        .collect();
+    for (url, probe) in candidates.iter().zip(probes) {
+        if probe.await.unwrap_or(false) {
+            return Ok(url.clone());
+        }
+    }
+    bail!(
+        "No Antigravity proxy found on localhost (tried ports {} with {}); pass --url",
+        PORTS.map(|p| p.to_string()).join(", "),
+        PATHS.join(" and ")
+    )
+}
@@ -0,0 +1,226 @@
+use crate::{intern::Name, config::Config, fanout::FetchPlan, parse_response, Account, ApiResponse, Args, GREEN, RED, RESET};
+use anyhow::{anyhow, Result};
+use clap::ValueEnum;
+use std::{
+    collections::HashMap,
+    fmt::{self, Write as _},
+    time::{Duration, Instant},
+};
+
+/// How one of several `--url`s fared on the last fetch
+#[derive(Debug, Clone)]
+pub struct EndpointStatus {
+    pub label: String,
+    pub latency: Duration,
+    /// Accounts it returned, or why it returned none
+    pub outcome: Result<usize, String>,
+}
+
+/// What to do with an email that several merged endpoints report
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DedupePolicy {
+    /// One row per endpoint
+    #[default]
+    Separate,
+    /// One row with the worst of each: lowest quota, limited or invalid anywhere
+    Min,
+    /// One row with the best of each: highest quota, usable anywhere
+    Max,
+}
+
+/// Folds `other`, the same email from another endpoint, into `kept`
+fn combine(kept: &mut Account, other: Account, policy: DedupePolicy) {
+    let worst = policy == DedupePolicy::Min;
+    let (enabled, other_enabled) = (kept.enabled.unwrap_or(true), other.enabled.unwrap_or(true));
+    let (invalid, other_invalid) = (kept.is_invalid.unwrap_or(false), other.is_invalid.unwrap_or(false));
+    kept.enabled = Some(if worst { enabled && other_enabled } else { enabled || other_enabled });
+    kept.is_invalid = Some(if worst { invalid || other_invalid } else { invalid && other_invalid });
+    kept.last_used = kept.last_used.max(other.last_used);
+    // Whichever credentials run out first matter either way
+    if let Some(at) = other.credentials_expiry() {
+        if kept.credentials_expiry().map_or(true, |current| at < current) {
+            kept.credentials_expire_at = other.credentials_expire_at;
+        }
+    }
+
+    if let Some(limits) = other.limits {
+        let kept_limits = kept.limits.get_or_insert_with(HashMap::new);
+        for (model, quota) in limits {
+            match kept_limits.get(&model) {
+                Some(current)
+                    if (worst && current.remaining_fraction <= quota.remaining_fraction)
+                        || (!worst && current.remaining_fraction >= quota.remaining_fraction) => {}
+                _ => {
+                    kept_limits.insert(model, quota);
+                }
+            }
+        }
+    }
+    if let Some(rate_limits) = other.model_rate_limits {
+        let kept_limits = kept.model_rate_limits.get_or_insert_with(HashMap::new);
+        for (model, limit) in rate_limits {
+            match kept_limits.get(&model) {
+                // Keep a limit for the worst case, a clear one for the best
+                Some(current) if current.is_rate_limited == worst => {}
+                _ => {
+                    kept_limits.insert(model, limit);
+                }
+            }
+        }
+    }
+
+    if let Some(origin) = other.origin {
+        let origins = kept.origin.get_or_insert_with(String::new);
+        if !origins.split(", ").any(|o| o == origin) {
+            if !origins.is_empty() {
+                origins.push_str(", ");
+            }
+            origins.push_str(&origin);
+        }
+    }
+}
+
+/// Collapses accounts with the same email into one row under `policy`,
+/// keeping the order in which emails first appear
+pub fn dedupe(accounts: Vec<Account>, policy: DedupePolicy) -> Vec<Account> {
+    if policy == DedupePolicy::Separate {
+        return accounts;
+    }
+    let mut kept: Vec<Account> = Vec::with_capacity(accounts.len());
+    let mut index: HashMap<Name, usize> = HashMap::new();
+    for account in accounts {
+        match index.get(&account.email) {
+            Some(&i) => combine(&mut kept[i], account, policy),
+            None => {
+                index.insert(account.email.clone(), kept.len());
+                kept.push(account);
+            }
+        }
+    }
+    kept
+}
+
+/// The `[[endpoint]]` name configured for `url`, else its `host:port`,
+/// which is all that tells endpoints apart on one line
+pub fn label(config: &Config, url: &str) -> String {
+    if let Some(endpoint) = config.endpoints.iter().find(|e| e.url == url) {
+        return endpoint.name.clone();
+    }
+    match reqwest::Url::parse(url) {
+        Ok(parsed) => match (parsed.host_str(), parsed.port()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_string(),
+            _ => url.to_string(),
+        },
+        Err(_) => url.to_string(),
+    }
+}
+
+/// Fetches every `--url` at once and merges the responses that parsed, in
+/// URL order. Each body is handed to `on_body` with its round trip before
+/// parsing. With several URLs the result is an error only when none answered,
+/// and `endpoints` says which did.
+pub async fn fetch_data(
+    client: &reqwest::Client,
+    args: &Args,
+    config: &Config,
+    mut on_body: impl FnMut(&str, Duration) -> Result<()>,
+) -> Result<ApiResponse> {
+    let tasks: Vec<_> = args
+        .urls
+        .iter()
+        .map(|url| {
+            let (client, url, plan) = (client.clone(), url.clone(), FetchPlan::new(args, config));
+            tokio::spawn(async move {
+                let started = Instant::now();
+                let result = plan.fetch(&client, &url).await;
+                (result, started.elapsed())
+            })
+        })
+        .collect();
+
+    let mut responses = Vec::new();
+    let mut endpoints = Vec::new();
+    for (label, task) in args.labels.iter().zip(tasks) {
+        let (result, latency) = task.await.map_err(|e| anyhow!("Fetch task failed: {}", e))?;
+        let parsed = match result {
+            Ok(text) => {
+                on_body(&text, latency)?;
+                parse_response(&text, config)
+            }
+            Err(e) => Err(e),
+        };
+        if args.urls.len() == 1 {
+            return Ok(parsed?);
+        }
+        endpoints.push(EndpointStatus {
+            label: label.clone(),
+            latency,
+            outcome: match parsed {
+                Ok(ref data) => Ok(data.accounts.len()),
+                Err(ref e) => Err(e.to_string()),
+            },
+        });
+        if let Ok(mut data) = parsed {
+            for account in &mut data.accounts {
+                account.origin = Some(label.clone());
+            }
+            responses.push(data);
+        }
+    }
+
+    let mut responses = responses.into_iter();
+    let Some(mut merged) = responses.next() else {
+        let reasons: Vec<String> = endpoints
+            .iter()
+            .filter_map(|e| e.outcome.as_ref().err().map(|why| format!("{}: {}", e.label, why)))
+            .collect();
+        return Err(anyhow!("All {} endpoints failed ({})", endpoints.len(), reasons.join("; ")));
+    };
+    for data in responses {
+        for model in data.models {
+            if !merged.models.contains(&model) {
+                merged.models.push(model);
+            }
+        }
+        merged.accounts.extend(data.accounts);
+        merged.wrapped |= data.wrapped;
+    }
+    config.models.sort(&mut merged.models);
+    merged.accounts = dedupe(std::mem::take(&mut merged.accounts), args.dedupe);
+    // Merged rows carry rate limits from several endpoints
+    if args.dedupe != DedupePolicy::Separate {
+        for account in &mut merged.accounts {
+            account.update_limited(&config.models);
+        }
+    }
+    merged.endpoints = endpoints;
+    Ok(merged)
+}
+
+/// Header line listing each endpoint when there are several:
+/// `Endpoints: localhost:8040 4 accounts, box:8040 failed: Failed to connect to server`
+pub fn render_endpoints(out: &mut String, endpoints: &[EndpointStatus]) -> fmt::Result {
+    if endpoints.is_empty() {
+        return Ok(());
+    }
+    out.push_str("Endpoints:");
+    for (i, endpoint) in endpoints.iter().enumerate() {
+        let sep = if i == 0 { " " } else { ", " };
+        match endpoint.outcome {
+            Ok(accounts) => write!(
+                out,
+                "{}{} {}{} account{}{} ({}ms)",
+                sep,
+                endpoint.label,
+                GREEN,
+                accounts,
+                if accounts == 1 { "" } else { "s" },
+                RESET,
+                endpoint.latency.as_millis()
+            )?,
+            Err(ref why) => write!(out, "{}{} {}failed: {}{}", sep, endpoint.label, RED, why, RESET)?,
+        }
+    }
+    writeln!(out)
+}
@@ -0,0 +1,194 @@
+use reqwest::StatusCode;
+use std::{error::Error as _, fmt, io};
+
+/// Why a fetch of the account limits failed, told apart so the error can
+/// come with a hint on what to check
+#[derive(Debug)]
+pub enum FetchError {
+    /// Nothing listens at the host and port
+    ConnectionRefused(reqwest::Error),
+    /// The host name doesn't resolve
+    Dns(reqwest::Error),
+    Timeout(reqwest::Error),
+    /// The HTTPS handshake failed, usually over the certificate
+    Tls(reqwest::Error),
+    /// Any other failure to reach the server
+    Connect(reqwest::Error),
+    /// A non-2xx response
+    Status(StatusCode),
+    /// The body went over `--max-body-size`
+    TooLarge { limit: u64 },
+    /// The body couldn't be read in full, or wasn't UTF-8
+    Body(String),
+    /// The body isn't the JSON the proxy should send; the message names the
+    /// path, and `excerpt` is how the body began for `--debug`
+    Shape { message: String, excerpt: String },
+}
+
+/// How much of a body that didn't parse `--debug` shows
+const EXCERPT_BYTES: usize = 500;
+
+/// What a TLS failure's causes mention; rustls reports a plain-HTTP answer
+/// to its hello as a corrupt message
+const TLS_WORDS: [&str; 4] = ["certificate", "tls", "handshake", "corrupt message"];
+
+impl FetchError {
+    /// A parse failure of `body`, keeping its start for `--debug`
+    pub fn shape(message: String, body: &str) -> FetchError {
+        let mut end = body.len().min(EXCERPT_BYTES);
+        while !body.is_char_boundary(end) {
+            end -= 1;
+        }
+        FetchError::Shape {
+            message,
+            excerpt: body[..end].to_string(),
+        }
+    }
+
+    /// Sorts a failed request by what its source chain says went wrong
+    pub fn from_request(error: reqwest::Error) -> FetchError {
+        if error.is_timeout() {
+            return FetchError::Timeout(error);
+        }
+        let mut refused = false;
+        let mut text = String::new();
+        let mut source = error.source();
+        while let Some(cause) = source {
+            if let Some(io) = cause.downcast_ref::<io::Error>() {
+                refused |= io.kind() == io::ErrorKind::ConnectionRefused;
+            }
+            text.push_str(&cause.to_string().to_lowercase());
+            text.push('\n');
+            source = cause.source();
+        }
+        if refused || text.contains("connection refused") {
+            FetchError::ConnectionRefused(error)
+        } else if text.contains("dns error") || text.contains("failed to lookup address") {
+            FetchError::Dns(error)
+        } else if TLS_WORDS.iter().any(|word| text.contains(word)) {
+            FetchError::Tls(error)
+        } else {
+            FetchError::Connect(error)
+        }
+    }
+
+    /// Short name for the JSON error line, e.g. `connection_refused`
+    pub fn kind(&self) -> &'static str {
+        match self {
+            FetchError::ConnectionRefused(_) => "connection_refused",
+            FetchError::Dns(_) => "dns",
+            FetchError::Timeout(_) => "timeout",
+            FetchError::Tls(_) => "tls",
+            FetchError::Connect(_) => "connect",
+            FetchError::Status(status) if matches!(status.as_u16(), 401 | 403) => "unauthorized",
+            FetchError::Status(StatusCode::NOT_FOUND) => "not_found",
+            FetchError::Status(status) if status.is_server_error() => "server_error",
+            FetchError::Status(_) => "http_status",
+            FetchError::TooLarge { .. } => "too_large",
+            FetchError::Body(_) => "body",
+            FetchError::Shape { .. } => "shape",
+        }
+    }
+
+    /// What to check next, given the `--url` that was fetched
+    pub fn hint(&self, url: &str) -> String {
+        match self {
+            FetchError::ConnectionRefused(_) => format!(
+                "Nothing is listening at {}. Is the proxy running, or on another port? `--discover` probes the usual ones.",
+                url
+            ),
+            FetchError::Dns(_) => format!("The host in {} doesn't resolve; check --url for typos.", url),
+            FetchError::Timeout(_) => {
+                "The proxy didn't answer within 10s. It may be overloaded, or a firewall drops the traffic.".to_string()
+            }
+            FetchError::Tls(_) => {
+                "Check the proxy's certificate, or use http:// if it doesn't speak TLS.".to_string()
+            }
+            FetchError::Connect(_) => format!("Make sure the proxy is running at {}", url),
+            FetchError::Status(status) if matches!(status.as_u16(), 401 | 403) => {
+                "The proxy refused the credentials; check --token or the profile's token.".to_string()
+            }
+            FetchError::Status(StatusCode::NOT_FOUND) => format!(
+                "The proxy answered, but not at this path; it usually serves /account-limits ({}).",
+                url
+            ),
+            FetchError::Status(status) if status.is_server_error() => {
+                "The proxy is up but failing; its own logs should say why.".to_string()
+            }
+            FetchError::Status(_) => format!("Make sure {} is the proxy's account-limits endpoint.", url),
+            FetchError::TooLarge { .. } => format!(
+                "Is {} the proxy's account-limits endpoint? If so, raise --max-body-size.",
+                url
+            ),
+            FetchError::Body(_) => "The connection dropped mid-response; it usually passes on the next refresh.".to_string(),
+            FetchError::Shape { .. } => format!(
+                "{} doesn't look like the proxy's account-limits endpoint; `ag-quota schema` prints what it should send.",
+                url
+            ),
+        }
+    }
+}
+
+impl fmt::Display for FetchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FetchError::ConnectionRefused(_) => f.write_str("Connection refused"),
+            FetchError::Dns(_) => f.write_str("Could not resolve the host name"),
+            FetchError::Timeout(_) => f.write_str("Request timed out"),
+            FetchError::Tls(_) => f.write_str("TLS handshake failed"),
+            FetchError::Connect(_) => f.write_str("Failed to connect to server"),
+            FetchError::Status(status) => write!(f, "Server returned error {}", status),
+            FetchError::TooLarge { limit } => write!(f, "Response is larger than {} bytes (--max-body-size)", limit),
+            FetchError::Body(message) | FetchError::Shape { message, .. } => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for FetchError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FetchError::ConnectionRefused(e)
+            | FetchError::Dns(e)
+            | FetchError::Timeout(e)
+            | FetchError::Tls(e)
+            | FetchError::Connect(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// `error` and its causes, outermost first. Wrappers such as reqwest's
+/// repeat their cause after a colon, so that part is cut from each.
+pub fn causes(error: &anyhow::Error) -> Vec<String> {
+    let texts: Vec<String> = error.chain().map(|cause| cause.to_string()).collect();
+    texts
+        .iter()
+        .enumerate()
+        .map(|(i, text)| match texts.get(i + 1) {
+            Some(next) => text
+                .strip_suffix(next.as_str())
+                .and_then(|rest| rest.strip_suffix(": "))
+                .unwrap_or(text)
+                .to_string(),
+            None => text.clone(),
+        })
+        .collect()
+}
+
+/// `error` with its causes on one line, e.g. for stderr
+pub fn one_line(error: &anyhow::Error) -> String {
+    causes(error).join(": ")
+}
+
+/// How the body began, when `error` is a response that didn't parse
+pub fn excerpt(error: &anyhow::Error) -> Option<&str> {
+    match error.downcast_ref::<FetchError>()? {
+        FetchError::Shape { excerpt, .. } => Some(excerpt),
+        _ => None,
+    }
+}
+
+/// The hint for `error` if it is a classified fetch failure
+pub fn hint(error: &anyhow::Error, url: &str) -> Option<String> {
+    error.downcast_ref::<FetchError>().map(|e| e.hint(url))
+}
@@ -0,0 +1,229 @@
+use crate::{
+    intern::Name,
+    config::{self, StatusLabels, Thresholds},
+    locale,
+    get_account_status, get_short_email, ApiResponse, BOLD, DIM, GREEN, RED, RESET, YELLOW,
+};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, Utc};
+use clap::Args as ClapArgs;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+#[derive(ClapArgs, Debug)]
+pub struct EventsArgs {
+    /// Only show events newer than this (e.g. 30m, 24h, 7d)
+    #[arg(long, default_value = "24h", value_parser = humantime::parse_duration)]
+    pub since: Duration,
+
+    /// Print the raw JSON lines instead of a table
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// One observed state transition, as stored in `events.jsonl`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Event {
+    pub timestamp: DateTime<Utc>,
+    pub account: String,
+    /// Set for quota threshold crossings, absent for account status changes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    pub from: String,
+    pub to: String,
+}
+
+pub fn log_path() -> Option<PathBuf> {
+    config::data_dir().map(|dir| dir.join("events.jsonl"))
+}
+
+/// Where a quota sits relative to the configured thresholds
+fn quota_level(fraction: f64, thresholds: &Thresholds) -> &'static str {
+    if fraction <= 0.0 {
+        "exhausted"
+    } else if fraction < thresholds.quota_warning {
+        "low"
+    } else {
+        "ok"
+    }
+}
+
+/// Detects transitions between consecutive refreshes and appends them to the event log
+pub struct EventTracker {
+    thresholds: Thresholds,
+    statuses: HashMap<Name, &'static str>,
+    levels: HashMap<(Name, Name), &'static str>,
+    file: Option<File>,
+}
+
+impl EventTracker {
+    /// Opens the event log for appending. Without a data dir, or when the log
+    /// can't be opened, events are only tracked in memory.
+    pub fn open(thresholds: &Thresholds) -> EventTracker {
+        let file = log_path().and_then(|path| match open_log(&path) {
+            Ok(file) => Some(file),
+            Err(e) => {
+                eprintln!("Warning: not logging events: {:#}", e);
+                None
+            }
+        });
+        EventTracker {
+            thresholds: thresholds.clone(),
+            statuses: HashMap::new(),
+            levels: HashMap::new(),
+            file,
+        }
+    }
+
+    /// Compares `data` with the previous refresh and logs every change. The
+    /// first refresh only establishes the baseline. When the log can't be
+    /// written, that is warned about once and events stay in memory.
+    pub fn observe(&mut self, data: &ApiResponse, now: DateTime<Utc>) -> Vec<Event> {
+        let mut events = Vec::new();
+
+        for account in &data.accounts {
+            let (status, _) = get_account_status(account);
+            if let Some(previous) = self.statuses.insert(account.email.clone(), status) {
+                if previous != status {
+                    events.push(Event {
+                        timestamp: now,
+                        account: account.email.to_string(),
+                        model: None,
+                        from: previous.to_string(),
+                        to: status.to_string(),
+                    });
+                }
+            }
+
+            let Some(ref limits) = account.limits else {
+                continue;
+            };
+            for model in &data.models {
+                let Some(quota) = limits.get(model) else {
+                    continue;
+                };
+                let level = quota_level(quota.remaining_fraction, &self.thresholds);
+                let key = (account.email.clone(), model.clone());
+                if let Some(previous) = self.levels.insert(key, level) {
+                    if previous != level {
+                        events.push(Event {
+                            timestamp: now,
+                            account: account.email.to_string(),
+                            model: Some(model.to_string()),
+                            from: previous.to_string(),
+                            to: level.to_string(),
+                        });
+                    }
+                }
+            }
+        }
+
+        if let Some(ref mut file) = self.file {
+            if let Err(e) = append(file, &events) {
+                eprintln!("Warning: not logging events: {:#}", e);
+                self.file = None;
+            }
+        }
+        events
+    }
+}
+
+fn open_log(path: &Path) -> Result<File> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).with_context(|| format!("Failed to create data dir {}", dir.display()))?;
+    }
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open event log {}", path.display()))
+}
+
+fn append(file: &mut File, events: &[Event]) -> Result<()> {
+    for event in events {
+        serde_json::to_writer(&mut *file, event).context("Failed to encode event")?;
+        writeln!(file).context("Failed to write event log")?;
+    }
+    file.flush().context("Failed to write event log")
+}
+
+/// Reads logged events newer than `since`, skipping lines that don't parse
+pub fn read_since(since: Duration) -> Result<Vec<Event>> {
+    let Some(path) = log_path() else {
+        return Ok(Vec::new());
+    };
+    let file = match File::open(&path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to open {}", path.display())),
+    };
+    let cutoff = chrono::Duration::from_std(since)
+        .ok()
+        .and_then(|d| Utc::now().checked_sub_signed(d))
+        .unwrap_or(DateTime::<Utc>::MIN_UTC);
+
+    let mut events = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let line = line.context("Failed to read event log")?;
+        if let Ok(event) = serde_json::from_str::<Event>(&line) {
+            if event.timestamp >= cutoff {
+                events.push(event);
+            }
+        }
+    }
+    Ok(events)
+}
+
+fn state_color(state: &str) -> &'static str {
+    match state {
+        "ok" => GREEN,
+        "limited" | "low" => YELLOW,
+        "invalid" | "exhausted" => RED,
+        _ => DIM,
+    }
+}
+
+/// `events` subcommand: prints the logged transitions, account statuses
+/// under their configured labels
+pub fn run(args: &EventsArgs, labels: &StatusLabels) -> Result<()> {
+    let events = read_since(args.since)?;
+
+    if args.json {
+        for event in &events {
+            println!("{}", serde_json::to_string(event).context("Failed to encode event")?);
+        }
+        return Ok(());
+    }
+
+    if events.is_empty() {
+        println!("No events in the last {}", humantime::format_duration(args.since));
+        return Ok(());
+    }
+
+    println!(
+        "{}{:<22} {:<20} {:<28} Change{}",
+        BOLD, "Time", "Account", "Model", RESET
+    );
+    println!("{}", "-".repeat(85));
+    for event in &events {
+        println!(
+            "{:<22} {:<20} {:<28} {}{}{} -> {}{}{}",
+            event.timestamp.with_timezone(&Local).format(locale::current().short_date_time).to_string(),
+            get_short_email(&event.account),
+            event.model.as_deref().unwrap_or("(account)"),
+            state_color(&event.from),
+            labels.label(&event.from),
+            RESET,
+            state_color(&event.to),
+            labels.label(&event.to),
+            RESET
+        );
+    }
+    Ok(())
+}
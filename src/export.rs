@@ -0,0 +1,154 @@
+use crate::history::{self, Sample};
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use std::{
+    borrow::Cow,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    time::Duration,
+};
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// Comma-separated values with a header row
+    Csv,
+    /// Apache Parquet, for pandas, Polars or DuckDB
+    #[cfg(feature = "parquet")]
+    Parquet,
+}
+
+/// One account's quota of one model in one sample: the long format analysts
+/// pivot from
+struct Row<'a> {
+    timestamp: i64,
+    account: &'a str,
+    model: &'a str,
+    remaining_fraction: f64,
+    rate_limited: bool,
+}
+
+fn rows(samples: &[Sample]) -> impl Iterator<Item = Row<'_>> {
+    samples.iter().flat_map(|sample| {
+        sample.quotas.iter().flat_map(move |(email, quotas)| {
+            let limited = sample.limited.get(email);
+            quotas.iter().map(move |(model, &fraction)| Row {
+                timestamp: sample.timestamp.timestamp_millis(),
+                account: email,
+                model,
+                remaining_fraction: fraction,
+                rate_limited: limited.is_some_and(|models| models.contains(model)),
+            })
+        })
+    })
+}
+
+/// `field` as one CSV field: quoted, with quotes doubled, when it holds a
+/// comma, quote or line break (RFC 4180)
+pub fn csv_field(field: &str) -> Cow<'_, str> {
+    if field.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+fn write_csv(out: &mut impl Write, samples: &[Sample]) -> io::Result<()> {
+    writeln!(out, "timestamp,account,model,remaining_fraction,rate_limited")?;
+    for row in rows(samples) {
+        let timestamp = chrono::DateTime::from_timestamp_millis(row.timestamp).unwrap_or_default();
+        writeln!(
+            out,
+            "{},{},{},{},{}",
+            timestamp.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            csv_field(row.account),
+            csv_field(row.model),
+            row.remaining_fraction,
+            row.rate_limited
+        )?;
+    }
+    out.flush()
+}
+
+#[cfg(feature = "parquet")]
+const PARQUET_SCHEMA: &str = "
+message sample {
+    required int64 timestamp (TIMESTAMP(MILLIS, true));
+    required binary account (STRING);
+    required binary model (STRING);
+    required double remaining_fraction;
+    required boolean rate_limited;
+}";
+
+/// All rows as a single row group; a month of minute samples is small
+#[cfg(feature = "parquet")]
+fn write_parquet(file: File, samples: &[Sample]) -> Result<()> {
+    use parquet::{
+        data_type::{BoolType, ByteArray, ByteArrayType, DoubleType, Int64Type},
+        file::{properties::WriterProperties, writer::SerializedFileWriter},
+        schema::parser::parse_message_type,
+    };
+    use std::sync::Arc;
+
+    let schema = Arc::new(parse_message_type(PARQUET_SCHEMA).expect("export schema is valid"));
+    let mut writer = SerializedFileWriter::new(file, schema, Arc::new(WriterProperties::builder().build()))
+        .context("Failed to start Parquet file")?;
+    let rows: Vec<Row> = rows(samples).collect();
+    let mut group = writer.next_row_group().context("Failed to write Parquet")?;
+    let mut index = 0;
+    while let Some(mut column) = group.next_column().context("Failed to write Parquet")? {
+        let written = match index {
+            0 => column
+                .typed::<Int64Type>()
+                .write_batch(&rows.iter().map(|r| r.timestamp).collect::<Vec<_>>(), None, None),
+            1 => column.typed::<ByteArrayType>().write_batch(
+                &rows.iter().map(|r| ByteArray::from(r.account)).collect::<Vec<_>>(),
+                None,
+                None,
+            ),
+            2 => column.typed::<ByteArrayType>().write_batch(
+                &rows.iter().map(|r| ByteArray::from(r.model)).collect::<Vec<_>>(),
+                None,
+                None,
+            ),
+            3 => column
+                .typed::<DoubleType>()
+                .write_batch(&rows.iter().map(|r| r.remaining_fraction).collect::<Vec<_>>(), None, None),
+            _ => column
+                .typed::<BoolType>()
+                .write_batch(&rows.iter().map(|r| r.rate_limited).collect::<Vec<_>>(), None, None),
+        };
+        written.context("Failed to write Parquet")?;
+        column.close().context("Failed to write Parquet")?;
+        index += 1;
+    }
+    group.close().context("Failed to write Parquet")?;
+    writer.close().context("Failed to finish Parquet file")?;
+    Ok(())
+}
+
+/// `report export`: the raw samples newer than `since`, one row per account
+/// and model, to `output` or stdout
+pub fn run(since: Duration, format: ExportFormat, output: Option<&Path>) -> Result<()> {
+    let samples = history::read_since(since)?;
+    let create = |path: &Path| File::create(path).with_context(|| format!("Failed to create {}", path.display()));
+    match format {
+        ExportFormat::Csv => match output {
+            Some(path) => write_csv(&mut BufWriter::new(create(path)?), &samples),
+            None => write_csv(&mut io::stdout().lock(), &samples),
+        }
+        .context("Failed to write CSV")?,
+        #[cfg(feature = "parquet")]
+        ExportFormat::Parquet => {
+            let Some(path) = output else {
+                anyhow::bail!("Parquet is binary; pass --output FILE");
+            };
+            write_parquet(create(path)?, &samples)?;
+        }
+    }
+    if let Some(path) = output {
+        let count: usize = samples.iter().map(|s| s.quotas.values().map(|q| q.len()).sum::<usize>()).sum();
+        eprintln!("Wrote {} rows from {} samples to {}", count, samples.len(), path.display());
+    }
+    Ok(())
+}
@@ -0,0 +1,735 @@
+//! A small expression language shared by `--filter-expr`, `--fail-when` and
+//! `watch-until --condition`, e.g. `quota("gemini") < 0.1 && status != "invalid"`.
+//!
+//! Numbers, strings in single or double quotes (`\` escapes a quote or
+//! itself), `true`/`false`, variables and function calls combine with `||`,
+//! `&&`, `!`, comparisons and arithmetic.
+//! Evaluation never fails: anything that doesn't make sense (a missing model,
+//! comparing a string with a number) is `null`, and `null` is false.
+
+use crate::{intern::Name, config::Config, count_stats, get_account_status, model_available, Account, ApiResponse};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    Str(String),
+}
+
+impl Value {
+    pub fn truthy(&self) -> bool {
+        match self {
+            Value::Null => false,
+            Value::Bool(b) => *b,
+            Value::Number(n) => *n != 0.0,
+            Value::Str(s) => !s.is_empty(),
+        }
+    }
+
+    fn number(&self) -> Option<f64> {
+        match self {
+            Value::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+}
+
+/// Whole numbers without a fraction, others to at most two decimals
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Null => f.write_str("-"),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Number(n) if n.fract() == 0.0 && n.abs() < 1e15 => write!(f, "{}", *n as i64),
+            Value::Number(n) => {
+                let text = format!("{:.2}", n);
+                f.write_str(text.trim_end_matches('0').trim_end_matches('.'))
+            }
+            Value::Str(s) => f.write_str(s),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BinOp {
+    Or,
+    And,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Literal(Value),
+    Var(String),
+    Call(String, Vec<Node>),
+    Not(Box<Node>),
+    Neg(Box<Node>),
+    Binary(BinOp, Box<Node>, Box<Node>),
+}
+
+/// What an expression can refer to
+pub trait Scope {
+    fn var(&self, name: &str) -> Value;
+    fn call(&self, name: &str, args: &[Value]) -> Value;
+}
+
+/// Names an expression may use, checked when it is parsed
+pub struct Names {
+    vars: &'static [&'static str],
+    funcs: &'static [&'static str],
+}
+
+/// Per account: `email`, `name` (before the `@`), `status` (ok, limited,
+/// invalid, disabled) and `origin`; `quota(model)`, `limited(model)`,
+/// `available(model)` and `tagged(tag)`
+pub const ACCOUNT: Names = Names {
+    vars: &["email", "name", "status", "origin"],
+    funcs: &["quota", "limited", "available", "tagged"],
+};
+
+/// Whole fleet: `total`, `available`, `rate_limited`, `invalid` and `disabled` counts;
+/// `available(model)` accounts and the average `quota(model)` of usable ones
+pub const FLEET: Names = Names {
+    vars: &["total", "available", "rate_limited", "invalid", "disabled"],
+    funcs: &["available", "quota"],
+};
+
+/// A parsed expression along with its source, for messages
+#[derive(Debug, Clone)]
+pub struct Expr {
+    source: String,
+    root: Node,
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl Expr {
+    /// Parses `source`, rejecting names outside `names`
+    pub fn parse(source: &str, names: &Names) -> Result<Expr, String> {
+        let (tokens, columns) = tokenize(source)?.into_iter().unzip();
+        let mut parser = Parser {
+            tokens,
+            columns,
+            pos: 0,
+        };
+        let root = parser.or()?;
+        if let Some(token) = parser.tokens.get(parser.pos) {
+            return Err(format!("unexpected {} at column {}", token, parser.column()));
+        }
+        check(&root, names)?;
+        Ok(Expr {
+            source: source.to_string(),
+            root,
+        })
+    }
+
+    pub fn eval(&self, scope: &dyn Scope) -> Value {
+        eval(&self.root, scope)
+    }
+}
+
+pub fn parse_account(source: &str) -> Result<Expr, String> {
+    Expr::parse(source, &ACCOUNT)
+}
+
+pub fn parse_fleet(source: &str) -> Result<Expr, String> {
+    Expr::parse(source, &FLEET)
+}
+
+/// Reads a per-account expression from a config string
+pub fn deserialize_account<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Expr, D::Error> {
+    let source = <String as serde::Deserialize>::deserialize(deserializer)?;
+    parse_account(&source).map_err(serde::de::Error::custom)
+}
+
+fn check(node: &Node, names: &Names) -> Result<(), String> {
+    match node {
+        Node::Literal(_) => Ok(()),
+        Node::Var(name) if names.vars.contains(&name.as_str()) => Ok(()),
+        Node::Var(name) => Err(format!("unknown variable '{}' (expected {})", name, names.vars.join(", "))),
+        Node::Call(name, args) if names.funcs.contains(&name.as_str()) => {
+            if args.len() != 1 {
+                return Err(format!("{}() takes one argument", name));
+            }
+            args.iter().try_for_each(|a| check(a, names))
+        }
+        Node::Call(name, _) => Err(format!(
+            "unknown function '{}' (expected {})",
+            name,
+            names.funcs.join(", ")
+        )),
+        Node::Not(inner) | Node::Neg(inner) => check(inner, names),
+        Node::Binary(_, left, right) => {
+            check(left, names)?;
+            check(right, names)
+        }
+    }
+}
+
+fn eval(node: &Node, scope: &dyn Scope) -> Value {
+    match node {
+        Node::Literal(value) => value.clone(),
+        Node::Var(name) => scope.var(name),
+        Node::Call(name, args) => {
+            let args: Vec<Value> = args.iter().map(|a| eval(a, scope)).collect();
+            scope.call(name, &args)
+        }
+        Node::Not(inner) => Value::Bool(!eval(inner, scope).truthy()),
+        Node::Neg(inner) => match eval(inner, scope).number() {
+            Some(n) => Value::Number(-n),
+            None => Value::Null,
+        },
+        Node::Binary(BinOp::Or, left, right) => Value::Bool(eval(left, scope).truthy() || eval(right, scope).truthy()),
+        Node::Binary(BinOp::And, left, right) => {
+            Value::Bool(eval(left, scope).truthy() && eval(right, scope).truthy())
+        }
+        Node::Binary(op, left, right) => {
+            let (left, right) = (eval(left, scope), eval(right, scope));
+            match op {
+                BinOp::Eq | BinOp::Ne if left == Value::Null || right == Value::Null => Value::Null,
+                BinOp::Eq => Value::Bool(left == right),
+                BinOp::Ne => Value::Bool(left != right),
+                _ => {
+                    let (Some(a), Some(b)) = (left.number(), right.number()) else {
+                        return Value::Null;
+                    };
+                    match op {
+                        BinOp::Lt => Value::Bool(a < b),
+                        BinOp::Le => Value::Bool(a <= b),
+                        BinOp::Gt => Value::Bool(a > b),
+                        BinOp::Ge => Value::Bool(a >= b),
+                        BinOp::Add => Value::Number(a + b),
+                        BinOp::Sub => Value::Number(a - b),
+                        BinOp::Mul => Value::Number(a * b),
+                        BinOp::Div if b != 0.0 => Value::Number(a / b),
+                        _ => Value::Null,
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Str(String),
+    Ident(String),
+    Op(&'static str),
+    Open,
+    Close,
+    Comma,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Number(n) => write!(f, "number {}", n),
+            Token::Str(s) => write!(f, "string \"{}\"", s),
+            Token::Ident(name) => write!(f, "'{}'", name),
+            Token::Op(op) => write!(f, "'{}'", op),
+            Token::Open => f.write_str("'('"),
+            Token::Close => f.write_str("')'"),
+            Token::Comma => f.write_str("','"),
+        }
+    }
+}
+
+/// Longest first, so `<=` isn't read as `<` then `=`
+const OPERATORS: [&str; 13] = ["||", "&&", "==", "!=", "<=", ">=", "<", ">", "!", "+", "-", "*", "/"];
+
+/// Each token with the column, counted in characters from 1, it starts at
+fn tokenize(source: &str) -> Result<Vec<(Token, usize)>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = source.trim_start();
+    while let Some(c) = rest.chars().next() {
+        let column = source[..source.len() - rest.len()].chars().count() + 1;
+        let (token, len) = if let Some(op) = OPERATORS.iter().find(|op| rest.starts_with(**op)) {
+            (Token::Op(op), op.len())
+        } else if c == '(' || c == ')' || c == ',' {
+            let token = match c {
+                '(' => Token::Open,
+                ')' => Token::Close,
+                _ => Token::Comma,
+            };
+            (token, 1)
+        } else if c == '"' || c == '\'' {
+            let (text, len) = string(rest, c).map_err(|e| format!("{} at column {}", e, column))?;
+            (Token::Str(text), len)
+        } else if c.is_ascii_digit() || c == '.' {
+            let end = rest.find(|ch: char| !(ch.is_ascii_digit() || ch == '.')).unwrap_or(rest.len());
+            let number = rest[..end]
+                .parse()
+                .map_err(|_| format!("bad number '{}' at column {}", &rest[..end], column))?;
+            (Token::Number(number), end)
+        } else if c.is_alphabetic() || c == '_' {
+            let end = rest.find(|ch: char| !(ch.is_alphanumeric() || ch == '_')).unwrap_or(rest.len());
+            (Token::Ident(rest[..end].to_string()), end)
+        } else {
+            return Err(format!("unexpected character '{}' at column {}", c, column));
+        };
+        tokens.push((token, column));
+        rest = rest[len..].trim_start();
+    }
+    Ok(tokens)
+}
+
+/// The string `rest` opens with, quoted by `quote`, and how many bytes it
+/// takes up quotes included
+fn string(rest: &str, quote: char) -> Result<(String, usize), String> {
+    let mut text = String::new();
+    let mut chars = rest.char_indices().skip(1);
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some((_, escaped @ ('\\' | '"' | '\''))) => text.push(escaped),
+                Some((_, other)) => return Err(format!("unknown escape '\\{}' in string", other)),
+                None => break,
+            },
+            c if c == quote => return Ok((text, i + c.len_utf8())),
+            c => text.push(c),
+        }
+    }
+    Err("unterminated string".to_string())
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    /// Where each token starts, for messages
+    columns: Vec<usize>,
+    pos: usize,
+}
+
+impl Parser {
+    /// The column of the token at `pos`, which must exist
+    fn column(&self) -> usize {
+        self.columns[self.pos]
+    }
+
+    fn peek_op(&self, ops: &[&'static str]) -> Option<&'static str> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Op(op)) if ops.contains(op) => Some(op),
+            _ => None,
+        }
+    }
+
+    fn binary(&mut self, ops: &[&'static str], next: fn(&mut Parser) -> Result<Node, String>) -> Result<Node, String> {
+        let mut node = next(self)?;
+        while let Some(op) = self.peek_op(ops) {
+            self.pos += 1;
+            let right = next(self)?;
+            let op = match op {
+                "||" => BinOp::Or,
+                "&&" => BinOp::And,
+                "==" => BinOp::Eq,
+                "!=" => BinOp::Ne,
+                "<" => BinOp::Lt,
+                "<=" => BinOp::Le,
+                ">" => BinOp::Gt,
+                ">=" => BinOp::Ge,
+                "+" => BinOp::Add,
+                "-" => BinOp::Sub,
+                "*" => BinOp::Mul,
+                _ => BinOp::Div,
+            };
+            node = Node::Binary(op, Box::new(node), Box::new(right));
+        }
+        Ok(node)
+    }
+
+    fn or(&mut self) -> Result<Node, String> {
+        self.binary(&["||"], Parser::and)
+    }
+
+    fn and(&mut self) -> Result<Node, String> {
+        self.binary(&["&&"], Parser::comparison)
+    }
+
+    fn comparison(&mut self) -> Result<Node, String> {
+        self.binary(&["==", "!=", "<", "<=", ">", ">="], Parser::sum)
+    }
+
+    fn sum(&mut self) -> Result<Node, String> {
+        self.binary(&["+", "-"], Parser::product)
+    }
+
+    fn product(&mut self) -> Result<Node, String> {
+        self.binary(&["*", "/"], Parser::unary)
+    }
+
+    fn unary(&mut self) -> Result<Node, String> {
+        match self.peek_op(&["!", "-"]) {
+            Some("!") => {
+                self.pos += 1;
+                Ok(Node::Not(Box::new(self.unary()?)))
+            }
+            Some(_) => {
+                self.pos += 1;
+                Ok(Node::Neg(Box::new(self.unary()?)))
+            }
+            None => self.primary(),
+        }
+    }
+
+    fn primary(&mut self) -> Result<Node, String> {
+        let token = self.tokens.get(self.pos).cloned().ok_or("unexpected end of expression")?;
+        self.pos += 1;
+        match token {
+            Token::Number(n) => Ok(Node::Literal(Value::Number(n))),
+            Token::Str(s) => Ok(Node::Literal(Value::Str(s))),
+            Token::Ident(name) if name == "true" || name == "false" => Ok(Node::Literal(Value::Bool(name == "true"))),
+            Token::Ident(name) if self.tokens.get(self.pos) == Some(&Token::Open) => {
+                self.pos += 1;
+                let mut args = Vec::new();
+                if self.tokens.get(self.pos) != Some(&Token::Close) {
+                    loop {
+                        args.push(self.or()?);
+                        match self.tokens.get(self.pos) {
+                            Some(Token::Comma) => self.pos += 1,
+                            _ => break,
+                        }
+                    }
+                }
+                self.expect_close()?;
+                Ok(Node::Call(name, args))
+            }
+            Token::Ident(name) => Ok(Node::Var(name)),
+            Token::Open => {
+                let node = self.or()?;
+                self.expect_close()?;
+                Ok(node)
+            }
+            other => Err(format!("unexpected {} at column {}", other, self.columns[self.pos - 1])),
+        }
+    }
+
+    fn expect_close(&mut self) -> Result<(), String> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Close) => {
+                self.pos += 1;
+                Ok(())
+            }
+            Some(other) => Err(format!("expected ')', found {} at column {}", other, self.column())),
+            None => Err("missing ')'".to_string()),
+        }
+    }
+}
+
+/// The models `pattern` names: itself if listed, else every model containing it
+fn matching<'a, I: Iterator<Item = &'a Name>>(models: I, pattern: &'a str) -> Vec<&'a Name> {
+    let models: Vec<&Name> = models.collect();
+    match models.iter().find(|m| m.as_str() == pattern) {
+        Some(exact) => vec![*exact],
+        None => models.into_iter().filter(|m| m.contains(pattern)).collect(),
+    }
+}
+
+fn string_arg(args: &[Value]) -> Option<&str> {
+    match args.first() {
+        Some(Value::Str(s)) => Some(s),
+        _ => None,
+    }
+}
+
+/// One account, for `--filter-expr` and custom columns
+pub struct AccountScope<'a> {
+    pub account: &'a Account,
+    pub config: &'a Config,
+}
+
+impl Scope for AccountScope<'_> {
+    fn var(&self, name: &str) -> Value {
+        let account = self.account;
+        match name {
+            "email" => Value::Str(account.email.to_string()),
+            "name" => Value::Str(crate::get_short_email(&account.email).to_string()),
+            "status" => Value::Str(get_account_status(account).0.to_string()),
+            "origin" => account.origin.clone().map_or(Value::Null, Value::Str),
+            _ => Value::Null,
+        }
+    }
+
+    fn call(&self, name: &str, args: &[Value]) -> Value {
+        let Some(arg) = string_arg(args) else {
+            return Value::Null;
+        };
+        let account = self.account;
+        if name == "tagged" {
+            return Value::Bool(self.config.tags_for(&account.email).any(|t| t == arg));
+        }
+        let Some(ref limits) = account.limits else {
+            return Value::Null;
+        };
+        let models = matching(limits.keys(), arg);
+        if models.is_empty() {
+            return Value::Null;
+        }
+        match name {
+            // Worst of the matching models, the one that runs out first
+            "quota" => Value::Number(
+                models
+                    .iter()
+                    .map(|m| limits[m.as_str()].remaining_fraction)
+                    .fold(f64::INFINITY, f64::min),
+            ),
+            "limited" => Value::Bool(models.iter().any(|m| {
+                account
+                    .model_rate_limits
+                    .as_ref()
+                    .and_then(|r| r.get(m.as_str()))
+                    .map(|l| l.is_rate_limited)
+                    .unwrap_or(false)
+            })),
+            "available" => Value::Bool(models.iter().any(|m| model_available(account, m))),
+            _ => Value::Null,
+        }
+    }
+}
+
+/// The whole response, for `--fail-when` and `watch-until`
+pub struct FleetScope<'a> {
+    pub data: &'a ApiResponse,
+}
+
+impl Scope for FleetScope<'_> {
+    fn var(&self, name: &str) -> Value {
+        let (available, rate_limited, invalid, disabled) = count_stats(&self.data.accounts);
+        let count = match name {
+            "total" => self.data.accounts.len(),
+            "available" => available,
+            "rate_limited" => rate_limited,
+            "invalid" => invalid,
+            "disabled" => disabled,
+            _ => return Value::Null,
+        };
+        Value::Number(count as f64)
+    }
+
+    fn call(&self, name: &str, args: &[Value]) -> Value {
+        let Some(arg) = string_arg(args) else {
+            return Value::Null;
+        };
+        let models = matching(self.data.models.iter(), arg);
+        if models.is_empty() {
+            return Value::Null;
+        }
+        let usable = self
+            .data
+            .accounts
+            .iter()
+            .filter(|a| !a.is_invalid.unwrap_or(false) && a.enabled.unwrap_or(true));
+        match name {
+            "available" => Value::Number(
+                self.data
+                    .accounts
+                    .iter()
+                    .filter(|a| models.iter().any(|m| model_available(a, m)))
+                    .count() as f64,
+            ),
+            "quota" => {
+                let fractions: Vec<f64> = usable
+                    .filter_map(|a| a.limits.as_ref())
+                    .flat_map(|limits| models.iter().filter_map(|m| limits.get(m.as_str())))
+                    .map(|q| q.remaining_fraction)
+                    .collect();
+                if fractions.is_empty() {
+                    Value::Null
+                } else {
+                    Value::Number(fractions.iter().sum::<f64>() / fractions.len() as f64)
+                }
+            }
+            _ => Value::Null,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NAMES: Names = Names {
+        vars: &["x", "y", "s", "missing"],
+        funcs: &["quota"],
+    };
+
+    /// `x` is 2, `y` is 3 and `s` is "ok"; `quota("flash")` is 0.25 and
+    /// anything else is null
+    struct Fixed;
+
+    impl Scope for Fixed {
+        fn var(&self, name: &str) -> Value {
+            match name {
+                "x" => Value::Number(2.0),
+                "y" => Value::Number(3.0),
+                "s" => Value::Str("ok".to_string()),
+                _ => Value::Null,
+            }
+        }
+
+        fn call(&self, name: &str, args: &[Value]) -> Value {
+            match (name, string_arg(args)) {
+                ("quota", Some("flash")) => Value::Number(0.25),
+                _ => Value::Null,
+            }
+        }
+    }
+
+    fn eval(source: &str) -> Value {
+        Expr::parse(source, &NAMES).unwrap_or_else(|e| panic!("{}: {}", source, e)).eval(&Fixed)
+    }
+
+    fn error(source: &str) -> String {
+        Expr::parse(source, &NAMES).map(|_| ()).unwrap_err()
+    }
+
+    #[test]
+    fn binds_tighter_further_down() {
+        assert_eq!(eval("1 + 2 * 3"), Value::Number(7.0));
+        assert_eq!(eval("(1 + 2) * 3"), Value::Number(9.0));
+        assert_eq!(eval("x + 1 < y * 2"), Value::Bool(true));
+        // `&&` before `||`
+        assert_eq!(eval("true || false && false"), Value::Bool(true));
+        assert_eq!(eval("(true || false) && false"), Value::Bool(false));
+        assert_eq!(eval("-x * y"), Value::Number(-6.0));
+        assert_eq!(eval("!false && false"), Value::Bool(false));
+    }
+
+    #[test]
+    fn groups_from_the_left() {
+        assert_eq!(eval("10 - 4 - 3"), Value::Number(3.0));
+        assert_eq!(eval("8 / 4 / 2"), Value::Number(1.0));
+    }
+
+    #[test]
+    fn tells_not_from_not_equal() {
+        assert_eq!(eval("x != y"), Value::Bool(true));
+        assert_eq!(eval("x!=2"), Value::Bool(false));
+        assert_eq!(eval("!x"), Value::Bool(false));
+        assert_eq!(eval("!!x"), Value::Bool(true));
+        assert_eq!(eval("! (x == y)"), Value::Bool(true));
+        assert_eq!(eval("s != 'ok'"), Value::Bool(false));
+        assert_eq!(error("x ! y"), "unexpected '!' at column 3");
+        assert_eq!(error("x ! = y"), "unexpected character '=' at column 5");
+    }
+
+    #[test]
+    fn makes_nonsense_null() {
+        assert_eq!(eval("missing"), Value::Null);
+        assert_eq!(eval("quota('pro')"), Value::Null);
+        assert_eq!(eval("quota(1)"), Value::Null);
+        assert_eq!(eval("s < 1"), Value::Null);
+        assert_eq!(eval("s + 1"), Value::Null);
+        assert_eq!(eval("-s"), Value::Null);
+        assert_eq!(eval("x / 0"), Value::Null);
+        assert_eq!(eval("s == 1"), Value::Bool(false));
+    }
+
+    #[test]
+    fn never_matches_null_either_way() {
+        assert_eq!(eval("missing == 1"), Value::Null);
+        assert_eq!(eval("missing != 1"), Value::Null);
+        assert_eq!(eval("quota('pro') < 0.5"), Value::Null);
+        assert!(!eval("missing == missing").truthy());
+        // Null is false, so negating it holds
+        assert_eq!(eval("!missing"), Value::Bool(true));
+        assert_eq!(eval("missing || quota('flash') < 0.5"), Value::Bool(true));
+        assert_eq!(eval("missing && true"), Value::Bool(false));
+    }
+
+    #[test]
+    fn reads_both_quotes_and_escapes() {
+        assert_eq!(eval("'ok' == \"ok\""), Value::Bool(true));
+        assert_eq!(eval(r#"'say "hi"'"#), Value::Str(r#"say "hi""#.to_string()));
+        assert_eq!(eval(r#""it's""#), Value::Str("it's".to_string()));
+        assert_eq!(eval(r"'it\'s'"), Value::Str("it's".to_string()));
+        assert_eq!(eval(r#""a \"b\"""#), Value::Str(r#"a "b""#.to_string()));
+        assert_eq!(eval(r"'back\\slash'"), Value::Str(r"back\slash".to_string()));
+        assert_eq!(eval("''"), Value::Str(String::new()));
+        assert!(!eval("''").truthy());
+        assert_eq!(eval("quota(\"flash\")"), Value::Number(0.25));
+    }
+
+    #[test]
+    fn rejects_broken_strings() {
+        assert_eq!(error("s == 'ok"), "unterminated string at column 6");
+        assert_eq!(error(r"s == 'ok\'"), "unterminated string at column 6");
+        assert_eq!(error(r"s == 'o\k'"), r"unknown escape '\k' in string at column 6");
+    }
+
+    #[test]
+    fn says_where_parsing_failed() {
+        assert_eq!(error("x $ y"), "unexpected character '$' at column 3");
+        // Columns count characters, not bytes
+        assert_eq!(error("'é' == x #"), "unexpected character '#' at column 10");
+        assert_eq!(error("x y"), "unexpected 'y' at column 3");
+        assert_eq!(error("x == 1.2.3"), "bad number '1.2.3' at column 6");
+        assert_eq!(error("x == )"), "unexpected ')' at column 6");
+        assert_eq!(error("quota('flash' 1)"), "expected ')', found number 1 at column 15");
+        assert_eq!(error("(x + 1"), "missing ')'");
+        assert_eq!(error("x +"), "unexpected end of expression");
+        assert_eq!(error(""), "unexpected end of expression");
+    }
+
+    #[test]
+    fn checks_names() {
+        assert_eq!(error("z"), "unknown variable 'z' (expected x, y, s, missing)");
+        assert_eq!(error("limited('a')"), "unknown function 'limited' (expected quota)");
+        assert_eq!(error("quota('a', 'b')"), "quota() takes one argument");
+        assert_eq!(error("quota()"), "quota() takes one argument");
+        assert!(parse_account("quota('gemini') < 0.1 && status != 'invalid'").is_ok());
+        assert!(parse_fleet("status == 'ok'").is_err());
+    }
+
+    #[test]
+    fn shows_numbers_briefly() {
+        assert_eq!(eval("x * y").to_string(), "6");
+        assert_eq!(eval("1 / 3").to_string(), "0.33");
+        assert_eq!(eval("quota('flash')").to_string(), "0.25");
+        assert_eq!(eval("missing").to_string(), "-");
+    }
+
+    #[test]
+    fn looks_up_accounts() {
+        let account: Account = serde_json::from_str(
+            r#"{
+                "email": "alice@example.com",
+                "limits": {
+                    "gemini-3-flash": {"remainingFraction": 0.5, "resetTime": null},
+                    "gemini-3-pro-high": {"remainingFraction": 0.2, "resetTime": null}
+                }
+            }"#,
+        )
+        .unwrap();
+        let config = Config::default();
+        let scope = AccountScope {
+            account: &account,
+            config: &config,
+        };
+        let eval = |source: &str| parse_account(source).unwrap().eval(&scope);
+        assert_eq!(eval("name"), Value::Str("alice".to_string()));
+        // Exactly named, else the worst of every model containing it
+        assert_eq!(eval("quota('gemini-3-flash')"), Value::Number(0.5));
+        assert_eq!(eval("quota('gemini')"), Value::Number(0.2));
+        // Fields the account doesn't have
+        assert_eq!(eval("origin"), Value::Null);
+        assert_eq!(eval("quota('claude')"), Value::Null);
+        assert_eq!(eval("limited('claude')"), Value::Null);
+        assert_eq!(eval("limited('gemini')"), Value::Bool(false));
+        assert_eq!(eval("tagged('team')"), Value::Bool(false));
+    }
+}
@@ -0,0 +1,148 @@
+use crate::{
+    api::ApiVersion,
+    config::Config,
+    error::FetchError,
+    fetch_text,
+    pages::{document, fetch_pages},
+    Args,
+};
+use clap::ValueEnum;
+use reqwest::Url;
+use serde_json::{json, Map, Value};
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+
+/// How each `--url`'s accounts are fetched
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FetchStrategy {
+    /// One request for every account's limits
+    #[default]
+    Bulk,
+    /// List the accounts, then request each one's limits on its own
+    PerAccount,
+}
+
+/// Everything fetching one endpoint's accounts takes, owned so that each
+/// endpoint can be fetched on a task of its own
+#[derive(Debug, Clone)]
+pub struct FetchPlan {
+    strategy: FetchStrategy,
+    max_body: u64,
+    max_pages: usize,
+    concurrency: usize,
+    accounts_path: String,
+    limits_path: String,
+    api_version: ApiVersion,
+}
+
+impl FetchPlan {
+    pub fn new(args: &Args, config: &Config) -> FetchPlan {
+        FetchPlan {
+            strategy: args.fetch,
+            max_body: args.max_body_size,
+            max_pages: args.max_pages,
+            concurrency: args.concurrency,
+            accounts_path: config.proxy.accounts_path.clone(),
+            limits_path: config.proxy.account_limits_path.clone(),
+            api_version: config.proxy.api_version,
+        }
+    }
+
+    /// The account limits behind `url` as one document in the bulk
+    /// endpoint's shape, whichever way they were fetched
+    pub async fn fetch(&self, client: &reqwest::Client, url: &str) -> Result<String, FetchError> {
+        match self.strategy {
+            FetchStrategy::Bulk => fetch_pages(client, url, self.max_body, self.max_pages).await,
+            FetchStrategy::PerAccount => self.fetch_per_account(client, url).await,
+        }
+    }
+
+    /// Lists the accounts at `accounts_path`, then fetches every account's
+    /// `limits_path` with at most `concurrency` requests in flight
+    async fn fetch_per_account(&self, client: &reqwest::Client, url: &str) -> Result<String, FetchError> {
+        let list_url = join(url, &self.accounts_path)?;
+        let text = fetch_pages(client, &list_url, self.max_body, self.max_pages).await?;
+        let list = document(&text).ok_or_else(|| FetchError::shape(format!("Account list at {} is not JSON", list_url), &text))?;
+        let entries = match list.get("accounts").unwrap_or(&list) {
+            Value::Array(entries) => entries.clone(),
+            _ => {
+                return Err(FetchError::shape(
+                    format!("Account list at {} has no accounts array", list_url),
+                    &text,
+                ))
+            }
+        };
+
+        let permits = Arc::new(Semaphore::new(self.concurrency));
+        let mut tasks = Vec::with_capacity(entries.len());
+        let mut accounts = Vec::with_capacity(entries.len());
+        for entry in entries {
+            let account = match entry {
+                Value::String(email) => Map::from_iter([("email".to_string(), Value::String(email))]),
+                Value::Object(fields) => fields,
+                other => {
+                    return Err(FetchError::shape(
+                        format!("Account list at {} has an entry that is neither an email nor an object", list_url),
+                        &other.to_string(),
+                    ))
+                }
+            };
+            let id = match account.get("id").or_else(|| account.get("email")) {
+                Some(Value::String(id)) => id.clone(),
+                Some(Value::Number(id)) => id.to_string(),
+                _ => {
+                    return Err(FetchError::shape(
+                        format!("Account list at {} has an entry without an id or email", list_url),
+                        &Value::Object(account).to_string(),
+                    ))
+                }
+            };
+            let limits_url = join(url, &self.limits_path.replace("{id}", &id))?;
+            let (client, permits, max_body) = (client.clone(), permits.clone(), self.max_body);
+            tasks.push(tokio::spawn(async move {
+                let _permit = permits.acquire_owned().await;
+                fetch_text(&client, &limits_url, max_body).await
+            }));
+            accounts.push(account);
+        }
+
+        let mut total = text.len() as u64;
+        let mut models: Vec<Value> = match list.get("models") {
+            Some(Value::Array(models)) => models.clone(),
+            _ => Vec::new(),
+        };
+        let listed_models = !models.is_empty();
+        for (account, task) in accounts.iter_mut().zip(tasks) {
+            let body = task
+                .await
+                .map_err(|e| FetchError::Body(format!("Fetch task failed: {}", e)))??;
+            total += body.len() as u64;
+            if total > self.max_body {
+                return Err(FetchError::TooLarge { limit: self.max_body });
+            }
+            let Some(Value::Object(fields)) = document(&body) else {
+                return Err(FetchError::shape("Account limits are not a JSON object".to_string(), &body));
+            };
+            // The account's own answer wins over what the list said about it
+            account.extend(fields);
+            if !listed_models {
+                for model in account.get("limits").and_then(Value::as_object).into_iter().flat_map(|l| l.keys()) {
+                    let model = Value::String(model.clone());
+                    if !models.contains(&model) {
+                        models.push(model);
+                    }
+                }
+            }
+        }
+        let document = json!({ "accounts": accounts, "models": models }).to_string();
+        Ok(self.api_version.encode(document))
+    }
+}
+
+/// `path` on the host of `url`
+fn join(url: &str, path: &str) -> Result<String, FetchError> {
+    Url::parse(url)
+        .and_then(|base| base.join(path))
+        .map(String::from)
+        .map_err(|e| FetchError::Body(format!("Invalid endpoint path {}: {}", path, e)))
+}
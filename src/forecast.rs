@@ -0,0 +1,205 @@
+use crate::{
+    intern::Name,
+    config::{ForecastConfig, ModelGroup},
+    history::{self, Sample},
+    parse_reset, ApiResponse,
+};
+use anyhow::Result;
+use chrono::{DateTime, Local, Timelike, Utc};
+use std::{collections::HashMap, collections::HashSet, time::Duration};
+
+/// Exhaustion further out than this isn't forecast
+const HORIZON_HOURS: i64 = 24;
+/// An hour of day needs this much observed time before its own average is
+/// trusted; thinner hours fall back to the overall average
+const MIN_HOUR_COVERAGE_MINUTES: f64 = 30.0;
+/// Total observed time needed before anything is forecast
+const MIN_COVERAGE_MINUTES: f64 = 60.0;
+/// How often the profile is re-learned from the history log
+const REBUILD_MINUTES: i64 = 60;
+
+/// Usual consumption per local hour of day, learned from history
+#[derive(Debug, Default)]
+struct HourlyProfile {
+    /// Summed fraction drops per model and hour of day
+    consumed: HashMap<String, [f64; 24]>,
+    /// Observed minutes per hour of day
+    covered: [f64; 24],
+}
+
+impl HourlyProfile {
+    fn from_samples(samples: &[Sample]) -> HourlyProfile {
+        let mut profile = HourlyProfile::default();
+        for (prev, cur, gap) in history::intervals(samples) {
+            let hour = cur.timestamp.with_timezone(&Local).hour() as usize;
+            profile.covered[hour] += gap as f64 / 60.0;
+
+            for (email, quotas) in &cur.quotas {
+                let Some(before) = prev.quotas.get(email) else {
+                    continue;
+                };
+                for (model, &fraction) in quotas {
+                    // Rises are resets, not negative consumption
+                    let drop = before.get(model).map_or(0.0, |&b| b - fraction);
+                    if drop > 0.0 {
+                        profile.consumed.entry(model.clone()).or_insert([0.0; 24])[hour] += drop;
+                    }
+                }
+            }
+        }
+        profile
+    }
+
+    fn total_coverage(&self) -> f64 {
+        self.covered.iter().sum()
+    }
+
+    /// Usual whole-account quotas per hour that `model` consumes at local `hour`
+    fn rate(&self, model: &str, hour: usize) -> f64 {
+        let Some(consumed) = self.consumed.get(model) else {
+            return 0.0;
+        };
+        if self.covered[hour] >= MIN_HOUR_COVERAGE_MINUTES {
+            consumed[hour] / (self.covered[hour] / 60.0)
+        } else {
+            consumed.iter().sum::<f64>() / (self.total_coverage() / 60.0)
+        }
+    }
+}
+
+/// When a model, or a model group from the config, is expected to run out
+#[derive(Debug, Clone)]
+pub struct Forecast {
+    pub name: String,
+    pub exhausted_at: DateTime<Utc>,
+    /// Inside `alert_within_minutes`
+    pub alert: bool,
+    /// Alerting on this refresh but not the previous one
+    pub newly_alerting: bool,
+}
+
+/// Projects fleet exhaustion from the usual hourly consumption in the history
+/// log rather than the last few minutes' burn rate, so a quiet morning doesn't
+/// hide the afternoon rush
+pub struct Forecaster {
+    config: ForecastConfig,
+    profile: Option<HourlyProfile>,
+    built: Option<DateTime<Utc>>,
+    alerting: HashSet<String>,
+}
+
+impl Forecaster {
+    pub fn new(config: &ForecastConfig) -> Self {
+        Forecaster {
+            config: config.clone(),
+            profile: None,
+            built: None,
+            alerting: HashSet::new(),
+        }
+    }
+
+    /// Forecasts each model group (each listed model without groups) and
+    /// leaves the ones running out within a day in `data.forecasts`
+    pub fn observe(&mut self, data: &mut ApiResponse, groups: &[ModelGroup], now: DateTime<Utc>) -> Result<()> {
+        if self.built.map(|at| (now - at).num_minutes() >= REBUILD_MINUTES).unwrap_or(true) {
+            // An unreadable log forecasts nothing rather than ending the watch
+            let samples = match history::read_since(Duration::from_secs(self.config.days * 24 * 60 * 60)) {
+                Ok(samples) => samples,
+                Err(e) => {
+                    if self.built.is_none() {
+                        eprintln!("Warning: no forecasts without the quota history: {:#}", e);
+                    }
+                    Vec::new()
+                }
+            };
+            let profile = HourlyProfile::from_samples(&samples);
+            self.profile = (profile.total_coverage() >= MIN_COVERAGE_MINUTES).then_some(profile);
+            self.built = Some(now);
+        }
+        let Some(ref profile) = self.profile else {
+            return Ok(());
+        };
+
+        let targets: Vec<(String, Vec<&Name>)> = if groups.is_empty() {
+            data.models.iter().map(|m| (m.to_string(), vec![m])).collect()
+        } else {
+            groups
+                .iter()
+                .map(|g| (g.name.clone(), data.models.iter().filter(|m| g.contains(m)).collect()))
+                .collect()
+        };
+
+        let alert_within = chrono::Duration::minutes(self.config.alert_within_minutes as i64);
+        let mut forecasts = Vec::new();
+        let mut alerting = HashSet::new();
+        for (name, models) in targets {
+            let Some(exhausted_at) = project(profile, data, &models, now) else {
+                continue;
+            };
+            let alert = self.config.alert_within_minutes > 0 && exhausted_at - now <= alert_within;
+            if alert {
+                alerting.insert(name.clone());
+            }
+            forecasts.push(Forecast {
+                newly_alerting: alert && !self.alerting.contains(&name),
+                name,
+                exhausted_at,
+                alert,
+            });
+        }
+        self.alerting = alerting;
+        data.forecasts = forecasts;
+        Ok(())
+    }
+}
+
+/// Walks forward from `now` hour by hour, drawing the usual consumption from
+/// the usable accounts' combined quota and refilling accounts at their reset
+/// times. `None` if the pool is already empty or lasts past the horizon.
+fn project(profile: &HourlyProfile, data: &ApiResponse, models: &[&Name], now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let mut pool = 0.0;
+    let mut refills: Vec<(DateTime<Utc>, f64)> = Vec::new();
+    for account in &data.accounts {
+        if account.is_invalid.unwrap_or(false) || !account.enabled.unwrap_or(true) {
+            continue;
+        }
+        let Some(ref limits) = account.limits else {
+            continue;
+        };
+        for quota in models.iter().filter_map(|m| limits.get(m.as_str())) {
+            pool += quota.remaining_fraction;
+            if let Some(at) = parse_reset(quota).filter(|at| *at > now) {
+                refills.push((at, 1.0 - quota.remaining_fraction));
+            }
+        }
+    }
+    if pool <= 0.0 {
+        return None;
+    }
+    refills.sort_by_key(|(at, _)| *at);
+    let mut refills = refills.into_iter().peekable();
+
+    let end = now + chrono::Duration::hours(HORIZON_HOURS);
+    let mut t = now;
+    while t < end {
+        let local = t.with_timezone(&Local);
+        let hour = local.hour() as usize;
+        let into_hour = (local.minute() * 60 + local.second()) as i64;
+        let mut step_end = (t + chrono::Duration::seconds(3600 - into_hour)).min(end);
+        if let Some(&(at, _)) = refills.peek() {
+            step_end = step_end.min(at);
+        }
+
+        let rate: f64 = models.iter().map(|m| profile.rate(m, hour)).sum();
+        let used = rate * (step_end - t).num_seconds() as f64 / 3600.0;
+        if rate > 0.0 && used >= pool {
+            return Some(t + chrono::Duration::seconds((pool / rate * 3600.0) as i64));
+        }
+        pool -= used;
+        t = step_end;
+        while let Some((_, amount)) = refills.next_if(|(at, _)| *at <= t) {
+            pool += amount;
+        }
+    }
+    None
+}
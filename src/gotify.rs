@@ -0,0 +1,29 @@
+use crate::{
+    config::GotifyConfig,
+    notify::{send_retrying, Level},
+};
+use anyhow::Result;
+use serde_json::json;
+
+/// Gotify's priorities run 0-10; clients notify from 4 and alert loudly from 8
+fn priority(level: Level) -> u8 {
+    match level {
+        Level::Good => 2,
+        Level::Warning => 5,
+        Level::Danger => 8,
+    }
+}
+
+/// Posts a message to the server's application; the token goes in a header
+/// rather than the URL
+pub async fn send(
+    client: &reqwest::Client,
+    config: &GotifyConfig,
+    title: String,
+    body: String,
+    level: Level,
+) -> Result<()> {
+    let url = format!("{}/message", config.url.trim_end_matches('/'));
+    let message = json!({ "title": title, "message": body, "priority": priority(level) });
+    send_retrying(|| client.post(&url).header("X-Gotify-Key", &config.token).json(&message)).await
+}
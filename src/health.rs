@@ -0,0 +1,102 @@
+use serde::Serialize;
+use std::{fmt, time::Duration};
+
+/// The watcher's own view of its fetches, to tell a stuck proxy from a bad network
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct FetchHealth {
+    pub consecutive_failures: usize,
+    /// Round trip of the last completed request, successful or not
+    pub last_latency_ms: Option<u64>,
+    /// Size of the last response body
+    pub last_bytes: Option<usize>,
+    /// Response bytes downloaded since start
+    pub total_bytes: u64,
+    /// Whether the last parsed response was the bare document rather than `{"result": ...}`
+    pub bare_fallback: Option<bool>,
+    /// Doublings of the refresh interval in effect: one more per failure,
+    /// one fewer per success once the proxy is back
+    #[serde(skip)]
+    backoff_level: u32,
+}
+
+/// Enough doublings to reach any sensible cap from a sub-second interval
+const MAX_BACKOFF_LEVEL: u32 = 16;
+
+impl FetchHealth {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Notes a response body that arrived after `latency`
+    pub fn record_response(&mut self, latency: Duration, bytes: usize) {
+        self.last_latency_ms = Some(latency.as_millis() as u64);
+        self.last_bytes = Some(bytes);
+        self.total_bytes += bytes as u64;
+    }
+
+    pub fn record_success(&mut self, wrapped: bool) {
+        self.consecutive_failures = 0;
+        self.bare_fallback = Some(!wrapped);
+        self.backoff_level = self.backoff_level.saturating_sub(1);
+    }
+
+    pub fn record_failure(&mut self) {
+        self.consecutive_failures += 1;
+        self.backoff_level = (self.backoff_level + 1).min(MAX_BACKOFF_LEVEL);
+    }
+
+    /// The wait before the next fetch: `interval` doubled once per backoff
+    /// level, but never beyond `cap` (or below `interval`)
+    pub fn backoff(&self, interval: Duration, cap: Duration) -> Duration {
+        let doubled = interval.saturating_mul(1 << self.backoff_level);
+        doubled.min(cap).max(interval)
+    }
+}
+
+/// `1.2 KB` style size
+struct Bytes(u64);
+
+impl fmt::Display for Bytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+        let mut value = self.0 as f64;
+        let mut unit = 0;
+        while value >= 1024.0 && unit < UNITS.len() - 1 {
+            value /= 1024.0;
+            unit += 1;
+        }
+        if unit == 0 {
+            write!(f, "{} {}", self.0, UNITS[0])
+        } else {
+            write!(f, "{:.1} {}", value, UNITS[unit])
+        }
+    }
+}
+
+/// Footer line: `Fetch: 42ms, 3.1 KB (1.2 MB total), bare JSON, 2 consecutive failures`
+impl fmt::Display for FetchHealth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Fetch: ")?;
+        match self.last_latency_ms {
+            Some(ms) => write!(f, "{}ms", ms)?,
+            None => f.write_str("no response")?,
+        }
+        if let Some(bytes) = self.last_bytes {
+            write!(f, ", {} ({} total)", Bytes(bytes as u64), Bytes(self.total_bytes))?;
+        }
+        match self.bare_fallback {
+            Some(true) => f.write_str(", bare JSON")?,
+            Some(false) => f.write_str(", wrapped JSON")?,
+            None => {}
+        }
+        if self.consecutive_failures > 0 {
+            write!(
+                f,
+                ", {} consecutive failure{}",
+                self.consecutive_failures,
+                if self.consecutive_failures == 1 { "" } else { "s" }
+            )?;
+        }
+        Ok(())
+    }
+}
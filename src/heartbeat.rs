@@ -0,0 +1,50 @@
+use crate::Args;
+use anyhow::{Context, Result};
+use std::time::{Duration, Instant};
+
+/// Pings are never closer together than this, however short the interval
+const MIN_SPACING: Duration = Duration::from_secs(60);
+const PING_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Pings `--heartbeat-url` after successful refreshes, for a dead man's
+/// switch such as Healthchecks.io: once the pings stop, because the watcher
+/// died or the proxy stopped answering it, the service raises the alarm.
+pub struct Heartbeat {
+    client: reqwest::Client,
+    url: String,
+    last: Option<Instant>,
+}
+
+impl Heartbeat {
+    pub fn new(args: &Args) -> Result<Option<Heartbeat>> {
+        let Some(ref url) = args.heartbeat_url else {
+            return Ok(None);
+        };
+        let client = reqwest::Client::builder()
+            .timeout(PING_TIMEOUT)
+            .build()
+            .context("Failed to create HTTP client")?;
+        Ok(Some(Heartbeat {
+            client,
+            url: url.clone(),
+            last: None,
+        }))
+    }
+
+    /// Notes a successful refresh, pinging in the background when the last
+    /// ping is a minute old, so a slow service never holds up the refresh
+    pub fn beat(&mut self) {
+        if self.last.is_some_and(|at| at.elapsed() < MIN_SPACING) {
+            return;
+        }
+        self.last = Some(Instant::now());
+        let request = self.client.get(&self.url);
+        tokio::spawn(async move {
+            let result = request.send().await.and_then(|response| response.error_for_status());
+            if let Err(e) = result {
+                // The URL is the check's secret
+                eprintln!("Warning: heartbeat ping failed: {}", e.without_url());
+            }
+        });
+    }
+}
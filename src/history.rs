@@ -0,0 +1,274 @@
+use crate::{config, ApiResponse};
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+/// Watch mode writes at most one sample per minute, whatever the refresh interval
+const SAMPLE_SPACING_SECS: i64 = 60;
+
+/// Consecutive samples further apart than this come from different watch
+/// sessions; nothing is known about the time in between
+const MAX_SAMPLE_GAP_SECS: i64 = 10 * 60;
+
+/// One observation of every account's quotas, as stored in `history.jsonl`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Sample {
+    pub timestamp: DateTime<Utc>,
+    /// Remaining fraction per account email, then per model
+    pub quotas: BTreeMap<String, BTreeMap<String, f64>>,
+    /// Models each account was rate-limited on
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub limited: BTreeMap<String, Vec<String>>,
+}
+
+impl Sample {
+    /// The listed models' quotas of every account in `data`
+    pub fn new(data: &ApiResponse, now: DateTime<Utc>) -> Sample {
+        let mut quotas = BTreeMap::new();
+        let mut limited = BTreeMap::new();
+        for account in &data.accounts {
+            let Some(ref limits) = account.limits else {
+                continue;
+            };
+            let fractions: BTreeMap<String, f64> = data
+                .models
+                .iter()
+                .filter_map(|m| limits.get(m).map(|q| (m.to_string(), q.remaining_fraction)))
+                .collect();
+            if fractions.is_empty() {
+                continue;
+            }
+            quotas.insert(account.email.to_string(), fractions);
+
+            let mut models: Vec<String> = account
+                .model_rate_limits
+                .iter()
+                .flatten()
+                .filter(|(m, r)| r.is_rate_limited && data.models.contains(m))
+                .map(|(m, _)| m.to_string())
+                .collect();
+            if !models.is_empty() {
+                models.sort();
+                limited.insert(account.email.to_string(), models);
+            }
+        }
+        Sample {
+            timestamp: now,
+            quotas,
+            limited,
+        }
+    }
+}
+
+/// Consecutive sample pairs close enough together to say what happened in
+/// between, with the seconds separating them
+pub fn intervals(samples: &[Sample]) -> impl Iterator<Item = (&Sample, &Sample, i64)> {
+    samples.windows(2).filter_map(|pair| {
+        let gap = (pair[1].timestamp - pair[0].timestamp).num_seconds();
+        (gap > 0 && gap <= MAX_SAMPLE_GAP_SECS).then_some((&pair[0], &pair[1], gap))
+    })
+}
+
+pub fn log_path() -> Option<PathBuf> {
+    config::data_dir().map(|dir| dir.join("history.jsonl"))
+}
+
+/// Appends quota samples to the history log during watch mode. The file
+/// work runs on tokio's blocking threads, since waiting for another
+/// watcher's [`LogLock`] would otherwise hold up refreshes and redraws.
+pub struct HistoryRecorder {
+    path: Option<PathBuf>,
+    last: Option<DateTime<Utc>>,
+    /// Set by a background write that failed, to stop recording
+    failed: Arc<AtomicBool>,
+}
+
+impl HistoryRecorder {
+    /// Drops samples older than `retention_days` from the history log and
+    /// records into it from then on. Without a data dir, or when the log
+    /// can't be pruned or written, the watch goes on without recording.
+    /// Must be called within the tokio runtime.
+    pub fn open(retention_days: u64) -> HistoryRecorder {
+        let path = log_path();
+        let failed = Arc::new(AtomicBool::new(false));
+        if let Some(ref path) = path {
+            let retention = Duration::from_secs(retention_days * 24 * 60 * 60);
+            let path = path.clone();
+            in_background(&failed, move || {
+                if let Some(dir) = path.parent() {
+                    fs::create_dir_all(dir).with_context(|| format!("Failed to create data dir {}", dir.display()))?;
+                }
+                prune(&path, retention)
+            });
+        }
+        HistoryRecorder { path, last: None, failed }
+    }
+
+    /// Appends a sample of `data` unless the previous one is less than a
+    /// minute old, returning the sample if one was taken
+    pub fn record(&mut self, data: &ApiResponse, now: DateTime<Utc>) -> Option<Sample> {
+        let path = self.path.as_ref()?;
+        if let Some(last) = self.last {
+            if (now - last).num_seconds() < SAMPLE_SPACING_SECS {
+                return None;
+            }
+        }
+        self.last = Some(now);
+
+        if self.failed.load(Ordering::Relaxed) {
+            self.path = None;
+            return None;
+        }
+
+        let sample = Sample::new(data, now);
+        let (path, written) = (path.clone(), sample.clone());
+        in_background(&self.failed, move || append(&path, &written));
+        Some(sample)
+    }
+}
+
+/// Runs `work` on a blocking thread; if it fails, warns and sets `failed`
+fn in_background(failed: &Arc<AtomicBool>, work: impl FnOnce() -> Result<()> + Send + 'static) {
+    let failed = failed.clone();
+    tokio::task::spawn_blocking(move || {
+        // Only the first failure is reported; recording stops after it
+        if let Err(e) = work() {
+            if !failed.swap(true, Ordering::Relaxed) {
+                eprintln!("Warning: not recording quota history: {:#}", e);
+            }
+        }
+    });
+}
+
+/// Writes `sample` at the end of the log. The log is opened afresh each
+/// time, so that after another watcher pruned it the sample lands in the
+/// new file rather than the replaced one.
+fn append(path: &Path, sample: &Sample) -> Result<()> {
+    let mut line = serde_json::to_vec(sample).context("Failed to encode history sample")?;
+    line.push(b'\n');
+    let _lock = LogLock::acquire(path)?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open history {}", path.display()))?;
+    // One write, so that a reader never sees half a line
+    file.write_all(&line).with_context(|| format!("Failed to write history {}", path.display()))
+}
+
+/// How long a writer waits for another one to finish with the log
+const LOCK_WAIT: Duration = Duration::from_secs(2);
+/// A lock this old was left by a watcher that died holding it
+const STALE_LOCK: Duration = Duration::from_secs(30);
+
+/// `history.jsonl.lock`, held while the log is written. Every watcher's
+/// appends and the rewrite that prunes the log take turns through it, so a
+/// rewrite can't drop samples another watcher appends meanwhile.
+struct LogLock {
+    path: PathBuf,
+}
+
+impl LogLock {
+    fn acquire(log: &Path) -> Result<LogLock> {
+        let path = log.with_extension("jsonl.lock");
+        let started = Instant::now();
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(_) => return Ok(LogLock { path }),
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    let age = fs::metadata(&path).and_then(|meta| meta.modified()).ok().and_then(|at| at.elapsed().ok());
+                    if age.is_some_and(|age| age > STALE_LOCK) {
+                        let _ = fs::remove_file(&path);
+                        continue;
+                    }
+                    if started.elapsed() > LOCK_WAIT {
+                        bail!("{} is held by another watcher", path.display());
+                    }
+                    std::thread::sleep(Duration::from_millis(20));
+                }
+                Err(e) => return Err(e).with_context(|| format!("Failed to create {}", path.display())),
+            }
+        }
+    }
+}
+
+impl Drop for LogLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+fn cutoff(since: Duration) -> DateTime<Utc> {
+    chrono::Duration::from_std(since)
+        .ok()
+        .and_then(|d| Utc::now().checked_sub_signed(d))
+        .unwrap_or(DateTime::<Utc>::MIN_UTC)
+}
+
+/// Samples in `path` from `cutoff` on, skipping lines that don't parse
+fn read_from(path: &Path, cutoff: DateTime<Utc>) -> Result<Vec<Sample>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to open {}", path.display())),
+    };
+    let mut samples = Vec::new();
+    for line in BufReader::new(file).split(b'\n') {
+        let line = line.with_context(|| format!("Failed to read {}", path.display()))?;
+        if let Ok(sample) = serde_json::from_slice::<Sample>(&line) {
+            if sample.timestamp >= cutoff {
+                samples.push(sample);
+            }
+        }
+    }
+    Ok(samples)
+}
+
+/// Reads recorded samples newer than `since`, oldest first
+pub fn read_since(since: Duration) -> Result<Vec<Sample>> {
+    match log_path() {
+        Some(path) => read_from(&path, cutoff(since)),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Rewrites the log without samples older than `retention`, holding the
+/// [`LogLock`]. Only the first line is read unless it is more than a day
+/// past the cutoff, so most starts don't touch the rest of the file.
+fn prune(path: &Path, retention: Duration) -> Result<()> {
+    let cutoff = cutoff(retention);
+    let first = match File::open(path) {
+        Ok(file) => BufReader::new(file).lines().next().transpose().context("Failed to read history")?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to open {}", path.display())),
+    };
+    let oldest = first.and_then(|line| serde_json::from_str::<Sample>(&line).ok()).map(|s| s.timestamp);
+    if !oldest.map(|at| at < cutoff - chrono::Duration::days(1)).unwrap_or(false) {
+        return Ok(());
+    }
+
+    let _lock = LogLock::acquire(path)?;
+    let samples = read_from(path, cutoff)?;
+    let temp = path.with_extension("jsonl.tmp");
+    let mut out = BufWriter::new(
+        File::create(&temp).with_context(|| format!("Failed to create {}", temp.display()))?,
+    );
+    for sample in &samples {
+        serde_json::to_writer(&mut out, sample).context("Failed to encode history sample")?;
+        writeln!(out).context("Failed to write history")?;
+    }
+    out.flush().context("Failed to write history")?;
+    drop(out);
+    fs::rename(&temp, path).with_context(|| format!("Failed to replace {}", path.display()))
+}
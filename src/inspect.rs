@@ -0,0 +1,184 @@
+use crate::{
+    intern::Name,
+    apply_tag_filter, endpoints::fetch_data, get_account_status, get_short_email, history, http_client, parse_reset,
+    Account, Args, HumanDuration, ModelQuota, RenderOptions, Timestamp, BOLD, DIM, GREEN, RED, RESET, YELLOW,
+};
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Local, Utc};
+use clap::Args as ClapArgs;
+use std::{
+    fmt::{self, Write as _},
+    time::Duration,
+};
+
+#[derive(ClapArgs, Debug)]
+pub struct InspectArgs {
+    /// Account email, or the part before the `@`
+    pub email: String,
+
+    /// Only this model [default: every model the account reports]
+    #[arg(short, long)]
+    pub model: Option<String>,
+
+    /// How far back the history sparkline reaches
+    #[arg(long, default_value = "6h", value_parser = humantime::parse_duration)]
+    pub since: Duration,
+}
+
+/// Sparkline cells, one bucket of history each
+const SPARK_WIDTH: usize = 48;
+const SPARK_LEVELS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// `▁▃▅█`-style line of `email`'s remaining fraction on `model` over the
+/// window, from the samples watch mode recorded; blank where none fall
+fn sparkline(samples: &[history::Sample], email: &str, model: &str, from: DateTime<Utc>, to: DateTime<Utc>) -> String {
+    let span = (to - from).num_seconds().max(1) as f64;
+    let mut buckets: [Option<f64>; SPARK_WIDTH] = [None; SPARK_WIDTH];
+    for sample in samples {
+        let Some(fraction) = sample.quotas.get(email).and_then(|q| q.get(model)) else {
+            continue;
+        };
+        let offset = (sample.timestamp - from).num_seconds() as f64 / span;
+        let index = ((offset * SPARK_WIDTH as f64) as usize).min(SPARK_WIDTH - 1);
+        // The last sample in a bucket wins
+        buckets[index] = Some(*fraction);
+    }
+    buckets
+        .iter()
+        .map(|bucket| match bucket {
+            Some(f) => SPARK_LEVELS[((f.clamp(0.0, 1.0) * (SPARK_LEVELS.len() - 1) as f64).round()) as usize],
+            None => ' ',
+        })
+        .collect()
+}
+
+/// `2026-10-14 15:33 local, 13:33 UTC`
+struct LocalAndUtc(DateTime<Utc>);
+
+impl fmt::Display for LocalAndUtc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} local, {} UTC",
+            self.0.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S"),
+            self.0.format("%Y-%m-%d %H:%M:%S")
+        )
+    }
+}
+
+fn render_account(
+    out: &mut String,
+    account: &Account,
+    inspect_args: &InspectArgs,
+    opts: &RenderOptions,
+    samples: &[history::Sample],
+    now: DateTime<Utc>,
+) -> fmt::Result {
+    let (status, color) = get_account_status(account);
+    let label = opts.config.status.label(status);
+    write!(out, "{}{}{} {}{}{}", BOLD, account.email, RESET, color, label, RESET)?;
+    if let Some(ref origin) = account.origin {
+        write!(out, " {}({}){}", DIM, origin, RESET)?;
+    }
+    writeln!(out)?;
+    match account.last_used {
+        Some(ms) => match DateTime::from_timestamp_millis(ms as i64) {
+            Some(at) => writeln!(out, "Last used:   {} ({} ago)", Timestamp(ms), HumanDuration(now - at))?,
+            None => writeln!(out, "Last used:   {}", Timestamp(ms))?,
+        },
+        None => writeln!(out, "Last used:   never")?,
+    }
+    if let Some(at) = account.credentials_expiry() {
+        writeln!(out, "Credentials: expire {}", LocalAndUtc(at))?;
+    }
+
+    let mut models: Vec<(&Name, &ModelQuota)> = account
+        .limits
+        .iter()
+        .flatten()
+        .filter(|(model, _)| inspect_args.model.as_ref().map_or(true, |m| m == *model))
+        .collect();
+    models.sort_by(|a, b| a.0.cmp(b.0));
+    if models.is_empty() {
+        writeln!(out, "{}No quotas reported{}", DIM, RESET)?;
+    }
+    let from = now - chrono::Duration::from_std(inspect_args.since).unwrap_or_else(|_| chrono::Duration::hours(6));
+    for (model, quota) in models {
+        let fraction = quota.remaining_fraction;
+        let fraction_color = if fraction <= 0.0 {
+            RED
+        } else if fraction < opts.config.thresholds.quota_warning {
+            YELLOW
+        } else {
+            GREEN
+        };
+        writeln!(out, "\n{}{}{}", BOLD, model, RESET)?;
+        write!(out, "  Remaining:  {}{:.4}{} ({:.1}%)", fraction_color, fraction, RESET, fraction * 100.0)?;
+        if let (Some(remaining), Some(limit)) = (quota.remaining, quota.limit) {
+            write!(out, ", {}/{} requests", remaining, limit)?;
+        }
+        writeln!(out)?;
+        match parse_reset(quota) {
+            Some(at) if at > now => writeln!(out, "  Resets:     {} (in {})", LocalAndUtc(at), HumanDuration(at - now))?,
+            Some(at) => writeln!(out, "  Resets:     {} ({} ago)", LocalAndUtc(at), HumanDuration(now - at))?,
+            None => writeln!(out, "  Resets:     {}unknown{}", DIM, RESET)?,
+        }
+        match account.model_rate_limits.as_ref().and_then(|r| r.get(model.as_str())) {
+            Some(limit) if limit.is_rate_limited => {
+                write!(out, "  Rate limit: {}limited{}", YELLOW, RESET)?;
+                if let Some(status) = limit.upstream_status {
+                    write!(out, ", upstream {}", status)?;
+                }
+                if let Some(ref reason) = limit.reason {
+                    write!(out, ", {}", reason)?;
+                }
+                if let Some(secs) = limit.retry_after {
+                    write!(out, ", retry after {}", HumanDuration(chrono::Duration::seconds(secs as i64)))?;
+                }
+                writeln!(out)?;
+            }
+            _ => writeln!(out, "  Rate limit: none")?,
+        }
+        if !samples.is_empty() {
+            let label = format!("Last {}:", humantime::format_duration(inspect_args.since));
+            writeln!(
+                out,
+                "  {:<12}{}{}{}",
+                label,
+                DIM,
+                sparkline(samples, &account.email, model, from, now),
+                RESET
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// `inspect` subcommand: everything known about one account, per model
+pub async fn run(args: &Args, opts: &RenderOptions, inspect_args: &InspectArgs) -> Result<()> {
+    let client = http_client(args)?;
+    let mut data = fetch_data(&client, args, &opts.config, |_, _| Ok(())).await?;
+    apply_tag_filter(&mut data, opts);
+    let wanted = inspect_args.email.to_lowercase();
+    let matches: Vec<&Account> = data
+        .accounts
+        .iter()
+        .filter(|a| a.email.to_lowercase() == wanted || get_short_email(&a.email).to_lowercase() == wanted)
+        .collect();
+    if matches.is_empty() {
+        bail!("No account {} in the response", inspect_args.email);
+    }
+
+    let now = Utc::now();
+    let samples = history::read_since(inspect_args.since)?;
+    let mut out = String::new();
+    // The same email behind several merged endpoints shows once per endpoint
+    for (i, account) in matches.into_iter().enumerate() {
+        if i > 0 {
+            writeln!(out)?;
+        }
+        render_account(&mut out, account, inspect_args, opts, &samples, now).context("Failed to render account")?;
+    }
+    print!("{}", out);
+    Ok(())
+}
@@ -0,0 +1,149 @@
+use std::{env, fmt, sync::OnceLock};
+
+static CURRENT: OnceLock<Locale> = OnceLock::new();
+
+/// Date and number conventions for everything the tables print
+#[derive(Debug, Clone, Copy)]
+pub struct Locale {
+    /// Full timestamp, e.g. in the header
+    pub date_time: &'static str,
+    /// Timestamp without the year, e.g. in the events table
+    pub short_date_time: &'static str,
+    /// Time of day without seconds, e.g. in forecasts
+    pub clock: &'static str,
+    /// `25 %` instead of `25%`
+    pub percent_space: bool,
+    /// `0,5` instead of `0.5`
+    pub decimal_comma: bool,
+    /// Decimal places in percentages (`--precision`)
+    pub precision: usize,
+    /// Durations of an hour or more without seconds, of a day or more without minutes (`--round-durations`)
+    pub round_durations: bool,
+}
+
+/// US style, also used for `C`/`POSIX` and when nothing is set
+const US: Locale = Locale {
+    date_time: "%-m/%-d/%Y, %-I:%M:%S %p",
+    short_date_time: "%-m/%-d %-I:%M:%S %p",
+    clock: "%-I:%M %p",
+    percent_space: false,
+    decimal_comma: false,
+    precision: 0,
+    round_durations: false,
+};
+
+/// Date formats by language (or `lang_REGION`), all with a 24h clock
+const DATE_FORMATS: &[(&[&str], &str, &str)] = &[
+    (&["de", "ru", "pl", "cs", "fi", "nb", "tr", "uk"], "%d.%m.%Y, %H:%M:%S", "%d.%m. %H:%M:%S"),
+    (&["en_GB", "en_AU", "en_NZ", "en_IE", "fr", "es", "it", "pt", "el"], "%d/%m/%Y, %H:%M:%S", "%d/%m %H:%M:%S"),
+    (&["nl"], "%d-%m-%Y %H:%M:%S", "%d-%m %H:%M:%S"),
+    (&["ja", "zh", "ko"], "%Y/%m/%d %H:%M:%S", "%m/%d %H:%M:%S"),
+];
+
+/// Languages that write a decimal comma and put a space before `%`
+const COMMA_LANGUAGES: &[&str] = &[
+    "de", "fr", "es", "it", "pt", "nl", "ru", "pl", "cs", "sv", "fi", "nb", "da", "tr", "uk", "el",
+];
+
+/// First non-empty variable among `names`, like libc's LC_ALL > LC_* > LANG lookup
+fn env_locale(names: &[&str]) -> Option<String> {
+    names
+        .iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|value| !value.is_empty())
+}
+
+/// `de_DE.UTF-8@euro` -> (`de`, `de_DE`)
+fn language_and_region(name: &str) -> (&str, &str) {
+    let base = name.split(['.', '@']).next().unwrap_or(name);
+    let language = base.split(['_', '-']).next().unwrap_or(base);
+    (language, base)
+}
+
+fn is_us(name: &str) -> bool {
+    let (language, base) = language_and_region(name);
+    matches!(name, "C" | "POSIX") || base == "en_US" || (language == "en" && base == "en")
+}
+
+impl Locale {
+    /// Locale for `--locale`, or from the environment with `None`
+    pub fn detect(name: Option<&str>) -> Locale {
+        let time = name.map(str::to_string).or_else(|| env_locale(&["LC_ALL", "LC_TIME", "LANG"]));
+        let numeric = name.map(str::to_string).or_else(|| env_locale(&["LC_ALL", "LC_NUMERIC", "LANG"]));
+
+        let mut locale = US;
+        if let Some(time) = time.as_deref().filter(|t| !is_us(t)) {
+            let (language, base) = language_and_region(time);
+            let (date_time, short_date_time) = DATE_FORMATS
+                .iter()
+                .find(|(names, _, _)| names.contains(&base) || names.contains(&language))
+                .map(|(_, long, short)| (*long, *short))
+                .unwrap_or(("%Y-%m-%d %H:%M:%S", "%m-%d %H:%M:%S"));
+            locale.date_time = date_time;
+            locale.short_date_time = short_date_time;
+            locale.clock = "%H:%M";
+        }
+        if let Some(numeric) = numeric.as_deref() {
+            let (language, _) = language_and_region(numeric);
+            let comma = COMMA_LANGUAGES.contains(&language);
+            locale.percent_space = comma;
+            locale.decimal_comma = comma;
+        }
+        locale
+    }
+}
+
+/// Sets the process-wide locale and number style; later calls are ignored
+pub fn init(name: Option<&str>, precision: usize, round_durations: bool) {
+    let locale = Locale {
+        precision,
+        round_durations,
+        ..Locale::detect(name)
+    };
+    let _ = CURRENT.set(locale);
+}
+
+/// The locale set by [`init`], US style if it was never called
+pub fn current() -> &'static Locale {
+    CURRENT.get().unwrap_or(&US)
+}
+
+/// A fraction as a rounded percentage, e.g. `25%`, `25 %` or `24.6%`. A
+/// fraction above zero that rounds to nothing shows as `<1%`, since some
+/// quota is left.
+pub struct Percent(pub f64);
+
+impl fmt::Display for Percent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let locale = current();
+        let value = self.0 * 100.0;
+        let scale = 10f64.powi(locale.precision as i32);
+        let rounded = (value * scale).round() / scale;
+        let mut text = if rounded == 0.0 && value > 0.0 {
+            format!("<{:.*}", locale.precision, 1.0 / scale)
+        } else {
+            format!("{:.*}", locale.precision, rounded)
+        };
+        if locale.decimal_comma {
+            text = text.replace('.', ",");
+        }
+        if locale.percent_space {
+            write!(f, "{} %", text)
+        } else {
+            write!(f, "{}%", text)
+        }
+    }
+}
+
+/// A decimal number with the locale's separator, e.g. `0.5` or `0,5`
+pub struct Decimal(pub f64);
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if current().decimal_comma {
+            write!(f, "{}", self.0.to_string().replace('.', ","))
+        } else {
+            write!(f, "{}", self.0)
+        }
+    }
+}
@@ -1,46 +1,539 @@
+mod accounts;
+mod api;
+mod alarm;
+#[cfg(feature = "keyring")]
+mod auth;
+mod capacity;
+mod changes;
+mod check;
+mod config;
+mod discord;
+mod discover;
+#[cfg(feature = "email")]
+mod email;
+mod endpoints;
+mod error;
+mod events;
+mod export;
+mod fanout;
+mod expr;
+mod forecast;
+mod gotify;
+mod health;
+mod heartbeat;
+mod history;
+mod inspect;
+mod intern;
+mod locale;
+mod motd;
+mod notify;
+mod pager;
+mod pages;
+mod pick;
+mod problems;
+mod serve;
+mod proxy;
+mod pushover;
+mod report;
+mod resize;
+mod roster;
+mod schema;
+#[cfg(feature = "scripting")]
+mod script;
+mod session;
+mod slack;
+mod state;
+mod template;
+mod telegram;
+mod tunnel;
+mod wait;
+mod watch_until;
+#[cfg(feature = "xmpp")]
+mod xmpp;
+mod zabbix;
+
 use anyhow::{Context, Result};
 use chrono::{DateTime, Local, Utc};
-use clap::Parser;
-use serde::Deserialize;
-use std::{collections::HashMap, time::Duration};
+use clap::{Parser, Subcommand, ValueEnum};
+use serde::{Deserialize, Serialize};
+use alarm::{BudgetAlarm, ModelAlarm};
+use api::ApiVersion;
+use capacity::CapacityEstimator;
+use changes::{ChangeMarker, QuotaDelta};
+use config::{Config, HistoryBackend, ModelGroup, ModelsConfig, StatusPolicy, Thresholds};
+use endpoints::{fetch_data, render_endpoints, DedupePolicy, EndpointStatus};
+use error::FetchError;
+use events::EventTracker;
+use intern::Name;
+use fanout::FetchStrategy;
+use expr::{AccountScope, Expr, FleetScope};
+use forecast::{Forecast, Forecaster};
+use health::FetchHealth;
+use heartbeat::Heartbeat;
+use history::HistoryRecorder;
+use notify::Notifier;
+use report::LimitedTime;
+use resize::Resize;
+use roster::Roster;
+use locale::{Decimal, Percent};
+use session::SessionStats;
+use state::{Digest, Snapshot};
+use template::Template;
+use std::{
+    borrow::{Borrow, Cow},
+    collections::{BTreeSet, HashMap, HashSet},
+    fmt::{self, Write as _},
+    fs::{File, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+use unicode_width::UnicodeWidthStr;
 
 #[derive(Parser, Debug)]
 #[command(name = "ag-quota")]
 #[command(about = "CLI tool for displaying Antigravity account usage and quotas")]
 #[command(version)]
 struct Args {
-    /// API URL to fetch account data from
-    #[arg(short, long, default_value = "http://localhost:8040/account-limits")]
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// API URL to fetch account data from; repeat to merge several proxies into one view [default: http://localhost:8040/account-limits]
+    #[arg(short = 'u', long = "url", value_name = "URL", global = true)]
+    url_flag: Vec<String>,
+
+    /// `--url`s, else the profile's, else the default
+    #[arg(skip)]
+    urls: Vec<String>,
+
+    /// The first of `urls`, for commands that talk to a single proxy
+    #[arg(skip)]
     url: String,
 
-    /// Refresh interval in seconds (0 to disable auto-refresh)
-    #[arg(short, long, default_value = "5")]
-    interval: u64,
+    /// What to call each of `urls` when several are merged
+    #[arg(skip)]
+    labels: Vec<String>,
+
+    /// Bearer token sent with every request
+    #[arg(long, env = "AG_TOKEN", hide_env_values = true, global = true)]
+    token: Option<String>,
+
+    /// Reach the URL through this HTTP or SOCKS5 proxy, e.g. socks5h://localhost:1080 [default: from HTTPS_PROXY/ALL_PROXY]
+    #[arg(long = "proxy", value_name = "PROXY_URL", global = true)]
+    outbound_proxy: Option<String>,
+
+    /// Reach the URL through an SSH port-forward via this host, e.g. user@build-box; the URL is resolved on that host
+    #[arg(long, value_name = "DESTINATION", global = true, conflicts_with = "outbound_proxy")]
+    ssh: Option<String>,
+
+    /// When a rate-limited model marks its account limited [default: models.status_policy in the config, else primary]
+    #[arg(long, value_enum, global = true)]
+    status_policy: Option<StatusPolicy>,
+
+    /// How to show an email that several --urls report
+    #[arg(long, value_enum, default_value_t = DedupePolicy::Separate, global = true)]
+    dedupe: DedupePolicy,
+
+    /// Without a configured URL, look for a proxy on the usual local ports and use the first one found
+    #[arg(long, global = true, conflicts_with_all = ["url_flag", "ssh"])]
+    discover: bool,
+
+    /// Config profile to use ([profile.NAME] in the config)
+    #[arg(short = 'P', long, env = "AG_PROFILE", global = true)]
+    profile: Option<String>,
+
+    /// Refresh interval, e.g. 5, 500ms, 2m or 1h30s; bare numbers are seconds (0 to disable auto-refresh)
+    #[arg(short, long, default_value = "5", value_parser = parse_interval)]
+    interval: Duration,
 
     /// Run once and exit (no auto-refresh)
     #[arg(short, long)]
     once: bool,
+
+    /// Append every fetched response to a JSONL session file
+    #[arg(long, value_name = "FILE", conflicts_with = "replay")]
+    record: Option<String>,
+
+    /// Play back a recorded session file instead of fetching
+    #[arg(long, value_name = "FILE")]
+    replay: Option<String>,
+
+    /// Check the live response (or FILE) against the `schema` and report every mismatch
+    #[arg(long, value_name = "FILE", num_args = 0..=1, conflicts_with = "replay")]
+    validate: Option<Option<PathBuf>>,
+
+    /// Replay speed multiplier (e.g. 10x, 0.5x)
+    #[arg(long, default_value = "1x", value_parser = parse_speed, requires = "replay")]
+    speed: f64,
+
+    /// Keep these accounts (emails or the part before the `@`) at the top of both tables, e.g. alice,bob
+    #[arg(long, value_name = "ACCOUNTS", value_delimiter = ',', global = true)]
+    pin: Vec<String>,
+
+    /// Only show accounts with this tag from the config (repeatable)
+    #[arg(short, long = "tag", value_name = "TAG", global = true)]
+    tags: Vec<String>,
+
+    /// Only show accounts for which this expression holds, e.g. "quota('gemini') < 0.1 && status != 'invalid'"
+    #[arg(long, value_name = "EXPR", value_parser = expr::parse_account, global = true)]
+    filter_expr: Option<Expr>,
+
+    /// Exit with status 1 once this expression over the fleet holds, e.g. "available < 2"
+    #[arg(long, value_name = "EXPR", value_parser = expr::parse_fleet)]
+    fail_when: Option<Expr>,
+
+    /// Ring the terminal bell when this model runs out of available accounts
+    /// and again when capacity returns (repeatable)
+    #[arg(long, value_name = "MODEL")]
+    watch_model: Vec<String>,
+
+    /// Show --watch-model alerts in inverse video
+    #[arg(long, requires = "watch_model")]
+    flash: bool,
+
+    /// Post state changes, and a daily summary at `[notify] summary_at`, to this Slack incoming webhook
+    #[arg(long, value_name = "URL", env = "AG_SLACK_WEBHOOK", hide_env_values = true)]
+    slack_webhook: Option<String>,
+
+    /// Post state changes as color-coded embeds to this Discord webhook
+    #[arg(long, value_name = "URL", env = "AG_DISCORD_WEBHOOK", hide_env_values = true)]
+    discord_webhook: Option<String>,
+
+    /// Don't highlight cells and statuses that changed since the previous refresh
+    #[arg(long)]
+    no_highlight: bool,
+
+    /// Compare the accounts table's Δ column with the refresh this long ago, e.g. 10m [default: the previous one]
+    #[arg(long, value_name = "DURATION", value_parser = humantime::parse_duration)]
+    delta_window: Option<Duration>,
+
+    /// Re-fetch right away when an exhausted quota's reset time has passed
+    #[arg(long)]
+    refetch_overdue: bool,
+
+    /// Randomly vary each refresh interval by up to this much (e.g. 20%), so
+    /// dashboards sharing a proxy don't poll in lockstep
+    #[arg(long, value_name = "PERCENT", default_value = "0%", value_parser = parse_jitter)]
+    jitter: f64,
+
+    /// How far the refresh interval may grow while fetches keep failing; it
+    /// doubles per failure and shrinks back once the proxy answers again
+    #[arg(long, value_name = "DURATION", default_value = "5m", value_parser = humantime::parse_duration)]
+    max_backoff: Duration,
+
+    /// Ping this URL after successful refreshes (at most once a minute), for a dead man's switch such as Healthchecks.io
+    #[arg(long, value_name = "URL", env = "AG_HEARTBEAT_URL", hide_env_values = true, global = true)]
+    heartbeat_url: Option<String>,
+
+    /// Give up on responses larger than this (e.g. 512K, 8M) instead of parsing them
+    #[arg(long, value_name = "SIZE", global = true, default_value = "8M", value_parser = parse_size)]
+    max_body_size: u64,
+
+    /// Follow a paginating proxy's `next` cursor or `page` numbers for at most this many pages
+    #[arg(long, value_name = "N", global = true, default_value_t = 20, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    max_pages: usize,
+
+    /// Response shape of the limits endpoint: v1 wrapped in `{"result": "..."}`, v2 plain JSON [default: [proxy] api_version in the config, else auto]
+    #[arg(long, value_enum, value_name = "VERSION", global = true)]
+    api_version: Option<ApiVersion>,
+
+    /// Reject responses with fields the schema doesn't describe, or without any of its optional ones
+    #[arg(long, global = true)]
+    strict: bool,
+
+    /// How each URL's accounts are fetched: all at once, or listed and then fetched one by one
+    #[arg(long, value_enum, value_name = "STRATEGY", global = true, default_value_t = FetchStrategy::Bulk)]
+    fetch: FetchStrategy,
+
+    /// Requests in flight at once with --fetch per-account
+    #[arg(long, value_name = "N", global = true, default_value_t = 8, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    concurrency: usize,
+
+    /// Never pipe one-shot output through $PAGER
+    #[arg(long)]
+    no_pager: bool,
+
+    /// Show how the response began when it doesn't parse
+    #[arg(long, global = true)]
+    debug: bool,
+
+    /// Accounts per page in watch mode, cycling pages on each refresh ("auto" fits the terminal)
+    #[arg(long, value_name = "N", value_parser = parse_page_size)]
+    page_size: Option<PageSize>,
+
+    /// Only print the header block (counts, severity, next reset, worst account)
+    #[arg(long)]
+    summary_only: bool,
+
+    /// Only print accounts and models that are rate-limited, invalid or below the quota warning; nothing when all is well
+    #[arg(long, conflicts_with_all = ["summary_only", "layout"])]
+    problems_only: bool,
+
+    /// Which sections the table output shows
+    #[arg(long, value_enum, default_value_t = Layout::Full, conflicts_with = "summary_only")]
+    layout: Layout,
+
+    /// Also print a one-line JSON digest (counts, worst account, next reset) to stderr per refresh
+    #[arg(long)]
+    summary_json_stderr: bool,
+
+    /// Output format; json/jsonl print the computed state instead of tables
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Table)]
+    format: OutputFormat,
+
+    /// Render each refresh through a MiniJinja (Jinja2-style) template instead of tables
+    #[arg(long, value_name = "FILE", conflicts_with = "format")]
+    template: Option<PathBuf>,
+
+    /// What a failed fetch prints instead of the error: `value:TEXT` prints just TEXT (e.g. `value:AG ?`), for status bars
+    #[arg(long, value_name = "MODE", value_parser = parse_on_error)]
+    on_error: Option<OnError>,
+
+    /// Rhai script whose hooks add header alerts, extra rows or an account order each refresh
+    #[cfg(feature = "scripting")]
+    #[arg(long, value_name = "FILE")]
+    script: Option<PathBuf>,
+
+    /// Account ordering in both tables
+    #[arg(long, value_enum, default_value_t = SortKey::Server)]
+    sort: SortKey,
+
+    /// Show absolute request counts (e.g. 123/500) when the proxy reports them,
+    /// or an estimate (e.g. 25% ~12) inferred from the limit or observed steps
+    #[arg(long)]
+    show_counts: bool,
+
+    /// Config file [default: ~/.config/ag-quota/config.toml]
+    #[arg(short, long, value_name = "FILE", global = true)]
+    config: Option<PathBuf>,
+
+    /// Date and number formatting, e.g. de_DE or en_US [default: from LC_ALL/LC_TIME/LANG]
+    #[arg(long, global = true)]
+    locale: Option<String>,
+
+    /// Decimal places in percentages
+    #[arg(long, default_value_t = 0, global = true, value_parser = clap::value_parser!(u8).range(0..=3))]
+    precision: u8,
+
+    /// Leave seconds off durations of an hour or more, and minutes off those of a day or more
+    #[arg(long, global = true)]
+    round_durations: bool,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Print the email of the best account to use next
+    Pick(pick::PickArgs),
+    /// Show account state transitions recorded by watch mode
+    Events(events::EventsArgs),
+    /// Poll continuously and serve a dashboard and the state over HTTP
+    Serve(serve::ServeArgs),
+    /// Print the JSON Schema expected from the proxy, or with `diff` how a response drifts from it
+    Schema(schema::SchemaArgs),
+    /// Inspect the proxy itself
+    Proxy(proxy::ProxyArgs),
+    /// Diagnose the setup: the proxy's health, version and accounts, and whether the clocks agree
+    Doctor,
+    /// Add, remove or re-check accounts through the proxy's management API
+    Accounts(accounts::AccountsArgs),
+    /// Summarize or export the quota history recorded by watch mode
+    #[command(visible_alias = "history")]
+    Report(report::ReportArgs),
+    /// Show everything known about one account: exact quotas, resets, rate limits, recent history
+    Inspect(inspect::InspectArgs),
+    /// Block until enough accounts have quota, then print their emails
+    Wait(wait::WaitArgs),
+    /// Poll quietly and exit the first time a fleet condition turns true (or false)
+    WatchUntil(watch_until::WatchUntilArgs),
+    /// Push the live state to another monitoring system on every refresh (`export zabbix`)
+    Export {
+        #[command(subcommand)]
+        command: ExportCommand,
+    },
+    /// Nagios/Icinga plugin: one status line with perfdata, exit status 0-3 from the thresholds
+    Check(check::CheckArgs),
+    /// Print a short status banner for login messages and shell rc files, from cache when the proxy is slow
+    Motd(motd::MotdArgs),
+    /// Manage bearer tokens stored in the system keyring
+    #[cfg(feature = "keyring")]
+    Auth(auth::AuthArgs),
+}
+
+/// Where `export` feeds the live state; `report export` writes out the recorded history
+#[derive(Subcommand, Debug)]
+enum ExportCommand {
+    /// Push fleet, per-model and per-account items to a Zabbix server or proxy every refresh
+    Zabbix(zabbix::ZabbixArgs),
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    /// Colored tables, redrawn in place
+    Table,
+    /// One pretty-printed JSON document per refresh
+    Json,
+    /// One compact JSON object per line per refresh, never clearing the screen
+    Jsonl,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum SortKey {
+    /// Order returned by the proxy
+    #[default]
+    Server,
+    /// Alphabetical by email
+    Name,
+    /// Soonest upcoming quota reset first
+    Reset,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum Layout {
+    /// Header with capacity bars, both tables, rate-limit details and the footer
+    #[default]
+    Full,
+    /// Header without bars and the model matrix, with a one-line footer
+    Compact,
+    /// Only the model matrix
+    MatrixOnly,
+    /// Only the accounts table
+    AccountsOnly,
+}
+
+impl Layout {
+    fn header(self) -> bool {
+        matches!(self, Layout::Full | Layout::Compact)
+    }
+
+    fn accounts(self) -> bool {
+        matches!(self, Layout::Full | Layout::AccountsOnly)
+    }
+
+    fn matrix(self) -> bool {
+        matches!(self, Layout::Full | Layout::Compact | Layout::MatrixOnly)
+    }
+
+    /// Capacity bars, rate-limit details and fetch health
+    fn details(self) -> bool {
+        self == Layout::Full
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PageSize {
+    Fixed(usize),
+    Auto,
+}
+
+/// `--on-error`: how a failed fetch shows in the output
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum OnError {
+    /// This text in place of the error
+    Value(String),
+}
+
+fn parse_on_error(s: &str) -> Result<OnError, String> {
+    match s.split_once(':') {
+        Some(("value", text)) => Ok(OnError::Value(text.to_string())),
+        _ => Err(format!("invalid --on-error '{}', expected value:TEXT", s)),
+    }
+}
+
+fn parse_page_size(s: &str) -> Result<PageSize, String> {
+    if s.eq_ignore_ascii_case("auto") {
+        return Ok(PageSize::Auto);
+    }
+    match s.parse::<usize>() {
+        Ok(n) if n > 0 => Ok(PageSize::Fixed(n)),
+        _ => Err(format!("invalid page size '{}', expected a positive number or 'auto'", s)),
+    }
+}
+
+/// One line of a `--record` session file
+#[derive(Debug, Serialize, Deserialize)]
+struct RecordedFrame<'a> {
+    timestamp: DateTime<Utc>,
+    body: Cow<'a, str>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
 struct ModelRateLimit {
     #[serde(rename = "isRateLimited")]
     is_rate_limited: bool,
+    #[serde(default)]
+    reason: Option<String>,
+    /// Seconds until the upstream allows another request
+    #[serde(rename = "retryAfter", default)]
+    retry_after: Option<u64>,
+    /// HTTP status of the upstream rejection
+    #[serde(rename = "upstreamStatus", default)]
+    upstream_status: Option<u16>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
 struct Account {
-    email: String,
+    email: Name,
     #[serde(default)]
     enabled: Option<bool>,
     #[serde(rename = "limits")]
-    limits: Option<HashMap<String, ModelQuota>>,
+    limits: Option<HashMap<Name, ModelQuota>>,
     #[serde(rename = "modelRateLimits")]
-    model_rate_limits: Option<HashMap<String, ModelRateLimit>>,
+    model_rate_limits: Option<HashMap<Name, ModelRateLimit>>,
     #[serde(rename = "isInvalid")]
     is_invalid: Option<bool>,
     #[serde(rename = "lastUsed")]
     last_used: Option<u64>,
+    /// When the account's token or cookie stops working, if the proxy knows
+    #[serde(rename = "credentialsExpireAt", alias = "tokenExpiresAt", alias = "expiresAt", default)]
+    credentials_expire_at: Option<ExpiryTime>,
+    /// Seconds rate-limited over the last day, filled in by `LimitedTime` from history
+    #[serde(skip)]
+    limited_secs: Option<i64>,
+    /// Status differs from the previous refresh (set by `ChangeMarker`)
+    #[serde(skip)]
+    status_changed: bool,
+    /// Models whose remaining fraction differs from the previous refresh
+    #[serde(skip)]
+    changed_models: HashSet<Name>,
+    /// Label of the endpoint the account came from, when several were merged
+    #[serde(skip)]
+    origin: Option<String>,
+    /// Change of the lowest remaining fraction since the baseline refresh (set by `QuotaDelta`)
+    #[serde(skip)]
+    quota_delta: Option<f64>,
+    /// Counts as limited under the status policy (set when the response is parsed)
+    #[serde(skip)]
+    limited: bool,
+}
+
+/// Expiry as milliseconds since the epoch or an RFC 3339 string
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum ExpiryTime {
+    Millis(i64),
+    Text(String),
+}
+
+impl Account {
+    fn credentials_expiry(&self) -> Option<DateTime<Utc>> {
+        match self.credentials_expire_at.as_ref()? {
+            ExpiryTime::Millis(ms) => DateTime::from_timestamp_millis(*ms),
+            ExpiryTime::Text(text) => DateTime::parse_from_rfc3339(text).ok().map(|d| d.with_timezone(&Utc)),
+        }
+    }
+
+    /// Sets `limited` from the rate limits under the configured status policy
+    fn update_limited(&mut self, models: &ModelsConfig) {
+        let quotas: Vec<&Name> = self.limits.iter().flatten().map(|(model, _)| model).collect();
+        let rate_limits = self.model_rate_limits.iter().flatten().map(|(m, r)| (m, r.is_rate_limited));
+        self.limited = models.is_limited(rate_limits, &quotas);
+    }
+
+    /// Time left on the credentials when it is under the configured warning window
+    fn credentials_expiring(&self, now: DateTime<Utc>, thresholds: &Thresholds) -> Option<chrono::Duration> {
+        let left = self.credentials_expiry()? - now;
+        (left < chrono::Duration::hours(thresholds.credentials_warning_hours as i64)).then_some(left)
+    }
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -49,18 +542,81 @@ struct ModelQuota {
     remaining_fraction: f64,
     #[serde(rename = "resetTime")]
     reset_time: Option<String>,
+    /// Requests left in the current window, if the proxy reports counts
+    #[serde(default)]
+    remaining: Option<u64>,
+    /// Requests allowed per window, if the proxy reports counts
+    #[serde(default)]
+    limit: Option<u64>,
+    /// Requests left as inferred by `CapacityEstimator` when `remaining` is missing
+    #[serde(skip)]
+    estimated_remaining: Option<u64>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
-struct ApiResponseWrapper {
-    result: String,
+/// Display settings shared by the renderers, merged from flags and config
+#[derive(Debug, Clone, Default)]
+struct RenderOptions {
+    tag_filter: Vec<String>,
+    filter_expr: Option<Expr>,
+    summary_only: bool,
+    problems_only: bool,
+    layout: Layout,
+    show_counts: bool,
+    sort: SortKey,
+    pins: Vec<String>,
+    debug: bool,
+    page_size: Option<PageSize>,
+    /// Refresh interval while watching, for judging how stale the data is
+    interval: Option<Duration>,
+    config: Config,
+}
+
+impl RenderOptions {
+    fn new(args: &Args, config: &Config) -> Self {
+        RenderOptions {
+            tag_filter: args.tags.clone(),
+            filter_expr: args.filter_expr.clone(),
+            summary_only: args.summary_only,
+            problems_only: args.problems_only,
+            layout: args.layout,
+            show_counts: args.show_counts,
+            sort: args.sort,
+            pins: args.pin.clone(),
+            debug: args.debug,
+            // Paging only makes sense when frames keep coming
+            page_size: if watching(args) { args.page_size } else { None },
+            interval: watching(args).then_some(args.interval),
+            config: config.clone(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Deserialize)]
 struct ApiResponse {
     timestamp: Option<String>,
     accounts: Vec<Account>,
-    models: Vec<String>,
+    models: Vec<Name>,
+    /// Whether the document came inside a `{"result": "..."}` wrapper
+    #[serde(skip)]
+    wrapped: bool,
+    /// Filled in by `Forecaster` in watch mode
+    #[serde(skip)]
+    forecasts: Vec<Forecast>,
+    /// When this response was fetched (or recorded, on replay)
+    #[serde(skip)]
+    fetched_at: Option<DateTime<Utc>>,
+    /// Per-URL outcome when several were merged
+    #[serde(skip)]
+    endpoints: Vec<EndpointStatus>,
+    /// Header lines from a `--script`'s `alerts` hook
+    #[serde(skip)]
+    script_alerts: Vec<String>,
+    /// Extra table from a `--script`'s `rows` hook
+    #[serde(skip)]
+    script_rows: Vec<Vec<String>>,
+    /// Accounts that came or went since earlier runs (set by `Roster`)
+    #[serde(skip)]
+    roster_changes: roster::RosterChanges,
 }
 
 // ANSI color codes
@@ -71,39 +627,211 @@ const GREEN: &str = "\x1b[32m";
 const YELLOW: &str = "\x1b[33m";
 const CYAN: &str = "\x1b[36m";
 const DIM: &str = "\x1b[2m";
+const INVERSE: &str = "\x1b[7m";
+const CLEAR_SCREEN: &str = "\x1b[2J\x1b[H";
+
+const DEFAULT_URL: &str = "http://localhost:8040/account-limits";
+
+/// Fetches are never closer together than this, whatever the interval or re-fetch triggers
+const MIN_FETCH_SPACING: Duration = Duration::from_millis(250);
 
-fn clear_screen() {
-    print!("\x1b[2J\x1b[H");
+/// How often countdowns are redrawn between fetches
+const COUNTDOWN_TICK: Duration = Duration::from_secs(1);
+
+/// Grace period before re-fetching an overdue reset, so the proxy has a moment to catch up
+const OVERDUE_REFETCH_DELAY: Duration = Duration::from_secs(1);
+
+/// `text` without the terminal color codes the renderers put in
+fn strip_ansi(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // CSI sequences end at their first letter
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
 }
 
 fn get_short_email(email: &str) -> &str {
     email.split('@').next().unwrap_or(email)
 }
 
-fn format_timestamp(ts: u64) -> String {
-    DateTime::from_timestamp_millis(ts as i64)
-        .map(|d| d.with_timezone(&Local).format("%-m/%-d/%Y, %-I:%M:%S %p").to_string())
-        .unwrap_or_else(|| "never".to_string())
+/// Local-time rendering of a millisecond timestamp, or "never" if out of range
+struct Timestamp(u64);
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match DateTime::from_timestamp_millis(self.0 as i64) {
+            Some(d) => write!(f, "{}", d.with_timezone(&Local).format(locale::current().date_time)),
+            None => f.write_str("never"),
+        }
+    }
+}
+
+/// `--interval`: a humantime duration, or whole seconds for backwards compatibility
+fn parse_interval(s: &str) -> Result<Duration, String> {
+    match s.parse::<u64>() {
+        Ok(secs) => Ok(Duration::from_secs(secs)),
+        Err(_) => humantime::parse_duration(s).map_err(|e| format!("invalid interval '{}': {}", s, e)),
+    }
+}
+
+/// `--jitter`: a percentage such as `20%` (or `20`) of the interval, up to 100%
+fn parse_jitter(s: &str) -> Result<f64, String> {
+    let value: f64 = s
+        .trim_end_matches('%')
+        .parse()
+        .map_err(|_| format!("invalid jitter '{}', expected e.g. 20%", s))?;
+    if (0.0..=100.0).contains(&value) {
+        Ok(value / 100.0)
+    } else {
+        Err(format!("jitter must be between 0% and 100%, got '{}'", s))
+    }
+}
+
+/// `interval` moved by a random amount within `±jitter` of itself
+fn jittered(interval: Duration, jitter: f64) -> Duration {
+    if jitter <= 0.0 {
+        return interval;
+    }
+    let factor = 1.0 + jitter * (fastrand::f64() * 2.0 - 1.0);
+    interval.mul_f64(factor.max(0.0))
+}
+
+/// `--max-body-size`: bytes with an optional K, M or G suffix (powers of 1024)
+fn parse_size(s: &str) -> Result<u64, String> {
+    let upper = s.trim().to_ascii_uppercase();
+    let digits = upper.trim_end_matches('B');
+    let (number, multiplier) = match digits.chars().last() {
+        Some('K') => (&digits[..digits.len() - 1], 1u64 << 10),
+        Some('M') => (&digits[..digits.len() - 1], 1 << 20),
+        Some('G') => (&digits[..digits.len() - 1], 1 << 30),
+        _ => (digits, 1),
+    };
+    number
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(multiplier))
+        .filter(|&n| n > 0)
+        .ok_or_else(|| format!("invalid size '{}', expected e.g. 512K or 8M", s))
+}
+
+fn parse_speed(s: &str) -> Result<f64, String> {
+    let value: f64 = s
+        .trim_end_matches(['x', 'X'])
+        .parse()
+        .map_err(|_| format!("invalid speed '{}', expected e.g. 10x", s))?;
+    if value.is_finite() && value > 0.0 {
+        Ok(value)
+    } else {
+        Err(format!("speed must be greater than zero, got '{}'", s))
+    }
+}
+
+/// Countdown until an RFC 3339 reset time, passing unparseable values through
+struct ResetIn<'a> {
+    reset_time: &'a str,
+    now: DateTime<Utc>,
+}
+
+impl fmt::Display for ResetIn<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match DateTime::parse_from_rfc3339(self.reset_time) {
+            Ok(dt) => HumanDuration(dt.signed_duration_since(self.now)).fmt(f),
+            Err(_) => f.write_str(self.reset_time),
+        }
+    }
 }
 
-fn format_reset_time(reset_time: &str) -> String {
-    if let Ok(dt) = DateTime::parse_from_rfc3339(reset_time) {
-        let duration = dt.signed_duration_since(Utc::now());
+/// Compact duration such as `1d6h2m3s` or `1h2m3s`, or "now" for
+/// non-positive durations; `--round-durations` shortens long ones to `1d6h`
+/// and `1h2m`
+struct HumanDuration(chrono::Duration);
+
+impl fmt::Display for HumanDuration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let duration = self.0;
         if duration.num_seconds() <= 0 {
-            return "now".to_string();
+            return f.write_str("now");
         }
-        let h = duration.num_hours();
+        let d = duration.num_days();
+        let h = duration.num_hours() % 24;
         let m = duration.num_minutes() % 60;
         let s = duration.num_seconds() % 60;
-        if h > 0 {
-            format!("{}h{}m{}s", h, m, s)
+        let round = locale::current().round_durations;
+        if d > 0 && round {
+            write!(f, "{}d{}h", d, h)
+        } else if d > 0 {
+            write!(f, "{}d{}h{}m{}s", d, h, m, s)
+        } else if h > 0 && round {
+            write!(f, "{}h{}m", h, m)
+        } else if h > 0 {
+            write!(f, "{}h{}m{}s", h, m, s)
         } else if m > 0 {
-            format!("{}m{}s", m, s)
+            write!(f, "{}m{}s", m, s)
         } else {
-            format!("{}s", s)
+            write!(f, "{}s", s)
+        }
+    }
+}
+
+/// Largest whole unit of a duration, e.g. `2d`, `5h`, `12m`; "expired" when not positive
+struct ShortDuration(chrono::Duration);
+
+impl fmt::Display for ShortDuration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let d = self.0;
+        if d.num_seconds() <= 0 {
+            f.write_str("expired")
+        } else if d.num_days() > 0 {
+            write!(f, "{}d", d.num_days())
+        } else if d.num_hours() > 0 {
+            write!(f, "{}h", d.num_hours())
+        } else {
+            write!(f, "{}m", d.num_minutes().max(1))
+        }
+    }
+}
+
+/// Writes `args` left-aligned in a `width`-column field without an intermediate
+/// String; wide characters such as emoji take two columns
+fn write_padded(out: &mut String, width: usize, args: fmt::Arguments<'_>) -> fmt::Result {
+    let start = out.len();
+    out.write_fmt(args)?;
+    let written = UnicodeWidthStr::width(&out[start..]);
+    out.extend(std::iter::repeat(' ').take(width.saturating_sub(written)));
+    Ok(())
+}
+
+fn write_rule(out: &mut String, width: usize) -> fmt::Result {
+    out.extend(std::iter::repeat('-').take(width));
+    writeln!(out)
+}
+
+/// A quota cell value: `123/500` with `--show-counts` and counts available,
+/// `25% ~12` with only an estimate, else `25%`
+struct QuotaValue<'a> {
+    quota: &'a ModelQuota,
+    show_counts: bool,
+}
+
+impl fmt::Display for QuotaValue<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let percent = Percent(self.quota.remaining_fraction);
+        match (self.show_counts, self.quota.remaining, self.quota.limit, self.quota.estimated_remaining) {
+            (true, Some(remaining), Some(limit), _) => write!(f, "{}/{}", remaining, limit),
+            (true, None, _, Some(estimate)) => write!(f, "{} ~{}", percent, estimate),
+            _ => write!(f, "{}", percent),
         }
-    } else {
-        reset_time.to_string()
     }
 }
 
@@ -114,187 +842,1590 @@ fn get_account_status(account: &Account) -> (&'static str, &'static str) {
     if !account.enabled.unwrap_or(true) {
         return ("disabled", DIM);
     }
-    if let Some(ref rate_limits) = account.model_rate_limits {
-        let limited = rate_limits.values().filter(|r| r.is_rate_limited).count();
-        if limited > 0 {
-            return ("limited", YELLOW);
-        }
+    if account.limited {
+        return ("limited", YELLOW);
     }
     ("ok", GREEN)
 }
 
-fn count_stats(accounts: &[Account]) -> (usize, usize, usize) {
+/// Available, rate-limited, invalid and disabled accounts, bucketed like
+/// `get_account_status` so the four add up to the total
+fn count_stats<'a>(accounts: impl IntoIterator<Item = &'a Account>) -> (usize, usize, usize, usize) {
     let mut available = 0;
     let mut rate_limited = 0;
     let mut invalid = 0;
+    let mut disabled = 0;
 
     for a in accounts {
         if a.is_invalid.unwrap_or(false) {
             invalid += 1;
-        } else if a.model_rate_limits.as_ref().map(|r| r.values().any(|l| l.is_rate_limited)).unwrap_or(false) {
+        } else if !a.enabled.unwrap_or(true) {
+            disabled += 1;
+        } else if a.limited {
             rate_limited += 1;
-        } else if a.enabled.unwrap_or(true) {
+        } else {
             available += 1;
         }
     }
-    (available, rate_limited, invalid)
+    (available, rate_limited, invalid, disabled)
+}
+
+fn parse_reset(quota: &ModelQuota) -> Option<DateTime<Utc>> {
+    let reset_time = quota.reset_time.as_deref()?;
+    DateTime::parse_from_rfc3339(reset_time)
+        .ok()
+        .map(|d| d.with_timezone(&Utc))
+}
+
+/// An exhausted quota whose reset time has already passed
+fn quota_overdue(quota: &ModelQuota, now: DateTime<Utc>) -> bool {
+    quota.remaining_fraction <= 0.0 && parse_reset(quota).map(|at| at < now).unwrap_or(false)
+}
+
+/// Records the currently overdue quotas in `seen`, returning whether any is new.
+/// Each overdue reset triggers at most one early re-fetch.
+fn newly_overdue(data: &ApiResponse, now: DateTime<Utc>, seen: &mut HashSet<(Name, Name)>) -> bool {
+    let overdue: HashSet<(Name, Name)> = data
+        .accounts
+        .iter()
+        .filter_map(|a| a.limits.as_ref().map(|limits| (a, limits)))
+        .flat_map(|(a, limits)| {
+            data.models.iter().filter_map(move |m| {
+                let quota = limits.get(m)?;
+                quota_overdue(quota, now).then(|| (a.email.clone(), m.clone()))
+            })
+        })
+        .collect();
+    let new = overdue.difference(seen).next().is_some();
+    *seen = overdue;
+    new
+}
+
+/// The response `timestamp`, which the proxy sends either as RFC 3339 or
+/// US-style local time (`10/14/2026, 01:08:16 PM`)
+fn proxy_time(data: &ApiResponse) -> Option<DateTime<Utc>> {
+    let timestamp = data.timestamp.as_deref()?;
+    match DateTime::parse_from_rfc3339(timestamp) {
+        Ok(d) => Some(d.with_timezone(&Utc)),
+        Err(_) => chrono::NaiveDateTime::parse_from_str(timestamp, "%m/%d/%Y, %I:%M:%S %p")
+            .ok()?
+            .and_local_timezone(Local)
+            .earliest()
+            .map(|d| d.with_timezone(&Utc)),
+    }
 }
 
-async fn fetch_data(url: &str) -> Result<ApiResponse> {
-    let client = reqwest::Client::new();
-    let response = client
+/// How far the local clock was ahead of the proxy's when the response arrived
+fn clock_skew(data: &ApiResponse, now: DateTime<Utc>) -> Option<chrono::Duration> {
+    Some(data.fetched_at.unwrap_or(now) - proxy_time(data)?)
+}
+
+/// `local clock is 5m ahead of the proxy`
+fn describe_skew(skew: chrono::Duration) -> String {
+    let (amount, direction) = if skew > chrono::Duration::zero() {
+        (skew, "ahead of")
+    } else {
+        (-skew, "behind")
+    };
+    format!("local clock is {} {} the proxy", HumanDuration(amount), direction)
+}
+
+/// How old the shown data is: from the response `timestamp`, or from the
+/// fetch time when there is none or the clocks disagree
+fn data_age(data: &ApiResponse, now: DateTime<Utc>, thresholds: &Thresholds) -> chrono::Duration {
+    let skewed = clock_skew(data, now)
+        .map(|skew| skew.num_seconds().unsigned_abs() > thresholds.clock_skew)
+        .unwrap_or(true);
+    let produced = if skewed { data.fetched_at } else { proxy_time(data) };
+    now - produced.unwrap_or(now)
+}
+
+/// The soonest future reset of a partially used quota on a listed model
+fn next_reset<'a>(
+    account: &'a Account,
+    models: &[Name],
+    now: DateTime<Utc>,
+) -> Option<(&'a str, DateTime<Utc>)> {
+    account
+        .limits
+        .as_ref()?
+        .iter()
+        .filter(|(model, quota)| quota.remaining_fraction < 1.0 && models.contains(model))
+        .filter_map(|(model, quota)| Some((model.as_str(), parse_reset(quota)?)))
+        .filter(|(_, at)| *at > now)
+        .min_by_key(|(_, at)| *at)
+}
+
+/// The soonest upcoming reset across all accounts
+fn fleet_next_reset(data: &ApiResponse, now: DateTime<Utc>) -> Option<(&Account, &str, DateTime<Utc>)> {
+    data.accounts
+        .iter()
+        .filter_map(|a| next_reset(a, &data.models, now).map(|(model, at)| (a, model, at)))
+        .min_by_key(|(_, _, at)| *at)
+}
+
+/// The usable account closest to running out: lowest remaining fraction on any listed model
+fn worst_account(data: &ApiResponse) -> Option<(&Account, &str, f64)> {
+    data.accounts
+        .iter()
+        .filter(|a| !a.is_invalid.unwrap_or(false) && a.enabled.unwrap_or(true))
+        .filter_map(|a| {
+            let limits = a.limits.as_ref()?;
+            data.models
+                .iter()
+                .filter_map(|m| limits.get(m).map(|q| (m.as_str(), q.remaining_fraction)))
+                .min_by(|x, y| x.1.total_cmp(&y.1))
+                .map(|(model, fraction)| (a, model, fraction))
+        })
+        .min_by(|x, y| x.2.total_cmp(&y.2))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Severity {
+    Ok,
+    Warning,
+    Critical,
+}
+
+impl Severity {
+    fn from_available(available: usize, thresholds: &Thresholds) -> Severity {
+        if available <= thresholds.available_critical {
+            Severity::Critical
+        } else if available <= thresholds.available_warning {
+            Severity::Warning
+        } else {
+            Severity::Ok
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Severity::Ok => "OK",
+            Severity::Warning => "WARNING",
+            Severity::Critical => "CRITICAL",
+        }
+    }
+
+    fn color(self) -> &'static str {
+        match self {
+            Severity::Ok => GREEN,
+            Severity::Warning => YELLOW,
+            Severity::Critical => RED,
+        }
+    }
+}
+
+/// Orders the accounts by `opts.sort`, then moves the `--pin`ned ones to the
+/// top in the order they were given
+fn sort_accounts(data: &mut ApiResponse, opts: &RenderOptions, now: DateTime<Utc>) {
+    sort_by_options(&mut data.accounts, &data.models, opts, now);
+}
+
+/// The accounts of `data` in [`sort_accounts`] order, leaving `data` as it is
+fn sorted_accounts<'a>(data: &'a ApiResponse, opts: &RenderOptions, now: DateTime<Utc>) -> Vec<&'a Account> {
+    let mut accounts: Vec<&Account> = data.accounts.iter().collect();
+    sort_by_options(&mut accounts, &data.models, opts, now);
+    accounts
+}
+
+fn sort_by_options<A: Borrow<Account>>(accounts: &mut [A], models: &[Name], opts: &RenderOptions, now: DateTime<Utc>) {
+    match opts.sort {
+        SortKey::Server => {}
+        SortKey::Name => accounts.sort_by(|a, b| a.borrow().email.cmp(&b.borrow().email)),
+        SortKey::Reset => {
+            // Accounts with no pending reset sort last
+            accounts.sort_by_cached_key(|a| {
+                let at = next_reset(a.borrow(), models, now).map(|(_, at)| at);
+                (at.is_none(), at)
+            });
+        }
+    }
+    if !opts.pins.is_empty() {
+        let pinned = |a: &Account| {
+            opts.pins
+                .iter()
+                .position(|p| p.eq_ignore_ascii_case(&a.email) || p.eq_ignore_ascii_case(get_short_email(&a.email)))
+        };
+        // Stable, so the pinned accounts keep their --pin order and the rest their sort order
+        accounts.sort_by_key(|a| pinned(a.borrow()).unwrap_or(usize::MAX));
+    }
+}
+
+/// Whether the account can currently serve requests for `model`
+fn model_available(account: &Account, model: &str) -> bool {
+    if account.is_invalid.unwrap_or(false) || !account.enabled.unwrap_or(true) {
+        return false;
+    }
+    let has_quota = account
+        .limits
+        .as_ref()
+        .and_then(|l| l.get(model))
+        .map(|q| q.remaining_fraction > 0.0)
+        .unwrap_or(false);
+    let limited = account
+        .model_rate_limits
+        .as_ref()
+        .and_then(|r| r.get(model))
+        .map(|l| l.is_rate_limited)
+        .unwrap_or(false);
+    has_quota && !limited
+}
+
+/// Number of accounts that can serve at least one listed model in `group`
+fn count_group_available(data: &ApiResponse, group: &ModelGroup) -> usize {
+    data.accounts
+        .iter()
+        .filter(|a| {
+            data.models
+                .iter()
+                .filter(|m| group.contains(m))
+                .any(|m| model_available(a, m))
+        })
+        .count()
+}
+
+/// Fills in what flags left unset from the selected profile, then the defaults
+fn resolve_profile(args: &mut Args, config: &Config) -> Result<()> {
+    let profile = match args.profile {
+        Some(ref name) => config.profile(name)?.clone(),
+        None => Default::default(),
+    };
+    // A profile URL makes discovery moot
+    args.discover &= profile.url.is_none() && profile.urls.is_empty() && config.endpoints.is_empty();
+    args.urls = if !args.url_flag.is_empty() {
+        args.url_flag.clone()
+    } else if !profile.urls.is_empty() {
+        profile.urls
+    } else if let Some(url) = profile.url {
+        vec![url]
+    } else if !config.endpoints.is_empty() {
+        config.endpoints.iter().map(|e| e.url.clone()).collect()
+    } else {
+        vec![DEFAULT_URL.to_string()]
+    };
+    args.url = args.urls[0].clone();
+    if args.token.is_none() {
+        args.token = profile.token;
+    }
+    #[cfg(feature = "keyring")]
+    if args.token.is_none() {
+        args.token = auth::stored_token(args.profile.as_deref());
+    }
+    if args.tags.is_empty() {
+        args.tags = profile.tags;
+    }
+    Ok(())
+}
+
+/// HTTP client shared by all fetches of a run, authenticating with `--token`.
+/// Without `--proxy`, reqwest picks up HTTP_PROXY/HTTPS_PROXY/ALL_PROXY itself.
+fn http_client(args: &Args) -> Result<reqwest::Client> {
+    let mut headers = reqwest::header::HeaderMap::new();
+    if let Some(ref token) = args.token {
+        let mut value = reqwest::header::HeaderValue::from_str(&format!("Bearer {}", token))
+            .context("Token contains characters not allowed in an HTTP header")?;
+        value.set_sensitive(true);
+        headers.insert(reqwest::header::AUTHORIZATION, value);
+    }
+    let mut builder = reqwest::Client::builder().default_headers(headers);
+    if let Some(ref url) = args.outbound_proxy {
+        let proxy = reqwest::Proxy::all(url)
+            .with_context(|| format!("Invalid --proxy URL '{}'", url))?
+            .no_proxy(reqwest::NoProxy::from_env());
+        builder = builder.proxy(proxy);
+    }
+    if args.ssh.is_some() {
+        // The tunnel's local end must not go through HTTPS_PROXY and friends
+        builder = builder.no_proxy();
+    }
+    builder.build().context("Failed to create HTTP client")
+}
+
+/// GETs `url` and returns the (decompressed) body, refusing bodies over `max_body` bytes
+async fn fetch_text(client: &reqwest::Client, url: &str, max_body: u64) -> Result<String, FetchError> {
+    let mut response = client
         .get(url)
         .timeout(Duration::from_secs(10))
         .send()
         .await
-        .context("Failed to connect to server")?;
+        .map_err(FetchError::from_request)?;
 
     if !response.status().is_success() {
-        let status = response.status();
-        return Err(anyhow::anyhow!("Server returned error {}", status));
+        return Err(FetchError::Status(response.status()));
+    }
+
+    let too_large = FetchError::TooLarge { limit: max_body };
+    if response.content_length().map(|len| len > max_body).unwrap_or(false) {
+        return Err(too_large);
+    }
+    let mut body = Vec::new();
+    loop {
+        let chunk = match response.chunk().await {
+            Ok(Some(chunk)) => chunk,
+            Ok(None) => break,
+            Err(e) if e.is_timeout() => return Err(FetchError::Timeout(e)),
+            Err(e) => return Err(FetchError::Body(format!("Failed to read response: {}", e))),
+        };
+        if (body.len() + chunk.len()) as u64 > max_body {
+            return Err(too_large);
+        }
+        body.extend_from_slice(&chunk);
     }
+    String::from_utf8(body).map_err(|_| FetchError::Body("Response is not valid UTF-8".to_string()))
+}
 
-    let text = response.text().await.context("Failed to read response")?;
+/// Deserializes `text`, naming the JSON path of the first bad value on failure, e.g.
+/// `Failed to parse JSON at accounts[3].limits.gemini.remainingFraction: invalid type: string "x", expected f64`
+fn deserialize_at_path<T: serde::de::DeserializeOwned>(text: &str, what: &str) -> Result<T, FetchError> {
+    let mut deserializer = serde_json::Deserializer::from_str(text);
+    serde_path_to_error::deserialize(&mut deserializer).map_err(|e| {
+        let path = e.path().to_string();
+        if path == "." {
+            FetchError::shape(format!("{}: {}", what, e.into_inner()), text)
+        } else {
+            FetchError::shape(format!("{} at {}: {}", what, path, e.into_inner()), text)
+        }
+    })
+}
 
-    // Try wrapped response first
-    if let Ok(wrapper) = serde_json::from_str::<ApiResponseWrapper>(&text) {
-        serde_json::from_str(&wrapper.result).context("Failed to parse inner JSON")
-    } else {
-        serde_json::from_str(&text).context("Failed to parse JSON")
+fn parse_response(text: &str, config: &Config) -> Result<ApiResponse, FetchError> {
+    let mut data = config.proxy.api_version.decode(text)?;
+    if config.proxy.strict {
+        // The body decoded, so it is JSON
+        let problems = schema::validate_strict(text).unwrap_or_default();
+        if let Some(first) = problems.first() {
+            let more = match problems.len() {
+                1 => String::new(),
+                2 => " (and 1 more problem)".to_string(),
+                n => format!(" (and {} more problems)", n - 1),
+            };
+            return Err(FetchError::shape(format!("Strict parsing failed at {}{}", first, more), text));
+        }
     }
+
+    let limit_models = data
+        .accounts
+        .iter()
+        .filter_map(|a| a.limits.as_ref())
+        .flat_map(|limits| limits.keys());
+    config.models.apply(&mut data.models, limit_models);
+    for account in &mut data.accounts {
+        if !config.models.ignore.is_empty() {
+            if let Some(ref mut limits) = account.limits {
+                limits.retain(|model, _| !config.models.is_ignored(model));
+            }
+            if let Some(ref mut rate_limits) = account.model_rate_limits {
+                rate_limits.retain(|model, _| !config.models.is_ignored(model));
+            }
+        }
+        account.update_limited(&config.models);
+    }
+    Ok(data)
+}
+
+fn record_frame(file: &mut File, body: &str) -> Result<()> {
+    let frame = RecordedFrame {
+        timestamp: Utc::now(),
+        body: Cow::Borrowed(body),
+    };
+    serde_json::to_writer(&mut *file, &frame).context("Failed to encode session frame")?;
+    writeln!(file).context("Failed to write session file")?;
+    file.flush().context("Failed to write session file")
 }
 
-fn print_table(data: &ApiResponse) {
-    let timestamp = data.timestamp.clone()
-        .unwrap_or_else(|| Local::now().format("%-m/%-d/%Y, %-I:%M:%S %p").to_string());
+/// Writes a fully rendered frame to stdout in one go to avoid flicker
+fn flush_frame(frame: &str) -> Result<()> {
+    let mut stdout = io::stdout().lock();
+    stdout.write_all(frame.as_bytes()).context("Failed to write to stdout")?;
+    stdout.flush().context("Failed to write to stdout")
+}
 
-    let (available, rate_limited, invalid) = count_stats(&data.accounts);
+/// Drops accounts without any of the `--tag` values
+fn apply_tag_filter(data: &mut ApiResponse, opts: &RenderOptions) {
+    if !opts.tag_filter.is_empty() {
+        data.accounts.retain(|a| {
+            opts.config
+                .tags_for(&a.email)
+                .any(|tag| opts.tag_filter.iter().any(|t| t == tag))
+        });
+    }
+    if let Some(ref filter) = opts.filter_expr {
+        data.accounts.retain(|account| {
+            filter
+                .eval(&AccountScope {
+                    account,
+                    config: &opts.config,
+                })
+                .truthy()
+        });
+    }
+}
+
+/// Which slice of the accounts a frame shows when `--page-size` is in effect
+#[derive(Debug, Clone, Copy)]
+struct Page {
+    index: usize,
+    count: usize,
+    size: usize,
+}
+
+impl Page {
+    /// Picks the page for refresh number `tick`, cycling through all pages
+    fn for_tick(total: usize, size: usize, tick: usize) -> Page {
+        let size = size.max(1);
+        let count = ((total + size - 1) / size).max(1);
+        Page {
+            index: tick % count,
+            count,
+            size,
+        }
+    }
+
+    fn range(&self, total: usize) -> std::ops::Range<usize> {
+        let start = (self.index * self.size).min(total);
+        start..(start + self.size).min(total)
+    }
+}
+
+/// Accounts per page that fit the terminal: matrix columns across, account rows down
+fn auto_page_size(models: usize) -> usize {
+    match terminal_size::terminal_size() {
+        Some((terminal_size::Width(cols), terminal_size::Height(rows))) => {
+            let across = (cols as usize).saturating_sub(28) / 20;
+            // Header with a gauge per model, both table headings/rules, blank lines and the footer
+            let down = (rows as usize).saturating_sub(14 + 2 * models);
+            across.min(down).max(1)
+        }
+        None => usize::MAX,
+    }
+}
+
+/// Renders a response in per-frame order without taking it apart, so the
+/// countdown can redraw the same response every second; `tick` counts
+/// refreshes so paged views cycle through the fleet
+fn render_frame(out: &mut String, data: &ApiResponse, now: DateTime<Utc>, opts: &RenderOptions, tick: usize) -> fmt::Result {
+    let accounts = sorted_accounts(data, opts, now);
+    let page = opts.page_size.map(|size| {
+        let size = match size {
+            PageSize::Fixed(n) => n,
+            PageSize::Auto => auto_page_size(data.models.len()),
+        };
+        Page::for_tick(accounts.len(), size, tick)
+    });
+    render_table(out, data, &accounts, now, opts, page)
+}
+
+/// `data` rendered with `accounts`, its accounts in display order
+fn render_table(
+    out: &mut String,
+    data: &ApiResponse,
+    accounts: &[&Account],
+    now: DateTime<Utc>,
+    opts: &RenderOptions,
+    page: Option<Page>,
+) -> fmt::Result {
+    if opts.problems_only {
+        return problems::render(out, accounts, &data.models, now, opts);
+    }
+    if opts.layout.header() {
+        render_header(out, data, now, opts)?;
+        if opts.summary_only {
+            return Ok(());
+        }
+    }
+
+    let accounts = match page {
+        Some(page) => {
+            let range = page.range(accounts.len());
+            if page.count > 1 {
+                writeln!(
+                    out,
+                    "{}Page {}/{} (accounts {}-{} of {}){}",
+                    DIM,
+                    page.index + 1,
+                    page.count,
+                    range.start + 1,
+                    range.end,
+                    accounts.len(),
+                    RESET
+                )?;
+            }
+            &accounts[range]
+        }
+        None => accounts,
+    };
+
+    if opts.layout.accounts() {
+        if opts.layout.header() {
+            writeln!(out)?;
+        }
+        render_accounts(out, accounts, now, &opts.config)?;
+    }
+    if opts.layout.matrix() {
+        if opts.layout != Layout::MatrixOnly {
+            writeln!(out)?;
+        }
+        render_matrix(out, &data.models, accounts, now, opts)?;
+    }
+    if opts.layout.details() {
+        render_rate_limit_details(out, accounts, now)?;
+    }
+    render_script_rows(out, &data.script_rows)?;
+    Ok(())
+}
+
+/// The `--script` table, its first row as the header
+fn render_script_rows(out: &mut String, rows: &[Vec<String>]) -> fmt::Result {
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    if columns == 0 {
+        return Ok(());
+    }
+    let widths: Vec<usize> = (0..columns)
+        .map(|i| {
+            rows.iter()
+                .filter_map(|row| row.get(i))
+                .map(|cell| cell.chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    writeln!(out)?;
+    for (index, row) in rows.iter().enumerate() {
+        if index == 0 {
+            out.push_str(BOLD);
+        }
+        for (i, cell) in row.iter().enumerate() {
+            let sep = if i == 0 { "" } else { " " };
+            write!(out, "{}{:<width$}", sep, cell, width = widths[i])?;
+        }
+        if index == 0 {
+            writeln!(out, "{}", RESET)?;
+            write_rule(out, widths.iter().sum::<usize>() + columns - 1)?;
+        } else {
+            writeln!(out)?;
+        }
+    }
+    Ok(())
+}
+
+fn render_header(
+    out: &mut String,
+    data: &ApiResponse,
+    now: DateTime<Utc>,
+    opts: &RenderOptions,
+) -> fmt::Result {
+    let (available, rate_limited, invalid, disabled) = count_stats(&data.accounts);
     let total = data.accounts.len();
+    let severity = Severity::from_available(available, &opts.config.thresholds);
 
-    // Header
-    println!("{}{}Account Limits{} {}({}){}", BOLD, CYAN, RESET, DIM, timestamp, RESET);
-    println!(
-        "Accounts: {} total, {}{} available{}, {}{} rate-limited{}, {}{} invalid{}",
+    write!(
+        out,
+        "{}{}Account Limits{} {}{}[{}]{} {}(",
+        BOLD, CYAN, RESET, BOLD, severity.color(), severity.label(), RESET, DIM
+    )?;
+    match data.timestamp {
+        Some(ref timestamp) => out.push_str(timestamp),
+        None => write!(
+            out,
+            "{}",
+            data.fetched_at.unwrap_or(now).with_timezone(&Local).format(locale::current().date_time)
+        )?,
+    }
+    let age = data_age(data, now, &opts.config.thresholds);
+    // Stale beyond two refreshes is worth a look, beyond four something has stopped
+    let age_color = match opts.interval {
+        Some(interval) if age.to_std().map(|a| a > interval * 4).unwrap_or(false) => RED,
+        Some(interval) if age.to_std().map(|a| a > interval * 2).unwrap_or(false) => YELLOW,
+        _ => DIM,
+    };
+    if age.num_seconds() > 0 {
+        write!(out, ", {}{}{}{} ago", age_color, HumanDuration(age), RESET, DIM)?;
+    } else {
+        out.push_str(", just now");
+    }
+    writeln!(out, "){}", RESET)?;
+    render_endpoints(out, &data.endpoints)?;
+    let threshold = opts.config.thresholds.clock_skew;
+    if let Some(skew) = clock_skew(data, now).filter(|skew| skew.num_seconds().unsigned_abs() > threshold) {
+        writeln!(out, "{}Clock skew: {}; reset countdowns may be off{}", YELLOW, describe_skew(skew), RESET)?;
+    }
+    writeln!(
+        out,
+        "Accounts: {} total, {}{} available{}, {}{} rate-limited{}, {}{} invalid{}, {}{} disabled{}",
         total,
         GREEN, available, RESET,
         YELLOW, rate_limited, RESET,
-        RED, invalid, RESET
-    );
-    println!();
+        RED, invalid, RESET,
+        DIM, disabled, RESET
+    )?;
+    let groups = &opts.config.models.groups;
+    if !groups.is_empty() {
+        out.push_str("Models:");
+        for (i, group) in groups.iter().enumerate() {
+            let available = count_group_available(data, group);
+            let color = if available > 0 { GREEN } else { RED };
+            let sep = if i == 0 { " " } else { ", " };
+            write!(out, "{}{} {}{} available{}", sep, group.name, color, available, RESET)?;
+        }
+        writeln!(out)?;
+    }
+    render_tag_summary(out, data, opts)?;
+    render_credential_warnings(out, data, now, &opts.config.thresholds)?;
+    roster::render(out, &data.roster_changes, now)?;
+    for alert in &data.script_alerts {
+        writeln!(out, "{}{}{}", YELLOW, alert, RESET)?;
+    }
+    if let Some((account, model, at)) = fleet_next_reset(data, now) {
+        writeln!(
+            out,
+            "Next reset: {}{}/{}{} in {}",
+            BOLD,
+            get_short_email(&account.email),
+            model,
+            RESET,
+            HumanDuration(at - now)
+        )?;
+    }
+    if !opts.summary_only && opts.layout.details() {
+        render_capacity_gauge(out, data)?;
+    }
+    render_forecasts(out, &data.forecasts, now)?;
+    if opts.summary_only {
+        if let Some((account, model, fraction)) = worst_account(data) {
+            let color = if fraction <= 0.0 {
+                RED
+            } else if fraction < opts.config.thresholds.quota_warning {
+                YELLOW
+            } else {
+                GREEN
+            };
+            writeln!(
+                out,
+                "Worst: {}{}/{}{} at {}{}{}",
+                BOLD,
+                get_short_email(&account.email),
+                model,
+                RESET,
+                color,
+                Percent(fraction),
+                RESET
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Per-tag "available/total" counts for the accounts in view
+fn render_tag_summary(out: &mut String, data: &ApiResponse, opts: &RenderOptions) -> fmt::Result {
+    let tags: BTreeSet<&str> = data
+        .accounts
+        .iter()
+        .flat_map(|a| opts.config.tags_for(&a.email))
+        .collect();
+    if tags.is_empty() {
+        return Ok(());
+    }
+
+    out.push_str("Tags:");
+    for (i, tag) in tags.into_iter().enumerate() {
+        let tagged: Vec<&Account> = data
+            .accounts
+            .iter()
+            .filter(|a| opts.config.tags_for(&a.email).any(|t| t == tag))
+            .collect();
+        let (available, ..) = count_stats(tagged.iter().copied());
+        let color = if available > 0 { GREEN } else { RED };
+        let sep = if i == 0 { " " } else { ", " };
+        write!(out, "{}{} {}{}/{} available{}", sep, tag, color, available, tagged.len(), RESET)?;
+    }
+    writeln!(out)
+}
 
-    // Account summary table
-    println!(
-        "{}{:<20} {:<15} {:<25} {:<25}{}",
-        BOLD, "Account", "Status", "Last Used", "Quota Reset", RESET
-    );
-    println!("{}", "-".repeat(85));
+/// Width of the fleet capacity bars in the header
+const GAUGE_WIDTH: usize = 30;
 
+/// One bar per model: the summed remaining quota of the usable accounts in
+/// green, what they have used up in red, e.g.
+/// `gemini-3-flash   ██████████████████░░░░░░░░░░░░  60% of 3 accounts`
+fn render_capacity_gauge(out: &mut String, data: &ApiResponse) -> fmt::Result {
+    for model in &data.models {
+        let fractions: Vec<f64> = data
+            .accounts
+            .iter()
+            .filter(|a| !a.is_invalid.unwrap_or(false) && a.enabled.unwrap_or(true))
+            .filter_map(|a| a.limits.as_ref()?.get(model))
+            .map(|q| q.remaining_fraction.clamp(0.0, 1.0))
+            .collect();
+        if fractions.is_empty() {
+            continue;
+        }
+        let share = fractions.iter().sum::<f64>() / fractions.len() as f64;
+        let filled = (share * GAUGE_WIDTH as f64).round() as usize;
+        write!(out, "{:<28}{}", model, GREEN)?;
+        out.extend(std::iter::repeat('█').take(filled));
+        out.push_str(RED);
+        out.extend(std::iter::repeat('░').take(GAUGE_WIDTH - filled));
+        writeln!(
+            out,
+            "{} {:>4} of {} account{}",
+            RESET,
+            Percent(share).to_string(),
+            fractions.len(),
+            if fractions.len() == 1 { "" } else { "s" }
+        )?;
+    }
+    Ok(())
+}
+
+/// `Forecast: gemini out ~4:40 PM, claude out ~tomorrow 9:05 AM at the usual pace`
+fn render_forecasts(out: &mut String, forecasts: &[Forecast], now: DateTime<Utc>) -> fmt::Result {
+    if forecasts.is_empty() {
+        return Ok(());
+    }
+    if forecasts.iter().any(|f| f.newly_alerting) {
+        out.push(alarm::BELL);
+    }
+    let today = now.with_timezone(&Local).date_naive();
+    out.push_str("Forecast:");
+    for (i, forecast) in forecasts.iter().enumerate() {
+        let at = forecast.exhausted_at.with_timezone(&Local);
+        let color = if forecast.alert { RED } else { YELLOW };
+        let day = if at.date_naive() == today {
+            String::new()
+        } else if Some(at.date_naive()) == today.succ_opt() {
+            "tomorrow ".to_string()
+        } else {
+            // The date half of the short timestamp, e.g. `10/17`
+            let date = locale::current().short_date_time.split(' ').next().unwrap_or("%x");
+            format!("{} ", at.format(date))
+        };
+        let sep = if i == 0 { " " } else { ", " };
+        write!(
+            out,
+            "{}{}{} out ~{}{}{}",
+            sep,
+            color,
+            forecast.name,
+            day,
+            at.format(locale::current().clock),
+            RESET
+        )?;
+    }
+    writeln!(out, " at the usual pace")
+}
+
+/// `Credentials: bob expires in 2d, carol expired` for accounts inside the warning window
+fn render_credential_warnings(
+    out: &mut String,
+    data: &ApiResponse,
+    now: DateTime<Utc>,
+    thresholds: &Thresholds,
+) -> fmt::Result {
+    let mut first = true;
     for account in &data.accounts {
+        let Some(left) = account.credentials_expiring(now, thresholds) else {
+            continue;
+        };
+        out.push_str(if first { "Credentials: " } else { ", " });
+        first = false;
+        let email = get_short_email(&account.email);
+        if left <= chrono::Duration::zero() {
+            write!(out, "{}{} expired{}", RED, email, RESET)?;
+        } else {
+            write!(out, "{}{} expires in {}{}", YELLOW, email, ShortDuration(left), RESET)?;
+        }
+    }
+    if !first {
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+/// Account summary table
+fn render_accounts(out: &mut String, accounts: &[&Account], now: DateTime<Utc>, config: &Config) -> fmt::Result {
+    let thresholds = &config.thresholds;
+    let labels = &config.status;
+    // Only with recorded history; otherwise every cell would be a dash
+    let show_limited = accounts.iter().any(|a| a.limited_secs.is_some());
+    // Only from the second refresh of a watch on
+    let show_delta = accounts.iter().any(|a| a.quota_delta.is_some());
+    // Only when several endpoints were merged
+    let origin_width = accounts
+        .iter()
+        .filter_map(|a| a.origin.as_deref())
+        .map(|o| o.chars().count().max("Origin".len()))
+        .max();
+    write!(out, "{}{:<20} ", BOLD, "Account")?;
+    if let Some(width) = origin_width {
+        write!(out, "{:<width$} ", "Origin", width = width)?;
+    }
+    write!(out, "{:<15} ", "Status")?;
+    if show_delta {
+        write!(out, "{:<6} ", "Δ")?;
+    }
+    write!(out, "{:<25} {:<25}", "Last Used", "Quota Reset")?;
+    if show_limited {
+        out.push_str(" Limited 24h");
+    }
+    // `[[column]]`s from the config, evaluated up front to size them
+    let custom: Vec<Vec<String>> = accounts
+        .iter()
+        .map(|account| {
+            config
+                .columns
+                .iter()
+                .map(|c| c.expr.eval(&AccountScope { account, config }).to_string())
+                .collect()
+        })
+        .collect();
+    let custom_widths: Vec<usize> = config
+        .columns
+        .iter()
+        .enumerate()
+        .map(|(i, c)| {
+            custom
+                .iter()
+                .map(|row| row[i].chars().count())
+                .chain([c.name.chars().count()])
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    for (column, width) in config.columns.iter().zip(&custom_widths) {
+        write!(out, " {:<width$}", column.name, width = width)?;
+    }
+    writeln!(out, "{}", RESET)?;
+    write_rule(
+        out,
+        85 + origin_width.map_or(0, |w| w + 1)
+            + if show_delta { 7 } else { 0 }
+            + if show_limited { 12 } else { 0 }
+            + custom_widths.iter().map(|w| w + 1).sum::<usize>(),
+    )?;
+
+    for (row, account) in accounts.iter().enumerate() {
         let email = get_short_email(&account.email);
         let (status, color) = get_account_status(account);
 
-        let status_display = if status == "limited" {
-            if let Some(ref rl) = account.model_rate_limits {
+        let expiring = account.credentials_expiring(now, thresholds);
+        write!(out, "{:<20} ", email)?;
+        if let Some(width) = origin_width {
+            write!(out, "{}{:<width$}{} ", DIM, account.origin.as_deref().unwrap_or(""), RESET, width = width)?;
+        }
+        out.push_str(color);
+        if account.status_changed {
+            out.push_str(INVERSE);
+        }
+        match (expiring, &account.model_rate_limits) {
+            // Expired credentials explain whatever the proxy flags; a healthy account just gets a warning
+            (Some(left), _) if left <= chrono::Duration::zero() => {
+                out.push_str(RED);
+                write_padded(out, 15, format_args!("{}", labels.label("expired")))?;
+            }
+            (Some(left), _) if status == "ok" => {
+                out.push_str(YELLOW);
+                write_padded(out, 15, format_args!("expires {}", ShortDuration(left)))?;
+            }
+            (_, Some(rl)) if status == "limited" => {
                 let limited = rl.values().filter(|r| r.is_rate_limited).count();
-                format!("({}/{}) limited", limited, rl.len())
-            } else {
-                status.to_string()
+                write_padded(out, 15, format_args!("({}/{}) {}", limited, rl.len(), labels.label(status)))?;
             }
-        } else {
-            status.to_string()
-        };
+            _ => write_padded(out, 15, format_args!("{}", labels.label(status)))?,
+        }
+        write!(out, "{} ", RESET)?;
+        if show_delta {
+            match account.quota_delta {
+                Some(delta) if delta.abs() < 0.005 => write!(out, "{}{:<6}{} ", DIM, "0", RESET)?,
+                Some(delta) => {
+                    let color = if delta > 0.0 {
+                        GREEN
+                    } else if delta <= -0.1 {
+                        RED
+                    } else {
+                        YELLOW
+                    };
+                    out.push_str(color);
+                    let sign = if delta > 0.0 { '+' } else { '-' };
+                    write_padded(out, 6, format_args!("{}{}", sign, Percent(delta.abs())))?;
+                    write!(out, "{} ", RESET)?;
+                }
+                None => write!(out, "{:<6} ", "")?,
+            }
+        }
 
-        let last_used = account.last_used
-            .map(format_timestamp)
-            .unwrap_or_else(|| "never".to_string());
-
-        let reset = account.limits.as_ref()
-            .and_then(|limits| {
-                limits.values()
-                    .filter_map(|q| q.reset_time.as_ref())
-                    .min()
-                    .map(|t| {
-                        DateTime::parse_from_rfc3339(t)
-                            .map(|d| format_timestamp(d.timestamp_millis() as u64))
-                            .unwrap_or_else(|_| "N/A".to_string())
-                    })
-            })
-            .unwrap_or_else(|| "N/A".to_string());
+        match account.last_used {
+            Some(ts) => write_padded(out, 25, format_args!("{}", Timestamp(ts)))?,
+            None => write!(out, "{:<25}", "never")?,
+        }
+        out.push(' ');
 
-        println!(
-            "{:<20} {}{:<15}{} {:<25} {:<25}",
-            email, color, status_display, RESET, last_used, reset
-        );
+        // Compared as instants, since the strings may carry different offsets
+        let reset = account.limits.iter().flatten().filter_map(|(_, q)| parse_reset(q)).min();
+        match reset {
+            Some(d) => write_padded(out, 25, format_args!("{}", Timestamp(d.timestamp_millis() as u64)))?,
+            None => write!(out, "{:<25}", "N/A")?,
+        }
+        match account.limited_secs {
+            // Padded only when custom columns follow
+            Some(secs) if secs > 0 && custom_widths.is_empty() => {
+                write!(out, " {}", HumanDuration(chrono::Duration::seconds(secs)))?
+            }
+            Some(secs) if secs > 0 => {
+                out.push(' ');
+                write_padded(out, 11, format_args!("{}", HumanDuration(chrono::Duration::seconds(secs))))?;
+            }
+            Some(_) if custom_widths.is_empty() => out.push_str(" -"),
+            Some(_) => write!(out, " {:<11}", "-")?,
+            None => {}
+        }
+        for (value, width) in custom[row].iter().zip(&custom_widths) {
+            write!(out, " {:<width$}", value, width = width)?;
+        }
+        writeln!(out)?;
     }
+    Ok(())
+}
 
-    println!();
-
-    // Model quota table
+/// Model quota table: one row per model, one column per account
+fn render_matrix(
+    out: &mut String,
+    models: &[Name],
+    accounts: &[&Account],
+    now: DateTime<Utc>,
+    opts: &RenderOptions,
+) -> fmt::Result {
     // Build header
-    print!("{}{:<28}", BOLD, "Model");
-    for account in &data.accounts {
-        print!("{:<20}", get_short_email(&account.email));
+    write!(out, "{}{:<28}", BOLD, "Model")?;
+    for account in accounts {
+        write!(out, "{:<20}", get_short_email(&account.email))?;
     }
-    println!("{}", RESET);
-    println!("{}", "-".repeat(28 + data.accounts.len() * 20));
+    writeln!(out, "{}", RESET)?;
+    write_rule(out, 28 + accounts.len() * 20)?;
 
     // Model rows
-    for model in &data.models {
-        print!("{:<28}", model);
+    for model in models {
+        if opts.config.models.is_primary(model) {
+            write!(out, "{}{:<28}{}", BOLD, model, RESET)?;
+        } else {
+            write!(out, "{:<28}", model)?;
+        }
 
-        for account in &data.accounts {
-            let cell = if let Some(ref limits) = account.limits {
-                if let Some(quota) = limits.get(model) {
-                    let pct = (quota.remaining_fraction * 100.0) as u32;
+        for account in accounts {
+            match account.limits.as_ref().and_then(|limits| limits.get(model)) {
+                Some(quota) => {
+                    let value = QuotaValue {
+                        quota,
+                        show_counts: opts.show_counts,
+                    };
                     let is_limited = account.model_rate_limits.as_ref()
                         .and_then(|r| r.get(model))
                         .map(|l| l.is_rate_limited)
                         .unwrap_or(false);
 
+                    if account.changed_models.contains(model) {
+                        out.push_str(INVERSE);
+                    }
                     if quota.remaining_fraction <= 0.0 || is_limited {
-                        let wait = quota.reset_time.as_ref()
-                            .map(|t| format!("{}% (wait {})", pct, format_reset_time(t)))
-                            .unwrap_or_else(|| format!("{}%", pct));
-                        format!("{}{:<20}{}", RED, wait, RESET)
-                    } else if quota.remaining_fraction < 0.3 {
-                        format!("{}{:<20}{}", YELLOW, format!("{}%", pct), RESET)
+                        out.push_str(RED);
+                        match quota.reset_time {
+                            // Past its reset but still empty: say so rather than "wait now"
+                            Some(_) if quota_overdue(quota, now) => write_padded(
+                                out,
+                                20,
+                                format_args!(
+                                    "{} (overdue {})",
+                                    value,
+                                    HumanDuration(now - parse_reset(quota).unwrap_or(now))
+                                ),
+                            )?,
+                            Some(ref t) => write_padded(
+                                out,
+                                20,
+                                format_args!("{} (wait {})", value, ResetIn { reset_time: t, now }),
+                            )?,
+                            None => write_padded(out, 20, format_args!("{}", value))?,
+                        }
+                    } else if quota.remaining_fraction < opts.config.thresholds.quota_warning {
+                        out.push_str(YELLOW);
+                        write_padded(out, 20, format_args!("{}", value))?;
                     } else {
-                        format!("{}{:<20}{}", GREEN, format!("{}%", pct), RESET)
+                        out.push_str(GREEN);
+                        write_padded(out, 20, format_args!("{}", value))?;
                     }
-                } else {
-                    format!("{}{:<20}{}", DIM, "N/A", RESET)
                 }
-            } else {
-                format!("{}{:<20}{}", DIM, "N/A", RESET)
-            };
-            print!("{}", cell);
+                None => write!(out, "{}{:<20}", DIM, "N/A")?,
+            }
+            out.push_str(RESET);
         }
-        println!();
+        writeln!(out)?;
     }
+    Ok(())
+}
+
+/// One row per rate-limited model, with that model's own quota reset
+fn render_rate_limit_details(out: &mut String, accounts: &[&Account], now: DateTime<Utc>) -> fmt::Result {
+    let mut rows = Vec::new();
+    for account in accounts {
+        if let Some(ref rl) = account.model_rate_limits {
+            let mut limited: Vec<_> = rl.iter().filter(|(_, r)| r.is_rate_limited).collect();
+            limited.sort_by(|a, b| a.0.cmp(b.0));
+            for (model, limit) in limited {
+                let reset = account.limits.as_ref().and_then(|l| l.get(model)).and_then(parse_reset);
+                rows.push((get_short_email(&account.email), model, limit, reset));
+            }
+        }
+    }
+    if rows.is_empty() {
+        return Ok(());
+    }
+
+    writeln!(out)?;
+    writeln!(
+        out,
+        "{}{:<20} {:<28} {:<10} {:<12} {:<12} Reason{}",
+        BOLD, "Account", "Rate-limited Model", "Upstream", "Retry After", "Resets In", RESET
+    )?;
+    write_rule(out, 98)?;
+    for (email, model, limit, reset) in rows {
+        write!(out, "{:<20} {}{:<28}{} ", email, YELLOW, model, RESET)?;
+        match limit.upstream_status {
+            Some(status) => write!(out, "{:<10} ", status)?,
+            None => write!(out, "{:<10} ", "-")?,
+        }
+        match limit.retry_after {
+            Some(secs) => {
+                let retry = HumanDuration(chrono::Duration::seconds(secs as i64));
+                write_padded(out, 12, format_args!("{}", retry))?;
+            }
+            None => write!(out, "{:<12}", "-")?,
+        }
+        out.push(' ');
+        match reset {
+            Some(at) => write_padded(out, 12, format_args!("{}", HumanDuration(at - now)))?,
+            None => write!(out, "{:<12}", "-")?,
+        }
+        writeln!(out, " {}", limit.reason.as_deref().unwrap_or("-"))?;
+    }
+    Ok(())
+}
+
+/// Writes the computed state as JSON, one line per refresh unless `pretty`
+fn render_json(out: &mut String, data: &ApiResponse, now: DateTime<Utc>, pretty: bool) -> Result<()> {
+    let snapshot = Snapshot::new(data, now);
+    let json = if pretty {
+        serde_json::to_string_pretty(&snapshot)
+    } else {
+        serde_json::to_string(&snapshot)
+    };
+    out.push_str(&json.context("Failed to encode state")?);
+    out.push('\n');
+    Ok(())
+}
+
+fn render_json_error(out: &mut String, error: &anyhow::Error, now: DateTime<Utc>) -> Result<()> {
+    let mut line = serde_json::json!({ "timestamp": now, "error": error::one_line(error) });
+    if let Some(fetch_error) = error.downcast_ref::<FetchError>() {
+        line["kind"] = fetch_error.kind().into();
+    }
+    out.push_str(&line.to_string());
+    out.push('\n');
+    Ok(())
+}
+
+/// The error and every cause under it, plus the start of the body that
+/// didn't parse with `--debug`
+fn render_error(out: &mut String, error: &anyhow::Error, debug: bool) -> fmt::Result {
+    let causes = error::causes(error);
+    writeln!(out, "{}Error: {}{}", RED, causes[0], RESET)?;
+    for cause in &causes[1..] {
+        writeln!(out, "{}  caused by: {}{}", DIM, cause, RESET)?;
+    }
+    if let Some(excerpt) = error::excerpt(error).filter(|_| debug) {
+        writeln!(out, "{}Response began with:{}\n{}", DIM, RESET, excerpt.trim_end())?;
+    }
+    Ok(())
+}
+
+async fn replay(path: &str, speed: f64, config: &Config, opts: &RenderOptions) -> Result<()> {
+    let file = File::open(path).with_context(|| format!("Failed to open session file {}", path))?;
+    let mut previous: Option<DateTime<Utc>> = None;
+    let mut frame = String::new();
+    let mut estimator = CapacityEstimator::new();
+
+    for (index, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.context("Failed to read session file")?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let recorded: RecordedFrame = serde_json::from_str(&line)
+            .with_context(|| format!("Invalid session frame on line {}", index + 1))?;
+
+        // Preserve the original pacing between frames, scaled by --speed
+        if let Some(prev) = previous {
+            let gap = (recorded.timestamp - prev).to_std().unwrap_or_default();
+            tokio::time::sleep(gap.div_f64(speed)).await;
+        }
+        previous = Some(recorded.timestamp);
+
+        frame.clear();
+        frame.push_str(CLEAR_SCREEN);
+        match parse_response(&recorded.body, config) {
+            Ok(mut data) => {
+                data.fetched_at = Some(recorded.timestamp);
+                apply_tag_filter(&mut data, opts);
+                estimator.observe(&mut data);
+                render_frame(&mut frame, &data, recorded.timestamp, opts, index)?
+            }
+            Err(e) => render_error(&mut frame, &e.into(), opts.debug)?,
+        }
+        writeln!(
+            frame,
+            "\n{}Replaying {} line {} (recorded {}) at {}x{}",
+            DIM,
+            path,
+            index + 1,
+            Timestamp(recorded.timestamp.timestamp_millis() as u64),
+            Decimal(speed),
+            RESET
+        )?;
+        flush_frame(&frame)?;
+    }
+
+    Ok(())
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let args = Args::parse();
+    let mut args = Args::parse();
+    locale::init(args.locale.as_deref(), args.precision as usize, args.round_durations);
+    let mut config = Config::load(args.config.as_deref())?;
+    if let Some(policy) = args.status_policy {
+        config.models.status_policy = policy;
+    }
+    if let Some(version) = args.api_version {
+        config.proxy.api_version = version;
+    }
+    if args.strict {
+        config.proxy.strict = true;
+    }
+    resolve_profile(&mut args, &config)?;
+    if args.discover {
+        args.urls = vec![discover::discover(&http_client(&args)?, &config).await?];
+        eprintln!("Found the proxy at {} (pass --url to skip discovery)", args.urls[0]);
+    }
+    // Before --ssh points the URLs at the tunnel
+    args.labels = args.urls.iter().map(|url| endpoints::label(&config, url)).collect();
+    let mut _tunnels = Vec::new();
+    if let Some(ref destination) = args.ssh {
+        for url in &mut args.urls {
+            _tunnels.push(tunnel::Tunnel::open(destination, url).await?);
+        }
+    }
+    args.url = args.urls[0].clone();
+    let opts = RenderOptions::new(&args, &config);
 
-    loop {
-        clear_screen();
+    if let Some(ref path) = args.replay {
+        return replay(path, args.speed, &config, &opts).await;
+    }
+    if let Some(ref path) = args.validate {
+        return run_validate(&args, path.as_deref()).await;
+    }
+
+    match args.command {
+        Some(Command::Pick(ref pick_args)) => return run_pick(&args, &config, &opts, pick_args).await,
+        Some(Command::Events(ref events_args)) => return events::run(events_args, &config.status),
+        Some(Command::Report(ref report_args)) => return report::run(&args, &opts, report_args).await,
+        Some(Command::Inspect(ref inspect_args)) => return inspect::run(&args, &opts, inspect_args).await,
+        Some(Command::Wait(ref wait_args)) => return wait::run(&args, &opts, wait_args).await,
+        Some(Command::Motd(ref motd_args)) => return motd::run(&args, &opts, motd_args).await,
+        Some(Command::WatchUntil(ref until_args)) => {
+            let code = watch_until::run(&args, &opts, until_args).await?;
+            // exit() skips destructors
+            drop(_tunnels);
+            std::process::exit(code);
+        }
+        Some(Command::Export {
+            command: ExportCommand::Zabbix(ref zabbix_args),
+        }) => return zabbix::run(&args, &opts, zabbix_args).await,
+        Some(Command::Check(ref check_args)) => {
+            let code = check::run(&args, &opts, check_args).await?;
+            drop(_tunnels);
+            std::process::exit(code);
+        }
+        Some(Command::Serve(ref serve_args)) => return serve::run(&args, &opts, serve_args).await,
+        Some(Command::Proxy(ref proxy_args)) => return proxy::run(&args, &config, proxy_args).await,
+        Some(Command::Doctor) => return proxy::status(&args, &config).await,
+        Some(Command::Accounts(ref accounts_args)) => return accounts::run(&args, &config, accounts_args).await,
+        Some(Command::Schema(ref schema_args)) => return schema::run(&args, schema_args).await,
+        #[cfg(feature = "keyring")]
+        Some(Command::Auth(ref auth_args)) => return auth::run(auth_args, args.profile.as_deref()),
+        None => {}
+    }
+
+    let mut stats = SessionStats::new();
+    tokio::select! {
+        result = watch(&args, &config, &opts, &mut stats) => result?,
+        _ = tokio::signal::ctrl_c() => {}
+    }
+
+    if watching(&args) {
+        if args.format == OutputFormat::Table && args.template.is_none() {
+            // Start on a fresh line in case Ctrl+C interrupted mid-frame
+            println!("\n{}", stats);
+        } else {
+            // Keep stdout pure JSON for pipelines
+            eprintln!("{}", stats);
+        }
+    }
+    Ok(())
+}
+
+/// Whether this run keeps refreshing rather than printing once
+fn watching(args: &Args) -> bool {
+    !(args.once || args.interval.is_zero())
+}
+
+async fn run_validate(args: &Args, path: Option<&Path>) -> Result<()> {
+    let (text, source) = match path {
+        Some(path) => (
+            std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?,
+            path.display().to_string(),
+        ),
+        None => {
+            let client = http_client(args)?;
+            (fetch_text(&client, &args.url, args.max_body_size).await?, args.url.clone())
+        }
+    };
+
+    let errors = if args.strict { schema::validate_strict(&text)? } else { schema::validate(&text)? };
+    if errors.is_empty() {
+        println!("{}{}OK{} {} matches the schema", BOLD, GREEN, RESET, source);
+        return Ok(());
+    }
+    for error in &errors {
+        println!("{}{}{}", RED, error, RESET);
+    }
+    anyhow::bail!("{} does not match the schema ({} problem{})", source, errors.len(), if errors.len() == 1 { "" } else { "s" })
+}
+
+async fn run_pick(args: &Args, config: &Config, opts: &RenderOptions, pick_args: &pick::PickArgs) -> Result<()> {
+    let client = http_client(args)?;
+    let mut data = fetch_data(&client, args, config, |_, _| Ok(())).await?;
+    apply_tag_filter(&mut data, opts);
+
+    match pick::pick(&data, pick_args)? {
+        Some(account) => {
+            println!("{}", account.email);
+            #[cfg(feature = "clipboard")]
+            if pick_args.copy {
+                pick::copy(&account.email)?;
+            }
+            Ok(())
+        }
+        None => {
+            // An error rather than exit(1), so an --ssh tunnel still gets closed
+            anyhow::bail!("No available account{}", match pick_args.model {
+                Some(ref m) => format!(" for {}", m),
+                None => String::new(),
+            });
+        }
+    }
+}
+
+async fn watch(args: &Args, config: &Config, opts: &RenderOptions, stats: &mut SessionStats) -> Result<()> {
+    let mut recording = match args.record {
+        // A session file replays one proxy's responses
+        Some(_) if args.urls.len() > 1 => anyhow::bail!("--record takes a single --url"),
+        Some(ref path) => Some(
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .with_context(|| format!("Failed to open session file {}", path))?,
+        ),
+        None => None,
+    };
+
+    // Reused across refreshes: keeps the connection alive and the frame buffer allocated
+    let client = http_client(args)?;
+    let mut frame = String::with_capacity(16 * 1024);
+    let mut alarm = ModelAlarm::new(&args.watch_model, args.flash);
+    let mut budgets = BudgetAlarm::default();
+    let mut estimator = CapacityEstimator::new();
+    let mut changes = (!args.no_highlight).then(ChangeMarker::new);
+    let mut deltas = QuotaDelta::new(args.delta_window);
+    let mut health = FetchHealth::new();
+    let mut overdue = HashSet::new();
+    let mut resize = Resize::new();
+    // A single fetch can't observe a transition, so only watch mode keeps the log
+    let mut tracker = if watching(args) {
+        Some(EventTracker::open(&config.thresholds))
+    } else {
+        None
+    };
+    let mut heartbeat = if watching(args) {
+        Heartbeat::new(args)?
+    } else {
+        None
+    };
+    let mut notifier = match tracker {
+        Some(_) => Notifier::new(args, config, Utc::now())?,
+        None => None,
+    };
+    let mut recorder = if watching(args) {
+        match config.history.backend {
+            HistoryBackend::Jsonl => Some(HistoryRecorder::open(config.history.retention_days)),
+        }
+    } else {
+        None
+    };
+    let mut forecaster = Forecaster::new(&config.forecast);
+    // A single fetch shows no rate-limited time, so it never touches the history
+    let mut limited_time = if watching(args) {
+        LimitedTime::load()
+    } else {
+        LimitedTime::default()
+    };
+    let mut roster = Roster::load(&args.labels);
+
+    let template = args.template.as_deref().map(|path| Template::load(path, &config.status)).transpose()?;
+    #[cfg(feature = "scripting")]
+    let script = args.script.as_deref().map(script::Script::load).transpose()?;
+    let table = args.format == OutputFormat::Table && template.is_none();
+
+    let mut last_fetch: Option<Instant> = None;
+    // Set once --fail-when holds; the frame is still shown before exiting
+    let mut tripped = false;
+    // The last good response and its alarm banner, redrawn between fetches
+    let mut live: Option<(ApiResponse, String)> = None;
+
+    for tick in 0.. {
+        if let Some(elapsed) = last_fetch.map(|at| at.elapsed()) {
+            if elapsed < MIN_FETCH_SPACING {
+                tokio::time::sleep(MIN_FETCH_SPACING - elapsed).await;
+            }
+        }
+        last_fetch = Some(Instant::now());
+
+        frame.clear();
+        // A single --problems-only check must print nothing at all when healthy
+        if table && (watching(args) || !args.problems_only) {
+            frame.push_str(CLEAR_SCREEN);
+        }
+        let now = Utc::now();
+        let mut pause;
 
-        match fetch_data(&args.url).await {
-            Ok(data) => print_table(&data),
+        let result = fetch_data(&client, args, config, |text, latency| {
+            health.record_response(latency, text.len());
+            match recording {
+                Some(ref mut file) => record_frame(file, text),
+                None => Ok(()),
+            }
+        })
+        .await;
+
+        match result {
+            Ok(mut data) => {
+                data.fetched_at = Some(now);
+                health.record_success(data.wrapped);
+                if let Some(ref mut heartbeat) = heartbeat {
+                    heartbeat.beat();
+                }
+                pause = jittered(health.backoff(args.interval, args.max_backoff), args.jitter);
+                // Before --tag narrows the accounts down
+                roster.observe(&mut data, now);
+                apply_tag_filter(&mut data, opts);
+                estimator.observe(&mut data);
+                if args.refetch_overdue && newly_overdue(&data, now, &mut overdue) {
+                    pause = pause.min(OVERDUE_REFETCH_DELAY);
+                }
+                stats.observe(&data);
+                if let Some(ref condition) = args.fail_when {
+                    tripped = condition.eval(&FleetScope { data: &data }).truthy();
+                }
+                if let Some(ref mut tracker) = tracker {
+                    let events = tracker.observe(&data, now);
+                    if let Some(ref mut notifier) = notifier {
+                        notifier.observe(&events, &data, opts, now);
+                    }
+                }
+                let sample = match recorder {
+                    Some(ref mut recorder) => recorder.record(&data, now),
+                    None => None,
+                };
+                limited_time.observe(sample, &mut data, now);
+                if args.summary_json_stderr {
+                    let digest = serde_json::to_string(&Digest::new(&data, now)).context("Failed to encode summary")?;
+                    eprintln!("{}", digest);
+                }
+                if table {
+                    #[cfg(feature = "scripting")]
+                    if let Some(ref script) = script {
+                        script.observe(&mut data, now)?;
+                    }
+                    forecaster.observe(&mut data, &config.models.groups, now)?;
+                    if let Some(ref mut changes) = changes {
+                        changes.observe(&mut data);
+                    }
+                    deltas.observe(&mut data, now);
+                    let banner_start = frame.len();
+                    alarm.render(&mut frame, &data)?;
+                    budgets.render(&mut frame, &data, config, now)?;
+                    let banner = frame[banner_start..].replace(alarm::BELL, "");
+                    render_frame(&mut frame, &data, now, opts, tick)?;
+                    if watching(args) {
+                        live = Some((data, banner));
+                    }
+                } else {
+                    sort_accounts(&mut data, opts, now);
+                    match template {
+                        Some(ref template) => template.render(&mut frame, &Snapshot::new(&data, now))?,
+                        None => render_json(&mut frame, &data, now, args.format == OutputFormat::Json)?,
+                    }
+                }
+            }
             Err(e) => {
-                println!("{}Error: {}{}", RED, e, RESET);
-                println!("\nMake sure the proxy is running at {}", args.url);
+                live = None;
+                if args.summary_json_stderr {
+                    eprintln!("{}", serde_json::json!({ "timestamp": now, "error": error::one_line(&e) }));
+                }
+                stats.record_error();
+                health.record_failure();
+                pause = jittered(health.backoff(args.interval, args.max_backoff), args.jitter);
+                if let Some(OnError::Value(ref text)) = args.on_error {
+                    // A bar takes the one line as-is; keep even the screen clear out of it
+                    if !watching(args) {
+                        frame.clear();
+                    }
+                    writeln!(frame, "{}", text)?;
+                    if args.debug {
+                        eprintln!("Error: {}", error::one_line(&e));
+                    }
+                } else if table {
+                    render_error(&mut frame, &e, args.debug)?;
+                    let urls = args.urls.join(", ");
+                    let hint = error::hint(&e, &urls).unwrap_or_else(|| format!("Make sure the proxy is running at {}", urls));
+                    writeln!(frame, "\n{}", hint)?;
+                } else if template.is_some() {
+                    // Templates feed status lines and banners; keep errors out of them
+                    eprintln!("Error: {}", e);
+                } else {
+                    render_json_error(&mut frame, &e, now)?;
+                }
             }
         }
 
-        if args.once || args.interval == 0 {
+        if !watching(args) {
+            let body = frame.strip_prefix(CLEAR_SCREEN).unwrap_or(&frame);
+            if !table || args.no_pager || !pager::should_page(body) || !pager::page(body).context("Pager failed")? {
+                flush_frame(&frame)?;
+            }
             break;
         }
 
-        println!("\n{}Refreshing every {}s... (Ctrl+C to exit){}", DIM, args.interval, RESET);
-        tokio::time::sleep(Duration::from_secs(args.interval)).await;
+        if !table {
+            flush_frame(&frame)?;
+            if tripped {
+                break;
+            }
+            tokio::time::sleep(pause).await;
+            continue;
+        }
+
+        render_footer(&mut frame, args, opts, &health, stats)?;
+        flush_frame(&frame)?;
+        if tripped {
+            break;
+        }
+        match live {
+            Some((ref data, ref banner)) => {
+                tick_countdowns(&mut frame, data, banner, pause, tick, args, opts, &health, stats, &mut resize).await?
+            }
+            None => tokio::time::sleep(pause).await,
+        }
     }
 
+    if tripped {
+        if let Some(ref condition) = args.fail_when {
+            anyhow::bail!("--fail-when condition holds: {}", condition);
+        }
+    }
     Ok(())
 }
+
+fn render_footer(
+    out: &mut String,
+    args: &Args,
+    opts: &RenderOptions,
+    health: &FetchHealth,
+    stats: &SessionStats,
+) -> fmt::Result {
+    // Summary-only and single-table output is meant for tiny panes; every line counts
+    if opts.summary_only || !opts.layout.header() {
+        return Ok(());
+    }
+    let pause = health.backoff(args.interval, args.max_backoff);
+    if pause > args.interval {
+        writeln!(
+            out,
+            "\n{}Backing off after failures: refreshing every {} (normally {})... (Ctrl+C to exit){}",
+            YELLOW,
+            humantime::format_duration(pause),
+            humantime::format_duration(args.interval),
+            RESET
+        )?;
+    } else {
+        writeln!(
+            out,
+            "\n{}Refreshing every {}... (Ctrl+C to exit){}",
+            DIM,
+            humantime::format_duration(args.interval),
+            RESET
+        )?;
+    }
+    writeln!(out, "{}{}{}", DIM, session::Footer(stats), RESET)?;
+    if opts.layout.details() {
+        writeln!(out, "{}{}{}", DIM, health, RESET)?;
+    }
+    Ok(())
+}
+
+/// Waits out `pause`, redrawing the last response every second so wait times,
+/// the next-reset banner and other countdowns keep moving between fetches,
+/// and at once when the terminal is resized
+#[allow(clippy::too_many_arguments)]
+async fn tick_countdowns(
+    frame: &mut String,
+    data: &ApiResponse,
+    banner: &str,
+    pause: Duration,
+    tick: usize,
+    args: &Args,
+    opts: &RenderOptions,
+    health: &FetchHealth,
+    stats: &SessionStats,
+    resize: &mut Resize,
+) -> Result<()> {
+    let deadline = Instant::now() + pause;
+    loop {
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return Ok(());
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(left.min(COUNTDOWN_TICK)) => {
+                if left <= COUNTDOWN_TICK {
+                    return Ok(());
+                }
+            }
+            _ = resize.changed() => {}
+        }
+
+        frame.clear();
+        frame.push_str(CLEAR_SCREEN);
+        frame.push_str(banner);
+        render_frame(frame, data, Utc::now(), opts, tick)?;
+        render_footer(frame, args, opts, health, stats)?;
+        flush_frame(frame)?;
+    }
+}
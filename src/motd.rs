@@ -0,0 +1,167 @@
+use crate::{
+    apply_tag_filter, config, count_stats, endpoints::fetch_data, fleet_next_reset, get_short_email, http_client,
+    model_available, Args, HumanDuration, RenderOptions, BOLD, DIM, GREEN, RED, RESET, YELLOW,
+};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use clap::{Args as ClapArgs, ValueEnum};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::Write as _,
+    fs,
+    io::IsTerminal,
+    path::PathBuf,
+    time::Duration,
+};
+
+#[derive(ClapArgs, Debug)]
+pub struct MotdArgs {
+    /// Give up on the proxy after this long and show the cached banner
+    #[arg(long, default_value = "800ms", value_parser = humantime::parse_duration)]
+    pub budget: Duration,
+
+    /// Show the cached banner without fetching while it is younger than this
+    #[arg(long, default_value = "60s", value_parser = humantime::parse_duration)]
+    pub max_age: Duration,
+
+    /// Color the banner: auto colors a terminal unless NO_COLOR is set
+    #[arg(long, value_enum, value_name = "WHEN", default_value_t = ColorChoice::Auto)]
+    pub color: ColorChoice,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+/// What the banner says, kept in `motd.json` so that the next login can
+/// show it without waiting on the proxy
+#[derive(Debug, Serialize, Deserialize)]
+struct Banner {
+    fetched_at: DateTime<Utc>,
+    total: usize,
+    available: usize,
+    rate_limited: usize,
+    invalid: usize,
+    /// Account, model and time of the soonest reset
+    next_reset: Option<(String, String, DateTime<Utc>)>,
+    /// Models no account can serve, then invalid accounts
+    issues: Vec<String>,
+}
+
+fn cache_path() -> Option<PathBuf> {
+    config::data_dir().map(|dir| dir.join("motd.json"))
+}
+
+fn read_cache() -> Option<Banner> {
+    let text = fs::read_to_string(cache_path()?).ok()?;
+    serde_json::from_str(&text).ok()
+}
+
+fn write_cache(banner: &Banner) -> Result<()> {
+    let Some(path) = cache_path() else {
+        return Ok(());
+    };
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create data dir {}", dir.display()))?;
+    }
+    let text = serde_json::to_string(banner).context("Failed to encode banner")?;
+    fs::write(&path, text).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+async fn fetch(args: &Args, opts: &RenderOptions, now: DateTime<Utc>) -> Result<Banner> {
+    let client = http_client(args)?;
+    let mut data = fetch_data(&client, args, &opts.config, |_, _| Ok(())).await?;
+    apply_tag_filter(&mut data, opts);
+    let (available, rate_limited, invalid, _) = count_stats(&data.accounts);
+    let mut issues: Vec<String> = data
+        .models
+        .iter()
+        .filter(|model| !data.accounts.iter().any(|a| model_available(a, model)))
+        .map(|model| format!("{} has no available account", model))
+        .collect();
+    issues.extend(
+        data.accounts
+            .iter()
+            .filter(|a| a.is_invalid.unwrap_or(false))
+            .map(|a| format!("{} is invalid", get_short_email(&a.email))),
+    );
+    Ok(Banner {
+        fetched_at: now,
+        total: data.accounts.len(),
+        available,
+        rate_limited,
+        invalid,
+        next_reset: fleet_next_reset(&data, now).map(|(a, model, at)| (a.email.to_string(), model.to_string(), at)),
+        issues,
+    })
+}
+
+fn render(banner: &Banner, now: DateTime<Utc>, color: bool) -> String {
+    let paint = |code: &'static str| if color { code } else { "" };
+    let mut out = String::new();
+    let available = if banner.available == 0 { RED } else { GREEN };
+    let _ = write!(
+        out,
+        "{}AG quota{}  {}{}/{} available{}",
+        paint(BOLD),
+        paint(RESET),
+        paint(available),
+        banner.available,
+        banner.total,
+        paint(RESET)
+    );
+    if banner.rate_limited > 0 {
+        let _ = write!(out, ", {}{} rate-limited{}", paint(YELLOW), banner.rate_limited, paint(RESET));
+    }
+    if banner.invalid > 0 {
+        let _ = write!(out, ", {}{} invalid{}", paint(RED), banner.invalid, paint(RESET));
+    }
+    if let Some((ref email, ref model, at)) = banner.next_reset {
+        if at > now {
+            let _ = write!(out, "; next reset {}/{} in {}", get_short_email(email), model, HumanDuration(at - now));
+        }
+    }
+    if now - banner.fetched_at > chrono::Duration::minutes(1) {
+        let _ = write!(out, " {}(as of {} ago){}", paint(DIM), HumanDuration(now - banner.fetched_at), paint(RESET));
+    }
+    out.push('\n');
+    for issue in &banner.issues {
+        let _ = writeln!(out, "  {}! {}{}", paint(RED), issue, paint(RESET));
+    }
+    out
+}
+
+/// `motd` subcommand: a few lines for login banners and shell rc files. A
+/// login never waits on the proxy for longer than `--budget`; when it is
+/// slow or down, the banner from the last successful run is shown, and
+/// without one nothing at all.
+pub async fn run(args: &Args, opts: &RenderOptions, motd_args: &MotdArgs) -> Result<()> {
+    let now = Utc::now();
+    let cached = read_cache();
+    let fresh = cached.as_ref().filter(|banner| {
+        (now - banner.fetched_at).to_std().is_ok_and(|age| age < motd_args.max_age)
+    });
+    let banner = match fresh {
+        Some(_) => cached,
+        None => match tokio::time::timeout(motd_args.budget, fetch(args, opts, now)).await {
+            Ok(Ok(banner)) => {
+                // A read-only data dir shouldn't cost the banner
+                let _ = write_cache(&banner);
+                Some(banner)
+            }
+            Ok(Err(_)) | Err(_) => cached,
+        },
+    };
+    let color = match motd_args.color {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none(),
+    };
+    if let Some(banner) = banner {
+        print!("{}", render(&banner, now, color));
+    }
+    Ok(())
+}
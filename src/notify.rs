@@ -0,0 +1,290 @@
+use crate::{
+    config::{Config, GotifyConfig, PushoverConfig, StatusLabels},
+    count_stats, discord,
+    events::Event,
+    get_short_email, gotify, parse_reset, pushover, render_header, slack,
+    telegram::{self, Telegram},
+    ApiResponse, Args, HumanDuration, RenderOptions,
+};
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use serde::Deserialize;
+use std::{
+    fmt::Write as _,
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tokio::sync::mpsc;
+
+/// A chat service is given this long to take a message
+const POST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Waits before trying a message again after the service failed to take it
+const RETRY_DELAYS: [Duration; 2] = [Duration::from_secs(1), Duration::from_secs(3)];
+/// Messages that may wait for one service; past this, new ones are dropped
+const QUEUE_LEN: usize = 32;
+
+/// How bad the state an account or quota moved to is, which picks the
+/// color or priority of its message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    #[default]
+    Good,
+    Warning,
+    Danger,
+}
+
+impl Level {
+    /// The level of an event's `to` state
+    pub fn of(state: &str) -> Level {
+        match state {
+            "ok" => Level::Good,
+            "invalid" | "exhausted" => Level::Danger,
+            _ => Level::Warning,
+        }
+    }
+}
+
+type Delivery = Pin<Box<dyn Future<Output = Result<()>> + Send>>;
+
+/// Delivers one service's messages in order on a task of its own, so that a
+/// slow or unreachable service never holds up a refresh
+struct Outbox {
+    service: &'static str,
+    queue: mpsc::Sender<Delivery>,
+}
+
+impl Outbox {
+    fn new(service: &'static str) -> Outbox {
+        let (queue, mut deliveries) = mpsc::channel::<Delivery>(QUEUE_LEN);
+        tokio::spawn(async move {
+            while let Some(delivery) = deliveries.recv().await {
+                if let Err(e) = delivery.await {
+                    eprintln!("Warning: {} notification failed: {:#}", service, e);
+                }
+            }
+        });
+        Outbox { service, queue }
+    }
+
+    /// Queues `delivery`, or drops it with a warning when the queue is full;
+    /// returns whether it was queued
+    fn send(&self, delivery: impl Future<Output = Result<()>> + Send + 'static) -> bool {
+        let queued = self.queue.try_send(Box::pin(delivery)).is_ok();
+        if !queued {
+            eprintln!("Warning: {} is {} messages behind, dropping one", self.service, QUEUE_LEN);
+        }
+        queued
+    }
+
+    /// Posts `message` to the webhook at `url`
+    fn post(&self, client: &reqwest::Client, url: &str, message: serde_json::Value) {
+        let (client, url) = (client.clone(), url.to_string());
+        self.send(async move { post_json(&client, &url, &message).await });
+    }
+}
+
+/// Posts the state changes watch mode observes to the chat webhooks given on
+/// the command line and the `[telegram]` bot, and once a day a summary of the
+/// fleet to Slack. A message that fails to go out is reported on stderr; the
+/// watch carries on without waiting for any of them.
+pub struct Notifier {
+    client: reqwest::Client,
+    slack: Option<(String, Outbox)>,
+    discord: Option<(String, Outbox)>,
+    telegram: Option<(Telegram, Outbox)>,
+    gotify: Option<(GotifyConfig, Outbox)>,
+    pushover: Option<(PushoverConfig, Outbox)>,
+    #[cfg(feature = "xmpp")]
+    xmpp: Option<(crate::config::XmppConfig, Outbox)>,
+    /// Set while a check for the bot's commands waits in the Telegram queue,
+    /// so that a slow Bot API fills it with one check rather than crowding
+    /// out the alerts
+    polling: Arc<AtomicBool>,
+    /// The day the last summary went out, so each day gets one
+    summarized: NaiveDate,
+}
+
+impl Notifier {
+    /// A notifier for the webhooks in `args` and the services in the config,
+    /// or none when there are neither
+    pub fn new(args: &Args, config: &Config, now: DateTime<Utc>) -> Result<Option<Notifier>> {
+        if cfg!(not(feature = "xmpp")) && config.xmpp.is_some() {
+            bail!("[xmpp] needs a build with the xmpp feature");
+        }
+        let configured = config.telegram.is_some()
+            || config.gotify.is_some()
+            || config.pushover.is_some()
+            || config.xmpp.is_some();
+        if args.slack_webhook.is_none() && args.discord_webhook.is_none() && !configured {
+            return Ok(None);
+        }
+        let client = reqwest::Client::builder()
+            .timeout(POST_TIMEOUT)
+            .build()
+            .context("Failed to create HTTP client")?;
+        let local = now.with_timezone(&Local);
+        // Starting after today's summary time waits for tomorrow's
+        let summarized = if local.time() >= config.notify.summary_at {
+            local.date_naive()
+        } else {
+            local.date_naive().pred_opt().unwrap_or(NaiveDate::MIN)
+        };
+        Ok(Some(Notifier {
+            client,
+            slack: args.slack_webhook.clone().map(|url| (url, Outbox::new("Slack"))),
+            discord: args.discord_webhook.clone().map(|url| (url, Outbox::new("Discord"))),
+            telegram: config.telegram.as_ref().map(|config| (Telegram::new(config), Outbox::new("Telegram"))),
+            gotify: config.gotify.clone().map(|config| (config, Outbox::new("Gotify"))),
+            pushover: config.pushover.clone().map(|config| (config, Outbox::new("Pushover"))),
+            #[cfg(feature = "xmpp")]
+            xmpp: config.xmpp.clone().map(|config| (config, Outbox::new("XMPP"))),
+            polling: Arc::new(AtomicBool::new(false)),
+            summarized,
+        }))
+    }
+
+    /// Queues `events` from the latest refresh, then the summary when it is
+    /// due, and a check for the bot's commands
+    pub fn observe(&mut self, events: &[Event], data: &ApiResponse, opts: &RenderOptions, now: DateTime<Utc>) {
+        let labels = &opts.config.status;
+        if !events.is_empty() {
+            if let Some((ref url, ref outbox)) = self.slack {
+                outbox.post(&self.client, url, slack::events_message(events, data, labels, now));
+            }
+            if let Some((ref url, ref outbox)) = self.discord {
+                // Discord takes at most ten embeds per message
+                for chunk in events.chunks(discord::MAX_EMBEDS) {
+                    outbox.post(&self.client, url, discord::events_message(chunk, data, labels, now));
+                }
+            }
+            if let Some((ref bot, ref outbox)) = self.telegram {
+                let text = telegram::events_message(events, data, labels, now);
+                let (bot, client) = (bot.clone(), self.client.clone());
+                outbox.send(async move { bot.send(&client, text).await });
+            }
+            if let Some((ref gotify, ref outbox)) = self.gotify {
+                if let Some((title, body, level)) = plain_text_from(events, gotify.min_level, data, labels, now) {
+                    let (client, gotify) = (self.client.clone(), gotify.clone());
+                    outbox.send(async move { gotify::send(&client, &gotify, title, body, level).await });
+                }
+            }
+            if let Some((ref pushover, ref outbox)) = self.pushover {
+                if let Some((title, body, level)) = plain_text_from(events, pushover.min_level, data, labels, now) {
+                    let (client, pushover) = (self.client.clone(), pushover.clone());
+                    outbox.send(async move { pushover::send(&client, &pushover, title, body, level).await });
+                }
+            }
+            #[cfg(feature = "xmpp")]
+            if let Some((ref xmpp, ref outbox)) = self.xmpp {
+                // Each message logs in afresh, which takes seconds; the queue keeps that off the refresh
+                if let Some((title, body, _)) = plain_text_from(events, xmpp.min_level, data, labels, now) {
+                    let xmpp = xmpp.clone();
+                    outbox.send(async move { crate::xmpp::send(&xmpp, format!("{}\n{}", title, body)).await });
+                }
+            }
+        }
+
+        let local = now.with_timezone(&Local);
+        if local.date_naive() > self.summarized && local.time() >= opts.config.notify.summary_at {
+            self.summarized = local.date_naive();
+            if let Some((ref url, ref outbox)) = self.slack {
+                outbox.post(&self.client, url, slack::summary_message(data, &opts.config, now));
+            }
+        }
+
+        if let Some((ref bot, ref outbox)) = self.telegram {
+            if bot.answers_commands() && !self.polling.swap(true, Ordering::Relaxed) {
+                // The reply is rendered now, as the bot can't know whether anyone asked
+                // until its turn in the queue comes
+                let mut header = String::new();
+                let queued = render_header(&mut header, data, now, opts).is_ok() && {
+                    let (bot, client, polling) = (bot.clone(), self.client.clone(), self.polling.clone());
+                    outbox.send(async move {
+                        let answered = bot.answer(&client, header).await;
+                        polling.store(false, Ordering::Relaxed);
+                        answered.context("Commands failed")
+                    })
+                };
+                if !queued {
+                    self.polling.store(false, Ordering::Relaxed);
+                }
+            }
+        }
+    }
+}
+
+async fn post_json(client: &reqwest::Client, url: &str, message: &serde_json::Value) -> Result<()> {
+    send_retrying(|| client.post(url).json(message)).await
+}
+
+/// Sends the request `build` makes, and again after each of `RETRY_DELAYS`
+/// while the service can't be reached or answers 429 or a 5xx. Other
+/// refusals won't go better on a second try.
+pub async fn send_retrying(build: impl Fn() -> reqwest::RequestBuilder) -> Result<()> {
+    let mut delays = RETRY_DELAYS.iter();
+    loop {
+        let error = match build().send().await {
+            Ok(response) if response.status().is_success() => return Ok(()),
+            Ok(response) => {
+                let status = response.status();
+                let body = response.text().await.unwrap_or_default();
+                let error = anyhow::anyhow!("Service answered {}: {}", status, body.trim());
+                if !(status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS) {
+                    return Err(error);
+                }
+                error
+            }
+            // Tokens travel in some services' URLs
+            Err(e) => anyhow::Error::new(e.without_url()).context("Failed to reach the service"),
+        };
+        match delays.next() {
+            Some(delay) => tokio::time::sleep(*delay).await,
+            None => return Err(error),
+        }
+    }
+}
+
+/// [`plain_text`] of the events at `min_level` or worse, with the level of
+/// the worst of them, or none when no event is that bad
+fn plain_text_from(
+    events: &[Event],
+    min_level: Level,
+    data: &ApiResponse,
+    labels: &StatusLabels,
+    now: DateTime<Utc>,
+) -> Option<(String, String, Level)> {
+    let events: Vec<Event> = events.iter().filter(|e| Level::of(&e.to) >= min_level).cloned().collect();
+    let level = events.iter().map(|e| Level::of(&e.to)).max()?;
+    let (title, body) = plain_text(&events, data, labels, now);
+    Some((title, body, level))
+}
+
+/// A title and one line per event, for the services that take plain text
+pub fn plain_text(events: &[Event], data: &ApiResponse, labels: &StatusLabels, now: DateTime<Utc>) -> (String, String) {
+    let (available, _, _, _) = count_stats(&data.accounts);
+    let title = format!("AG quota: {}/{} accounts available", available, data.accounts.len());
+    let mut body = String::new();
+    for event in events {
+        let name = get_short_email(&event.account);
+        let (from, to) = (labels.label(&event.from), labels.label(&event.to));
+        let _ = match event.model {
+            Some(ref model) => write!(body, "{} {}: {} -> {}", name, model, from, to),
+            None => write!(body, "{}: {} -> {}", name, from, to),
+        };
+        let reset = event.model.as_ref().and_then(|model| {
+            let account = data.accounts.iter().find(|a| a.email == event.account)?;
+            parse_reset(account.limits.as_ref()?.get(model.as_str())?).filter(|at| *at > now)
+        });
+        if let Some(at) = reset {
+            let _ = write!(body, ", resets in {}", HumanDuration(at - now));
+        }
+        body.push('\n');
+    }
+    (title, body.trim_end().to_string())
+}
@@ -0,0 +1,52 @@
+use std::{
+    env,
+    io::{self, IsTerminal, Write},
+    process::{Command, Stdio},
+};
+use terminal_size::{terminal_size, Height};
+
+/// Whether `text` should go through a pager: stdout is a terminal and the
+/// text has more lines than fit on screen.
+pub fn should_page(text: &str) -> bool {
+    if !io::stdout().is_terminal() {
+        return false;
+    }
+    match terminal_size() {
+        Some((_, Height(rows))) => text.lines().count() > rows as usize,
+        None => false,
+    }
+}
+
+/// Pipes `text` through `$PAGER` (default `less -R`). Returns `false` if
+/// the pager could not be started so the caller can print directly instead.
+pub fn page(text: &str) -> io::Result<bool> {
+    let pager = env::var("PAGER")
+        .ok()
+        .filter(|p| !p.trim().is_empty())
+        .unwrap_or_else(|| "less -R".to_string());
+    let mut parts = pager.split_whitespace();
+    let Some(program) = parts.next() else {
+        return Ok(false);
+    };
+
+    let mut command = Command::new(program);
+    command.args(parts).stdin(Stdio::piped());
+    // Keep colors when the user's PAGER is a bare `less`
+    if env::var_os("LESS").is_none() {
+        command.env("LESS", "FRX");
+    }
+
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(_) => return Ok(false),
+    };
+    if let Some(mut stdin) = child.stdin.take() {
+        // Quitting the pager early closes the pipe; that's not an error
+        match stdin.write_all(text.as_bytes()) {
+            Err(e) if e.kind() != io::ErrorKind::BrokenPipe => return Err(e),
+            _ => {}
+        }
+    }
+    child.wait()?;
+    Ok(true)
+}
@@ -0,0 +1,135 @@
+use crate::{api::ApiVersion, error::FetchError, fetch_text};
+use reqwest::Url;
+use serde_json::Value;
+use std::{
+    collections::HashSet,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+/// Where a page names the next one: a URL, a path or an opaque cursor
+const NEXT_KEYS: [&str; 4] = ["next", "nextCursor", "next_cursor", "nextPageToken"];
+/// Page counts that go with a numeric `page`
+const TOTAL_KEYS: [&str; 3] = ["totalPages", "total_pages", "pages"];
+
+/// Set once `--max-pages` cut a walk short, so a watch warns only once
+static TRUNCATED: AtomicBool = AtomicBool::new(false);
+
+/// The account document in `text`, unwrapped from `{"result": "..."}`
+pub fn document(text: &str) -> Option<Value> {
+    let value: Value = serde_json::from_str(text).ok()?;
+    match value.get("result").and_then(Value::as_str) {
+        Some(inner) => serde_json::from_str(inner).ok(),
+        None => Some(value),
+    }
+}
+
+/// `url` with `key` set to `value`, replacing an earlier one
+fn with_param(mut url: Url, key: &str, value: &str) -> String {
+    let kept: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(k, _)| k != key)
+        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+        .collect();
+    url.query_pairs_mut().clear().extend_pairs(kept).append_pair(key, value);
+    url.into()
+}
+
+/// The number of the first page, for the proxies that count from 0
+fn first_page(doc: &Value) -> u64 {
+    doc.get("page").and_then(Value::as_u64).map_or(1, |page| page.min(1))
+}
+
+/// The URL of the page after `doc`, which was fetched from `current`; a
+/// numeric `page` counts from `first`
+fn next_page(current: &str, doc: &Value, first: u64) -> Option<String> {
+    let base = Url::parse(current).ok()?;
+    let next = NEXT_KEYS
+        .iter()
+        .find_map(|key| doc.get(key)?.as_str())
+        .filter(|next| !next.is_empty());
+    if let Some(next) = next {
+        if next.contains("://") || next.starts_with('/') || next.starts_with('?') {
+            return base.join(next).ok().map(String::from);
+        }
+        return Some(with_param(base, "cursor", next));
+    }
+    let page = doc.get("page")?.as_u64()?;
+    let pages = TOTAL_KEYS.iter().find_map(|key| doc.get(key)?.as_u64())?;
+    // Pages seen so far, which is `page` itself when they count from 1
+    (page.saturating_sub(first) + 1 < pages).then(|| with_param(base, "page", &(page + 1).to_string()))
+}
+
+/// Appends `page`'s accounts to `doc`, and any models `doc` doesn't list yet
+fn merge(doc: &mut Value, page: Value) {
+    let Value::Object(mut page) = page else {
+        return;
+    };
+    if let (Some(Value::Array(accounts)), Some(Value::Array(more))) = (doc.get_mut("accounts"), page.remove("accounts")) {
+        accounts.extend(more);
+    }
+    if let (Some(Value::Array(models)), Some(Value::Array(more))) = (doc.get_mut("models"), page.remove("models")) {
+        for model in more {
+            if !models.contains(&model) {
+                models.push(model);
+            }
+        }
+    }
+}
+
+/// Fetches `url` and, when the proxy paginates its accounts, every following
+/// page, up to `max_pages` in all; past that, the pages so far are returned
+/// with a warning. The pages come back as one document in the first one's
+/// shape, so what reads the body never sees the paging; a single page is
+/// returned as sent. A page without accounts ends the walk.
+pub async fn fetch_pages(
+    client: &reqwest::Client,
+    url: &str,
+    max_body: u64,
+    max_pages: usize,
+) -> Result<String, FetchError> {
+    let first = fetch_text(client, url, max_body).await?;
+    let Some(mut doc) = document(&first) else {
+        return Ok(first);
+    };
+    let base = first_page(&doc);
+    let Some(mut next) = next_page(url, &doc, base) else {
+        return Ok(first);
+    };
+
+    let mut total = first.len() as u64;
+    let mut fetched = HashSet::from([url.to_string()]);
+    // A proxy that links back to a page it already sent ends the walk
+    while fetched.insert(next.clone()) {
+        if fetched.len() > max_pages {
+            if !TRUNCATED.swap(true, Ordering::Relaxed) {
+                eprintln!(
+                    "Warning: accounts continue past {} pages; showing only those (raise --max-pages)",
+                    max_pages
+                );
+            }
+            break;
+        }
+        let text = fetch_text(client, &next, max_body).await?;
+        total += text.len() as u64;
+        if total > max_body {
+            return Err(FetchError::TooLarge { limit: max_body });
+        }
+        let page = document(&text).ok_or_else(|| FetchError::shape(format!("Page {} is not JSON", next), &text))?;
+        let empty = page.get("accounts").and_then(Value::as_array).is_some_and(Vec::is_empty);
+        let following = next_page(&next, &page, base);
+        merge(&mut doc, page);
+        if empty {
+            break;
+        }
+        match following {
+            Some(url) => next = url,
+            None => break,
+        }
+    }
+    if let Value::Object(ref mut fields) = doc {
+        for key in NEXT_KEYS.iter().chain(&TOTAL_KEYS).chain(&["page"]) {
+            fields.remove(*key);
+        }
+    }
+    Ok(ApiVersion::detect(&first).encode(doc.to_string()))
+}
@@ -0,0 +1,248 @@
+use crate::{intern::Name, config, get_short_email, locale::Percent, model_available, Account, ApiResponse, BOLD, DIM, RESET};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use clap::{Args as ClapArgs, ValueEnum};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::PathBuf,
+};
+
+/// Recent picks count against an account with a weight that halves every 30 minutes
+const HALF_LIFE_MINUTES: f64 = 30.0;
+/// Picks older than this no longer influence fairness and are dropped
+const PICK_HISTORY_HOURS: i64 = 24;
+
+#[derive(ClapArgs, Debug)]
+pub struct PickArgs {
+    /// Only consider accounts that can serve this model
+    #[arg(short, long)]
+    pub model: Option<String>,
+
+    /// How to choose among available accounts
+    #[arg(long, value_enum, default_value_t = Strategy::Fair)]
+    pub strategy: Strategy,
+
+    /// Choose from a filterable list on the terminal instead of automatically
+    #[arg(short, long)]
+    pub interactive: bool,
+
+    /// Also copy the chosen email to the system clipboard
+    #[cfg(feature = "clipboard")]
+    #[arg(long)]
+    pub copy: bool,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    /// Always the account with the most remaining quota
+    Highest,
+    /// Most remaining quota, discounted by how recently the account was picked
+    Fair,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PickLog {
+    picks: Vec<PickEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PickEntry {
+    email: String,
+    at: DateTime<Utc>,
+}
+
+fn log_path() -> Option<PathBuf> {
+    config::data_dir().map(|dir| dir.join("picks.json"))
+}
+
+impl PickLog {
+    /// Reads the pick log; a missing or unreadable log just means no history
+    fn load() -> PickLog {
+        log_path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self) -> Result<()> {
+        let Some(path) = log_path() else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create data dir {}", dir.display()))?;
+        }
+        let text = serde_json::to_string(self).context("Failed to encode pick log")?;
+        fs::write(&path, text).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Sum of exponentially decayed weights of recent picks of `email`
+    fn recency_penalty(&self, email: &str, now: DateTime<Utc>) -> f64 {
+        self.picks
+            .iter()
+            .filter(|p| p.email == email)
+            .map(|p| {
+                let age_minutes = (now - p.at).num_seconds().max(0) as f64 / 60.0;
+                0.5f64.powf(age_minutes / HALF_LIFE_MINUTES)
+            })
+            .sum()
+    }
+
+    fn push(&mut self, email: &str, now: DateTime<Utc>) {
+        let cutoff = now - chrono::Duration::hours(PICK_HISTORY_HOURS);
+        self.picks.retain(|p| p.at > cutoff);
+        self.picks.push(PickEntry {
+            email: email.to_string(),
+            at: now,
+        });
+    }
+}
+
+/// Remaining quota used to rank an account: the model's fraction with
+/// `--model`, otherwise the mean over listed models it can serve
+fn quota_score(account: &Account, models: &[Name], model: Option<&str>) -> Option<f64> {
+    let limits = account.limits.as_ref()?;
+    match model {
+        Some(m) => model_available(account, m).then(|| limits[m].remaining_fraction),
+        None => {
+            let fractions: Vec<f64> = models
+                .iter()
+                .filter(|m| model_available(account, m))
+                .map(|m| limits[m.as_str()].remaining_fraction)
+                .collect();
+            if fractions.is_empty() {
+                None
+            } else {
+                Some(fractions.iter().sum::<f64>() / fractions.len() as f64)
+            }
+        }
+    }
+}
+
+/// Available accounts, best first according to `args.strategy`
+fn ranked<'a>(data: &'a ApiResponse, args: &PickArgs, log: &PickLog, now: DateTime<Utc>) -> Vec<&'a Account> {
+    let mut scored: Vec<(&Account, f64)> = data
+        .accounts
+        .iter()
+        .filter_map(|a| {
+            let score = quota_score(a, &data.models, args.model.as_deref())?;
+            let score = match args.strategy {
+                Strategy::Highest => score,
+                Strategy::Fair => score / (1.0 + log.recency_penalty(&a.email, now)),
+            };
+            Some((a, score))
+        })
+        .collect();
+    scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+    scored.into_iter().map(|(a, _)| a).collect()
+}
+
+/// Chooses an account from `data` and records the choice for future fairness
+pub fn pick<'a>(data: &'a ApiResponse, args: &PickArgs) -> Result<Option<&'a Account>> {
+    let now = Utc::now();
+    let mut log = PickLog::load();
+
+    let candidates = ranked(data, args, &log, now);
+    let best = if args.interactive {
+        choose(&candidates, &data.models)?
+    } else {
+        candidates.first().copied()
+    };
+
+    if let Some(account) = best {
+        log.push(&account.email, now);
+        log.save()?;
+    }
+    Ok(best)
+}
+
+/// Puts `text` on the system clipboard. On X11 and Wayland the contents are
+/// handed to the clipboard manager on exit, so one must be running.
+#[cfg(feature = "clipboard")]
+pub fn copy(text: &str) -> Result<()> {
+    arboard::Clipboard::new()
+        .and_then(|mut clipboard| clipboard.set_text(text))
+        .context("Failed to copy to the clipboard")
+}
+
+/// Case-insensitive subsequence match, so `alc` finds `alice@example.com`
+fn fuzzy_match(query: &str, text: &str) -> bool {
+    let mut chars = text.chars().flat_map(char::to_lowercase);
+    query
+        .chars()
+        .flat_map(char::to_lowercase)
+        .all(|q| chars.any(|c| c == q))
+}
+
+/// Interactive `pick -i`: lists `candidates` with a quota preview on the
+/// terminal and narrows them by fuzzy filter until one is chosen. Talks to
+/// `/dev/tty` directly so it works inside `$(...)`.
+fn choose<'a>(candidates: &[&'a Account], models: &[Name]) -> Result<Option<&'a Account>> {
+    if candidates.is_empty() {
+        return Ok(None);
+    }
+    let mut tty = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/tty")
+        .context("pick -i needs a terminal")?;
+    let mut input = BufReader::new(tty.try_clone().context("pick -i needs a terminal")?);
+
+    let mut query = String::new();
+    loop {
+        let matches: Vec<&Account> = candidates
+            .iter()
+            .copied()
+            .filter(|a| fuzzy_match(&query, &a.email))
+            .collect();
+        list(&mut tty, &matches, models)?;
+
+        if query.is_empty() {
+            write!(tty, "{}Filter or number{} (Enter takes #1)> ", BOLD, RESET)?;
+        } else {
+            write!(tty, "{}Filter [{}] or number{} (Enter takes #1)> ", BOLD, query, RESET)?;
+        }
+        tty.flush()?;
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim();
+
+        if line.is_empty() {
+            if let Some(first) = matches.first() {
+                return Ok(Some(first));
+            }
+            // Nothing left to take: start over with the full list
+            query.clear();
+        } else if let Ok(n) = line.parse::<usize>() {
+            match n.checked_sub(1).and_then(|i| matches.get(i)) {
+                Some(account) => return Ok(Some(account)),
+                None => writeln!(tty, "No entry {}", n)?,
+            }
+        } else {
+            query = line.to_string();
+        }
+    }
+}
+
+fn list(tty: &mut File, accounts: &[&Account], models: &[Name]) -> Result<()> {
+    writeln!(tty)?;
+    if accounts.is_empty() {
+        writeln!(tty, "{}No matching accounts{}", DIM, RESET)?;
+    }
+    for (i, account) in accounts.iter().enumerate() {
+        write!(tty, "{:>3}) {:<20}", i + 1, get_short_email(&account.email))?;
+        if let Some(ref limits) = account.limits {
+            for model in models {
+                if let Some(quota) = limits.get(model) {
+                    write!(tty, " {}{}{} {}", DIM, model, RESET, Percent(quota.remaining_fraction))?;
+                }
+            }
+        }
+        writeln!(tty)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,71 @@
+use crate::{
+    intern::Name,
+    get_account_status, get_short_email, locale::Percent, parse_reset, Account, HumanDuration, RenderOptions, RED,
+    RESET, YELLOW,
+};
+use chrono::{DateTime, Utc};
+use std::fmt::{self, Write as _};
+
+/// `--problems-only`: a line per thing that needs attention, and nothing at
+/// all when every account is fine, so that cron mails and MOTD hooks stay
+/// silent while the fleet is healthy. Disabled accounts were turned off on
+/// purpose and don't count.
+pub fn render(
+    out: &mut String,
+    accounts: &[&Account],
+    models: &[Name],
+    now: DateTime<Utc>,
+    opts: &RenderOptions,
+) -> fmt::Result {
+    let thresholds = &opts.config.thresholds;
+    let width = accounts
+        .iter()
+        .map(|a| get_short_email(&a.email).chars().count())
+        .max()
+        .unwrap_or(0);
+    for account in accounts {
+        let name = get_short_email(&account.email);
+        let (status, _) = get_account_status(account);
+        match status {
+            "invalid" => {
+                writeln!(out, "{}{:<width$}  invalid{}", RED, name, RESET)?;
+                continue;
+            }
+            "disabled" => continue,
+            "limited" => {
+                let mut models: Vec<&str> = account
+                    .model_rate_limits
+                    .iter()
+                    .flatten()
+                    .filter(|(_, limit)| limit.is_rate_limited)
+                    .map(|(model, _)| model.as_str())
+                    .collect();
+                models.sort_unstable();
+                writeln!(out, "{}{:<width$}  rate-limited on {}{}", YELLOW, name, models.join(", "), RESET)?;
+            }
+            _ => {}
+        }
+        for model in models {
+            let Some(quota) = account.limits.as_ref().and_then(|l| l.get(model)) else {
+                continue;
+            };
+            if quota.remaining_fraction >= thresholds.quota_warning {
+                continue;
+            }
+            let color = if quota.remaining_fraction <= 0.0 { RED } else { YELLOW };
+            write!(out, "{}{:<width$}  {} at {}", color, name, model, Percent(quota.remaining_fraction))?;
+            if let Some(at) = parse_reset(quota).filter(|at| *at > now) {
+                write!(out, ", resets in {}", HumanDuration(at - now))?;
+            }
+            writeln!(out, "{}", RESET)?;
+        }
+        if let Some(left) = account.credentials_expiring(now, thresholds) {
+            if left > chrono::Duration::zero() {
+                writeln!(out, "{}{:<width$}  credentials expire in {}{}", YELLOW, name, HumanDuration(left), RESET)?;
+            } else {
+                writeln!(out, "{}{:<width$}  credentials expired{}", RED, name, RESET)?;
+            }
+        }
+    }
+    Ok(())
+}
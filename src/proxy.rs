@@ -0,0 +1,148 @@
+use crate::{
+    clock_skew, config::Config, count_stats, describe_skew, fetch_text, fanout::FetchPlan, http_client, parse_response,
+    Args, HumanDuration, BOLD, DIM, GREEN, RED, RESET, YELLOW,
+};
+use chrono::Utc;
+use anyhow::{bail, Context, Result};
+use clap::{Args as ClapArgs, Subcommand};
+use serde_json::Value;
+use std::time::Instant;
+
+#[derive(ClapArgs, Debug)]
+pub struct ProxyArgs {
+    #[command(subcommand)]
+    pub command: ProxyCommand,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ProxyCommand {
+    /// Check the proxy's health and version endpoints and summarize its accounts
+    Status,
+}
+
+/// `path` on the same host as the account-limits `url`
+pub fn endpoint(url: &str, path: &str) -> Result<reqwest::Url> {
+    let base = reqwest::Url::parse(url).with_context(|| format!("Invalid URL {}", url))?;
+    base.join(path).with_context(|| format!("Invalid endpoint path {}", path))
+}
+
+fn label(name: &str) {
+    print!("  {}{:<10}{}", BOLD, name, RESET);
+}
+
+/// Strings as-is, everything else as compact JSON
+fn scalar(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// `proxy` subcommand
+pub async fn run(args: &Args, config: &Config, proxy_args: &ProxyArgs) -> Result<()> {
+    match proxy_args.command {
+        ProxyCommand::Status => status(args, config).await,
+    }
+}
+
+/// `proxy status`, also run as `doctor`
+pub async fn status(args: &Args, config: &Config) -> Result<()> {
+    let client = http_client(args)?;
+    let paths = &config.proxy;
+    println!("{}Proxy{} {}", BOLD, RESET, endpoint(&args.url, "/")?);
+
+    let health_url = endpoint(&args.url, &paths.health_path)?;
+    let started = Instant::now();
+    let health = fetch_text(&client, health_url.as_str(), args.max_body_size).await;
+    let latency = started.elapsed();
+    let health: Option<Value> = health.as_ref().ok().map(|text| {
+        serde_json::from_str(text).unwrap_or_else(|_| Value::String(text.trim().to_string()))
+    });
+
+    label("Health");
+    match (&health, health.as_ref().and_then(|h| h.get("status"))) {
+        (Some(_), Some(status)) => {
+            let status = scalar(status);
+            let color = if matches!(status.as_str(), "ok" | "healthy" | "up") { GREEN } else { YELLOW };
+            println!("{}{}{} ({}ms)", color, status, RESET, latency.as_millis());
+        }
+        (Some(_), None) => println!("{}reachable{} ({}ms)", GREEN, RESET, latency.as_millis()),
+        (None, _) => println!("{}unreachable{} {}({}){}", RED, RESET, DIM, health_url, RESET),
+    }
+
+    // A dedicated version endpoint wins over a `version` field in the health response
+    let version = match endpoint(&args.url, &paths.version_path) {
+        Ok(url) => match fetch_text(&client, url.as_str(), args.max_body_size).await {
+            Ok(text) => match serde_json::from_str::<Value>(&text) {
+                Ok(json) => json.get("version").map(scalar),
+                Err(_) => Some(text.trim().to_string()).filter(|t| !t.is_empty()),
+            },
+            Err(_) => None,
+        },
+        Err(_) => None,
+    }
+    .or_else(|| health.as_ref()?.get("version").map(scalar));
+    label("Version");
+    println!("{}", version.as_deref().unwrap_or("unknown"));
+
+    if let Some(uptime) = health.as_ref().and_then(|h| h.get("uptime")) {
+        label("Uptime");
+        match uptime.as_i64() {
+            Some(secs) => println!("{}", HumanDuration(chrono::Duration::seconds(secs))),
+            None => println!("{}", scalar(uptime)),
+        }
+    }
+
+    // Whatever else the health endpoint reports is the proxy's own config summary
+    if let Some(Value::Object(fields)) = health.as_ref() {
+        let summary: Vec<String> = fields
+            .iter()
+            .filter(|(key, value)| {
+                !matches!(key.as_str(), "status" | "version" | "uptime") && !value.is_object() && !value.is_array()
+            })
+            .map(|(key, value)| format!("{}={}", key, scalar(value)))
+            .collect();
+        if !summary.is_empty() {
+            label("Config");
+            println!("{}", summary.join(", "));
+        }
+    }
+
+    label("Accounts");
+    let accounts = match FetchPlan::new(args, config).fetch(&client, &args.url).await {
+        Ok(text) => parse_response(&text, config),
+        Err(e) => Err(e),
+    };
+    let now = Utc::now();
+    match accounts {
+        Ok(ref data) => {
+            let (available, rate_limited, invalid, disabled) = count_stats(&data.accounts);
+            println!(
+                "{} total, {}{} available{}, {}{} rate-limited{}, {}{} invalid{}, {}{} disabled{}",
+                data.accounts.len(),
+                GREEN, available, RESET,
+                YELLOW, rate_limited, RESET,
+                RED, invalid, RESET,
+                DIM, disabled, RESET
+            );
+        }
+        Err(ref e) => println!("{}{}{}", RED, e, RESET),
+    }
+
+    // Skew throws off every reset countdown, so it is worth a line of its own
+    if let Ok(ref data) = accounts {
+        label("Clock");
+        match clock_skew(data, now) {
+            Some(skew) if skew.num_seconds().unsigned_abs() > config.thresholds.clock_skew => {
+                println!("{}{}{}; reset countdowns may be off", YELLOW, describe_skew(skew), RESET)
+            }
+            Some(_) => println!("{}in sync{} (within {}s)", GREEN, RESET, config.thresholds.clock_skew),
+            None => println!("{}unknown{} {}(the response has no timestamp){}", DIM, RESET, DIM, RESET),
+        }
+    }
+
+    if health.is_none() {
+        bail!("Proxy health check failed");
+    }
+    Ok(())
+}
@@ -0,0 +1,41 @@
+use crate::{
+    config::PushoverConfig,
+    notify::{send_retrying, Level},
+};
+use anyhow::Result;
+use serde_json::json;
+
+/// Pushover's priorities: -1 quiet, 0 normal, 1 high (bypasses quiet
+/// hours), 2 emergency (repeats until acknowledged)
+fn priority(level: Level, config: &PushoverConfig) -> i8 {
+    match level {
+        Level::Good => -1,
+        Level::Warning => 0,
+        Level::Danger if config.emergency => 2,
+        Level::Danger => 1,
+    }
+}
+
+pub async fn send(
+    client: &reqwest::Client,
+    config: &PushoverConfig,
+    title: String,
+    body: String,
+    level: Level,
+) -> Result<()> {
+    let url = format!("{}/1/messages.json", config.api_url.trim_end_matches('/'));
+    let priority = priority(level, config);
+    let mut message = json!({
+        "token": config.token,
+        "user": config.user,
+        "title": title,
+        "message": body,
+        "priority": priority,
+    });
+    if priority == 2 {
+        // Pushover won't repeat more often than every 30 seconds
+        message["retry"] = json!(config.retry_secs.max(30));
+        message["expire"] = json!(config.expire_secs);
+    }
+    send_retrying(|| client.post(&url).json(&message)).await
+}
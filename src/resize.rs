@@ -0,0 +1,29 @@
+#[cfg(unix)]
+use tokio::signal::unix::{signal, Signal, SignalKind};
+
+/// Terminal resizes (`SIGWINCH`), so watch mode can redraw at the new size
+/// right away instead of leaving wrapped lines until the next tick. Off
+/// Unix, or if the handler can't be installed, it never fires.
+pub struct Resize {
+    #[cfg(unix)]
+    signal: Option<Signal>,
+}
+
+impl Resize {
+    pub fn new() -> Resize {
+        Resize {
+            #[cfg(unix)]
+            signal: signal(SignalKind::window_change()).ok(),
+        }
+    }
+
+    /// Resolves on the next resize
+    pub async fn changed(&mut self) {
+        #[cfg(unix)]
+        if let Some(ref mut signal) = self.signal {
+            signal.recv().await;
+            return;
+        }
+        std::future::pending::<()>().await
+    }
+}
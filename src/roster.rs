@@ -0,0 +1,162 @@
+use crate::{config, get_short_email, ApiResponse, HumanDuration, RESET, YELLOW};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt::{self, Write as _},
+    fs,
+    path::PathBuf,
+};
+
+/// Accounts unseen for this long are forgotten, which ends their warning
+const FORGET_DAYS: i64 = 7;
+
+/// When an account was first and last in a response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Seen {
+    first: DateTime<Utc>,
+    last: DateTime<Utc>,
+}
+
+/// Accounts that came or went against the remembered roster
+#[derive(Debug, Clone, Default)]
+pub struct RosterChanges {
+    /// In the response but in no earlier run's
+    pub new: Vec<String>,
+    /// Remembered but not in the response, with when each was last seen
+    pub missing: Vec<(String, DateTime<Utc>)>,
+}
+
+/// Every account email the proxy has reported, kept in `roster.json` across
+/// runs so that an account the proxy silently stops serving gets noticed.
+/// Rosters are kept per set of endpoint labels, since each proxy serves its own.
+pub struct Roster {
+    path: Option<PathBuf>,
+    /// Key for this run's endpoints in the file
+    key: String,
+    all: BTreeMap<String, BTreeMap<String, Seen>>,
+    /// Emails known before this run started; what's outside it is new
+    baseline: HashSet<String>,
+}
+
+fn roster_path() -> Option<PathBuf> {
+    config::data_dir().map(|dir| dir.join("roster.json"))
+}
+
+impl Roster {
+    /// Reads the roster; a missing or unreadable file starts an empty one
+    pub fn load(labels: &[String]) -> Roster {
+        let path = roster_path();
+        let all: BTreeMap<String, BTreeMap<String, Seen>> = path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default();
+        let key = labels.join(",");
+        let baseline = all.get(&key).map(|known| known.keys().cloned().collect()).unwrap_or_default();
+        Roster {
+            path,
+            key,
+            all,
+            baseline,
+        }
+    }
+
+    /// Fills `roster_changes` of `data` and saves the roster when it changed.
+    /// The very first run only records the accounts. A roster that can't be
+    /// saved is warned about once and then only kept in memory.
+    pub fn observe(&mut self, data: &mut ApiResponse, now: DateTime<Utc>) {
+        let current: HashSet<&str> = data.accounts.iter().map(|a| a.email.as_str()).collect();
+        let known = self.all.entry(self.key.clone()).or_default();
+        let before: Vec<String> = known.keys().cloned().collect();
+
+        let cutoff = now - chrono::Duration::days(FORGET_DAYS);
+        known.retain(|email, seen| current.contains(email.as_str()) || seen.last > cutoff);
+        for email in &current {
+            known
+                .entry(email.to_string())
+                .and_modify(|seen| seen.last = now)
+                .or_insert(Seen { first: now, last: now });
+        }
+
+        let mut changes = RosterChanges::default();
+        if !self.baseline.is_empty() {
+            changes.new = current
+                .iter()
+                .filter(|email| !self.baseline.contains(**email))
+                .map(|email| email.to_string())
+                .collect();
+            changes.new.sort();
+        }
+        changes.missing = known
+            .iter()
+            .filter(|(email, _)| !current.contains(email.as_str()))
+            .map(|(email, seen)| (email.clone(), seen.last))
+            .collect();
+        data.roster_changes = changes;
+
+        let changed = before.len() != known.len() || before.iter().any(|email| !known.contains_key(email));
+        // Last-seen times only matter once an account goes missing, so a
+        // steady roster is saved once an hour rather than every refresh
+        if changed || self.due(now) {
+            if let Err(e) = self.save() {
+                eprintln!("Warning: not remembering the account roster: {:#}", e);
+                self.path = None;
+            }
+        }
+    }
+
+    /// Whether the file is over an hour old, or was never written
+    fn due(&self, now: DateTime<Utc>) -> bool {
+        let saved = self
+            .path
+            .as_ref()
+            .and_then(|path| fs::metadata(path).ok())
+            .and_then(|meta| meta.modified().ok())
+            .map(DateTime::<Utc>::from);
+        saved.map_or(true, |at| now - at > chrono::Duration::hours(1))
+    }
+
+    fn save(&self) -> Result<()> {
+        let Some(ref path) = self.path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).with_context(|| format!("Failed to create data dir {}", dir.display()))?;
+        }
+        let text = serde_json::to_string(&self.all).context("Failed to encode roster")?;
+        fs::write(path, text).with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+/// Header lines such as `2 accounts missing, last seen 1d2h ago: alice, bob`
+pub fn render(out: &mut String, changes: &RosterChanges, now: DateTime<Utc>) -> fmt::Result {
+    if !changes.missing.is_empty() {
+        let since = changes.missing.iter().map(|(_, at)| *at).max().unwrap_or(now);
+        let mut names: Vec<&str> = changes.missing.iter().map(|(email, _)| get_short_email(email)).collect();
+        names.sort();
+        writeln!(
+            out,
+            "{}{} missing, last seen {} ago: {}{}",
+            YELLOW,
+            accounts(names.len()),
+            HumanDuration(now - since),
+            names.join(", "),
+            RESET
+        )?;
+    }
+    if !changes.new.is_empty() {
+        let names: Vec<&str> = changes.new.iter().map(|email| get_short_email(email)).collect();
+        writeln!(out, "{}{} new since the last run: {}{}", YELLOW, accounts(names.len()), names.join(", "), RESET)?;
+    }
+    Ok(())
+}
+
+fn accounts(n: usize) -> String {
+    match n {
+        1 => "1 account".to_string(),
+        n => format!("{} accounts", n),
+    }
+}
+
@@ -0,0 +1,445 @@
+use crate::{fetch_text, http_client, Args, BOLD, GREEN, RED, RESET, YELLOW};
+use anyhow::{Context, Result};
+use clap::{Args as ClapArgs, Subcommand};
+use serde_json::Value;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write as _,
+    path::PathBuf,
+};
+
+#[derive(ClapArgs, Debug)]
+pub struct SchemaArgs {
+    #[command(subcommand)]
+    pub command: Option<SchemaCommand>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SchemaCommand {
+    /// Compare a response's fields with the schema: added, removed, renamed and retyped ones
+    Diff {
+        /// Captured response to compare [default: fetch the live one]
+        #[arg(long, value_name = "FILE")]
+        against: Option<PathBuf>,
+    },
+}
+
+/// JSON Schema of the proxy response, printed by `schema` and enforced by `--validate`
+pub const SCHEMA: &str = include_str!("schema.json");
+
+/// Checks a response body against [`SCHEMA`], returning one message per
+/// violation with its JSON path. Wrapped responses are checked inside `result`.
+pub fn validate(text: &str) -> Result<Vec<String>> {
+    validate_with(text, false)
+}
+
+/// [`validate`] for `--strict`: fields the schema doesn't describe are
+/// violations too, and so are the optional fields it does when they're missing
+pub fn validate_strict(text: &str) -> Result<Vec<String>> {
+    validate_with(text, true)
+}
+
+fn validate_with(text: &str, strict: bool) -> Result<Vec<String>> {
+    let schema: Value = serde_json::from_str(SCHEMA).expect("embedded schema is valid JSON");
+    let value: Value = serde_json::from_str(text).context("Response is not JSON")?;
+
+    let mut errors = Vec::new();
+    match value.get("result").and_then(Value::as_str) {
+        Some(inner) => match serde_json::from_str::<Value>(inner) {
+            Ok(inner) => check(&schema, &schema, &inner, "result", strict, &mut errors),
+            Err(e) => errors.push(format!("result: not a JSON document ({})", e)),
+        },
+        None => check(&schema, &schema, &value, "", strict, &mut errors),
+    }
+    Ok(errors)
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_u64() || n.is_i64() => "integer",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn type_matches(expected: &str, value: &Value) -> bool {
+    type_accepts(expected, type_name(value))
+}
+
+fn type_accepts(expected: &str, actual: &str) -> bool {
+    actual == expected || (expected == "number" && actual == "integer")
+}
+
+/// `.name` for identifier-like keys, `["gemini-3-pro"]` otherwise
+fn child_path(path: &str, key: &str) -> String {
+    let mut out = path.to_string();
+    if !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        if !out.is_empty() {
+            out.push('.');
+        }
+        out.push_str(key);
+    } else {
+        let _ = write!(out, "[{:?}]", key);
+    }
+    out
+}
+
+fn display_path(path: &str) -> &str {
+    if path.is_empty() {
+        "(root)"
+    } else {
+        path
+    }
+}
+
+/// Interprets the subset of JSON Schema that [`SCHEMA`] uses: `$ref` into
+/// `$defs`, `type`, `required`, `properties`, `additionalProperties`, `items`,
+/// `minimum` and `maximum`. When `strict`, every listed property is required
+/// and an object without `additionalProperties` takes no others.
+fn check(root: &Value, schema: &Value, value: &Value, path: &str, strict: bool, errors: &mut Vec<String>) {
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        let name = reference.trim_start_matches("#/$defs/");
+        if let Some(def) = root.get("$defs").and_then(|defs| defs.get(name)) {
+            check(root, def, value, path, strict, errors);
+        }
+        return;
+    }
+
+    if let Some(expected) = schema.get("type") {
+        let allowed: Vec<&str> = match expected {
+            Value::String(t) => vec![t.as_str()],
+            Value::Array(types) => types.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !allowed.iter().any(|t| type_matches(t, value)) {
+            errors.push(format!(
+                "{}: expected {}, got {} {}",
+                display_path(path),
+                allowed.join(" or "),
+                type_name(value),
+                value
+            ));
+            return;
+        }
+    }
+
+    if let Some(n) = value.as_f64() {
+        if let Some(min) = schema.get("minimum").and_then(Value::as_f64) {
+            if n < min {
+                errors.push(format!("{}: {} is below the minimum {}", display_path(path), n, min));
+            }
+        }
+        if let Some(max) = schema.get("maximum").and_then(Value::as_f64) {
+            if n > max {
+                errors.push(format!("{}: {} is above the maximum {}", display_path(path), n, max));
+            }
+        }
+    }
+
+    if let Value::Object(map) = value {
+        for key in schema.get("required").and_then(Value::as_array).into_iter().flatten() {
+            if let Some(key) = key.as_str() {
+                if !map.contains_key(key) {
+                    errors.push(format!("{}: missing required field {:?}", display_path(path), key));
+                }
+            }
+        }
+        let properties = schema.get("properties").and_then(Value::as_object);
+        let additional = schema.get("additionalProperties");
+        if strict {
+            for key in properties.into_iter().flat_map(|p| p.keys()) {
+                if !map.contains_key(key) {
+                    errors.push(format!("{}: missing optional field {:?}", display_path(path), key));
+                }
+            }
+        }
+        for (key, child) in map {
+            match properties.and_then(|p| p.get(key)) {
+                Some(property) => check(root, property, child, &child_path(path, key), strict, errors),
+                None => match additional {
+                    Some(additional) => {
+                        let child_path = format!("{}[{:?}]", path, key);
+                        check(root, additional, child, &child_path, strict, errors);
+                    }
+                    None if strict && properties.is_some() => {
+                        errors.push(format!("{}: unknown field {:?}", display_path(path), key));
+                    }
+                    None => {}
+                },
+            }
+        }
+    }
+
+    if let (Value::Array(items), Some(item_schema)) = (value, schema.get("items")) {
+        for (i, item) in items.iter().enumerate() {
+            check(root, item_schema, item, &format!("{}[{}]", path, i), strict, errors);
+        }
+    }
+}
+
+/// Where a field sits: names, `[]` for every array item and `[*]` for every
+/// key of a map, so that all accounts and models fold into one path
+type FieldPath = Vec<String>;
+
+const ITEM: &str = "[]";
+const ENTRY: &str = "[*]";
+
+/// A field the schema describes
+struct Expected {
+    types: Vec<String>,
+    required: bool,
+}
+
+fn resolve<'a>(root: &'a Value, schema: &'a Value) -> &'a Value {
+    match schema.get("$ref").and_then(Value::as_str) {
+        Some(reference) => {
+            let name = reference.trim_start_matches("#/$defs/");
+            root.get("$defs").and_then(|defs| defs.get(name)).unwrap_or(schema)
+        }
+        None => schema,
+    }
+}
+
+/// Every path [`SCHEMA`] describes below `path`
+fn expected_fields(root: &Value, schema: &Value, path: &mut FieldPath, out: &mut BTreeMap<FieldPath, Expected>) {
+    let schema = resolve(root, schema);
+    let required: Vec<&str> = schema
+        .get("required")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .collect();
+    let mut visit = |segment: &str, child: &Value, required: bool, path: &mut FieldPath| {
+        path.push(segment.to_string());
+        let types = match resolve(root, child).get("type") {
+            Some(Value::String(t)) => vec![t.clone()],
+            Some(Value::Array(types)) => types.iter().filter_map(Value::as_str).map(String::from).collect(),
+            _ => Vec::new(),
+        };
+        out.insert(path.clone(), Expected { types, required });
+        expected_fields(root, child, path, out);
+        path.pop();
+    };
+    for (key, child) in schema.get("properties").and_then(Value::as_object).into_iter().flatten() {
+        visit(key, child, required.contains(&key.as_str()), path);
+    }
+    if let Some(child) = schema.get("additionalProperties") {
+        visit(ENTRY, child, false, path);
+    }
+    if let Some(child) = schema.get("items") {
+        visit(ITEM, child, false, path);
+    }
+}
+
+/// Every path in `value` with the JSON types seen at it. `schema` tells maps
+/// keyed by model name from objects with fields; below a field it doesn't
+/// describe, objects are taken as having fields.
+fn observed_fields(
+    root: &Value,
+    schema: Option<&Value>,
+    value: &Value,
+    path: &mut FieldPath,
+    out: &mut BTreeMap<FieldPath, BTreeSet<&'static str>>,
+) {
+    out.entry(path.clone()).or_default().insert(type_name(value));
+    let schema = schema.map(|schema| resolve(root, schema));
+    match value {
+        Value::Object(map) => {
+            let properties = schema.and_then(|s| s.get("properties")).and_then(Value::as_object);
+            let additional = schema.and_then(|s| s.get("additionalProperties"));
+            for (key, child) in map {
+                match (properties, additional) {
+                    (None, Some(additional)) => {
+                        path.push(ENTRY.to_string());
+                        observed_fields(root, Some(additional), child, path, out);
+                    }
+                    _ => {
+                        path.push(key.clone());
+                        observed_fields(root, properties.and_then(|p| p.get(key)), child, path, out);
+                    }
+                }
+                path.pop();
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                path.push(ITEM.to_string());
+                observed_fields(root, schema.and_then(|s| s.get("items")), item, path, out);
+                path.pop();
+            }
+        }
+        _ => {}
+    }
+}
+
+/// `accounts[].limits[*].remainingFraction`
+fn render_path(path: &[String]) -> String {
+    path.iter().fold(String::new(), |out, segment| match segment.as_str() {
+        ITEM | ENTRY => out + segment.as_str(),
+        _ => child_path(&out, segment),
+    })
+}
+
+/// Lowercase without `_` and `-`, so `reset_time` and `resetTime` compare equal
+fn normalize(name: &str) -> String {
+    name.chars().filter(|c| *c != '_' && *c != '-').flat_map(char::to_lowercase).collect()
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let next = (diagonal + usize::from(ca != *cb)).min(row[j] + 1).min(row[j + 1] + 1);
+            diagonal = row[j + 1];
+            row[j + 1] = next;
+        }
+    }
+    row[b.len()]
+}
+
+/// Whether `to` reads as `from` under another name
+fn looks_renamed(from: &str, to: &str) -> bool {
+    let (from, to) = (normalize(from), normalize(to));
+    from == to || from.contains(&to) || to.contains(&from) || edit_distance(&from, &to) <= from.len().max(to.len()) / 3
+}
+
+/// One way the response differs from the schema
+enum Drift {
+    Added(FieldPath, String),
+    Removed(FieldPath, bool),
+    Renamed(FieldPath, FieldPath),
+    Retyped(FieldPath, String, String),
+}
+
+impl Drift {
+    /// The field as the response has it
+    fn path(&self) -> &FieldPath {
+        match self {
+            Drift::Added(path, _) | Drift::Removed(path, _) | Drift::Retyped(path, _, _) | Drift::Renamed(_, path) => path,
+        }
+    }
+}
+
+fn drift(text: &str) -> Result<Vec<Drift>> {
+    let root: Value = serde_json::from_str(SCHEMA).expect("embedded schema is valid JSON");
+    let mut value: Value = serde_json::from_str(text).context("Response is not JSON")?;
+    if let Some(inner) = value.get("result").and_then(Value::as_str) {
+        value = serde_json::from_str(inner).context("The response's result is not a JSON document")?;
+    }
+
+    let mut expected = BTreeMap::new();
+    expected_fields(&root, &root, &mut Vec::new(), &mut expected);
+    let mut observed = BTreeMap::new();
+    observed_fields(&root, Some(&root), &value, &mut Vec::new(), &mut observed);
+
+    let types = |seen: &BTreeSet<&str>| seen.iter().copied().collect::<Vec<_>>().join(" or ");
+    // Only the outermost unknown field counts; what's inside it is new anyway
+    let mut added: Vec<&FieldPath> = observed
+        .keys()
+        .filter(|path| !path.is_empty() && !expected.contains_key(*path))
+        .filter(|path| path.len() == 1 || expected.contains_key(&path[..path.len() - 1]))
+        .collect();
+    // A field is only gone when what holds it was seen, e.g. not for quotas
+    // of a response without any
+    let removed: Vec<&FieldPath> = expected
+        .keys()
+        .filter(|path| !observed.contains_key(*path))
+        .filter(|path| path.len() == 1 || observed.contains_key(&path[..path.len() - 1]))
+        .filter(|path| path.last().is_some_and(|last| last != ITEM && last != ENTRY))
+        .collect();
+
+    let mut drifts = Vec::new();
+    for path in removed {
+        let field = &expected[path];
+        let renamed = added.iter().position(|to| {
+            to.len() == path.len()
+                && to[..to.len() - 1] == path[..path.len() - 1]
+                && observed[*to].iter().all(|t| field.types.iter().any(|e| type_accepts(e, t)))
+                && looks_renamed(&path[path.len() - 1], &to[to.len() - 1])
+        });
+        match renamed {
+            Some(i) => drifts.push(Drift::Renamed(path.clone(), added.remove(i).clone())),
+            None => drifts.push(Drift::Removed(path.clone(), field.required)),
+        }
+    }
+    for path in added {
+        drifts.push(Drift::Added(path.clone(), types(&observed[path])));
+    }
+    for (path, field) in &expected {
+        let Some(seen) = observed.get(path) else {
+            continue;
+        };
+        if !seen.iter().all(|t| field.types.is_empty() || field.types.iter().any(|e| type_accepts(e, t))) {
+            drifts.push(Drift::Retyped(path.clone(), field.types.join(" or "), types(seen)));
+        }
+    }
+    drifts.sort_by(|a, b| a.path().cmp(b.path()));
+    Ok(drifts)
+}
+
+/// `schema` subcommand: the schema itself, or with `diff` how a response's
+/// fields drift from it
+pub async fn run(args: &Args, schema_args: &SchemaArgs) -> Result<()> {
+    let Some(SchemaCommand::Diff { ref against }) = schema_args.command else {
+        print!("{}", SCHEMA);
+        return Ok(());
+    };
+    let (text, source) = match against {
+        Some(path) => (
+            std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?,
+            path.display().to_string(),
+        ),
+        None => {
+            let client = http_client(args)?;
+            (fetch_text(&client, &args.url, args.max_body_size).await?, args.url.clone())
+        }
+    };
+
+    let drifts = drift(&text)?;
+    if drifts.is_empty() {
+        println!("{}{}OK{} {} has the fields the schema describes", BOLD, GREEN, RESET, source);
+        return Ok(());
+    }
+    let width = drifts.iter().map(|drift| render_path(drift.path()).len()).max().unwrap_or(0);
+    for drift in &drifts {
+        match drift {
+            Drift::Added(path, types) => println!("{}+ {:<width$}{}  new, {}", GREEN, render_path(path), RESET, types),
+            Drift::Removed(path, required) => println!(
+                "{}- {:<width$}{}  missing{}",
+                RED,
+                render_path(path),
+                RESET,
+                if *required { " (required)" } else { "" }
+            ),
+            Drift::Renamed(from, to) => println!(
+                "{}~ {:<width$}{}  renamed from {}",
+                YELLOW,
+                render_path(to),
+                RESET,
+                from.last().map(String::as_str).unwrap_or_default()
+            ),
+            Drift::Retyped(path, expected, seen) => println!(
+                "{}~ {:<width$}{}  {} instead of {}",
+                YELLOW,
+                render_path(path),
+                RESET,
+                seen,
+                expected
+            ),
+        }
+    }
+    anyhow::bail!(
+        "{} drifts from the schema ({} field{})",
+        source,
+        drifts.len(),
+        if drifts.len() == 1 { "" } else { "s" }
+    )
+}
@@ -0,0 +1,131 @@
+use crate::{state::Snapshot, ApiResponse};
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use rhai::{Array, CallFnOptions, Dynamic, Engine, Scope, AST};
+use std::{cmp::Ordering, fs, path::Path};
+
+/// Runaway loops in a hook end with an error instead of freezing the view
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+/// A user-supplied `--script` file. Every hook is optional; each gets the
+/// refresh's [`Snapshot`] (or one of its accounts) as a Rhai map:
+///
+/// - `alerts(snapshot)` returns strings shown in the header
+/// - `rows(snapshot)` returns arrays of cells shown as an extra table, the
+///   first one as its header
+/// - `sort_key(account)` returns a number or string to order accounts by
+pub struct Script {
+    engine: Engine,
+    ast: AST,
+    alerts: bool,
+    rows: bool,
+    sort_key: bool,
+}
+
+impl Script {
+    /// Reads and compiles the script so syntax errors surface before the first fetch
+    pub fn load(path: &Path) -> Result<Script> {
+        let source = fs::read_to_string(path).with_context(|| format!("Failed to read script {}", path.display()))?;
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        let ast = engine
+            .compile(source)
+            .map_err(|e| anyhow!("Invalid script {}: {}", path.display(), e))?;
+        let defines = |name: &str| ast.iter_functions().any(|f| f.name == name && f.params.len() == 1);
+        let (alerts, rows, sort_key) = (defines("alerts"), defines("rows"), defines("sort_key"));
+        if !(alerts || rows || sort_key) {
+            anyhow::bail!(
+                "Script {} defines none of alerts(snapshot), rows(snapshot), sort_key(account)",
+                path.display()
+            );
+        }
+        Ok(Script {
+            engine,
+            ast,
+            alerts,
+            rows,
+            sort_key,
+        })
+    }
+
+    fn call(&self, name: &str, arg: Dynamic) -> Result<Dynamic> {
+        // Hooks only see their argument, so top-level statements needn't rerun each call
+        let options = CallFnOptions::new().eval_ast(false);
+        self.engine
+            .call_fn_with_options(options, &mut Scope::new(), &self.ast, name, (arg,))
+            .map_err(|e| anyhow!("Script {}() failed: {}", name, e))
+    }
+
+    /// Runs the hooks on a freshly parsed response: fills `script_alerts` and
+    /// `script_rows`, and reorders the accounts when `sort_key` is defined
+    pub fn observe(&self, data: &mut ApiResponse, now: DateTime<Utc>) -> Result<()> {
+        let snapshot = rhai::serde::to_dynamic(Snapshot::new(data, now))
+            .map_err(|e| anyhow!("Failed to hand the snapshot to the script: {}", e))?;
+
+        if self.alerts {
+            data.script_alerts = strings(self.call("alerts", snapshot.clone())?, "alerts")?;
+        }
+        if self.rows {
+            data.script_rows = self
+                .call("rows", snapshot.clone())?
+                .try_cast::<Array>()
+                .ok_or_else(|| anyhow!("Script rows() must return an array of arrays"))?
+                .into_iter()
+                .map(|row| strings(row, "rows"))
+                .collect::<Result<_>>()?;
+        }
+        if self.sort_key {
+            let accounts: Array = snapshot.cast::<rhai::Map>().remove("accounts").map(Dynamic::cast).unwrap_or_default();
+            let mut keys = Vec::with_capacity(accounts.len());
+            for account in accounts {
+                keys.push(SortValue::from(self.call("sort_key", account)?));
+            }
+            let mut keyed: Vec<_> = keys.into_iter().zip(data.accounts.drain(..)).collect();
+            keyed.sort_by(|a, b| a.0.cmp(&b.0));
+            data.accounts = keyed.into_iter().map(|(_, account)| account).collect();
+        }
+        Ok(())
+    }
+}
+
+/// The array `hook` returned, with every element as display text
+fn strings(value: Dynamic, hook: &str) -> Result<Vec<String>> {
+    let items = value
+        .try_cast::<Array>()
+        .ok_or_else(|| anyhow!("Script {}() must return an array", hook))?;
+    Ok(items.into_iter().map(|item| item.to_string()).collect())
+}
+
+/// A `sort_key` result: numbers order before strings, anything else last
+#[derive(Debug)]
+enum SortValue {
+    Number(f64),
+    Text(String),
+    Other,
+}
+
+impl From<Dynamic> for SortValue {
+    fn from(value: Dynamic) -> Self {
+        if let Ok(n) = value.as_int() {
+            SortValue::Number(n as f64)
+        } else if let Ok(n) = value.as_float() {
+            SortValue::Number(n)
+        } else if value.is_string() {
+            SortValue::Text(value.to_string())
+        } else {
+            SortValue::Other
+        }
+    }
+}
+
+impl SortValue {
+    fn cmp(&self, other: &SortValue) -> Ordering {
+        match (self, other) {
+            (SortValue::Number(a), SortValue::Number(b)) => a.total_cmp(b),
+            (SortValue::Text(a), SortValue::Text(b)) => a.cmp(b),
+            (SortValue::Number(_), _) | (SortValue::Text(_), SortValue::Other) => Ordering::Less,
+            (SortValue::Other, SortValue::Other) => Ordering::Equal,
+            _ => Ordering::Greater,
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use crate::{apply_tag_filter, capacity::CapacityEstimator, endpoints::fetch_data, error, health::FetchHealth, heartbeat::Heartbeat, http_client, jittered, sort_accounts, state::Snapshot, Args, RenderOptions};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use clap::Args as ClapArgs;
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+#[derive(ClapArgs, Debug)]
+pub struct ServeArgs {
+    /// Address to listen on
+    #[arg(short, long, default_value = "127.0.0.1:9898")]
+    pub listen: SocketAddr,
+}
+
+/// Polling as fast as `-i 0` would just hammer the proxy
+const MIN_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Single-page dashboard served at `/`; it polls `/api/accounts` itself
+const DASHBOARD: &str = include_str!("dashboard.html");
+
+/// What the poller last saw, shared with the HTTP handlers
+#[derive(Default)]
+struct Shared {
+    /// Serialized snapshot from the last successful fetch
+    accounts_json: Option<String>,
+    last_success: Option<DateTime<Utc>>,
+    last_error: Option<String>,
+    health: FetchHealth,
+}
+
+/// `serve` subcommand: polls the proxy every `--interval` and serves the
+/// computed state over HTTP (`/` dashboard, `/api/accounts`, `/healthz`)
+pub async fn run(args: &Args, opts: &RenderOptions, serve_args: &ServeArgs) -> Result<()> {
+    let listener = TcpListener::bind(serve_args.listen)
+        .await
+        .with_context(|| format!("Failed to listen on {}", serve_args.listen))?;
+    eprintln!("Serving on http://{} (/, /api/accounts, /healthz)", serve_args.listen);
+
+    let shared = Arc::new(Mutex::new(Shared::default()));
+    let interval = args.interval.max(MIN_INTERVAL);
+    // The page refreshes as often as the data can change
+    let dashboard: Arc<str> = DASHBOARD
+        .replace("__REFRESH_MS__", &interval.as_millis().to_string())
+        .into();
+
+    let server = {
+        let shared = Arc::clone(&shared);
+        async move {
+            loop {
+                let (stream, _) = match listener.accept().await {
+                    Ok(conn) => conn,
+                    Err(_) => continue,
+                };
+                let shared = Arc::clone(&shared);
+                let dashboard = Arc::clone(&dashboard);
+                tokio::spawn(async move {
+                    // A client hanging up mid-response is not our problem
+                    let _ = handle(stream, &shared, &dashboard, interval).await;
+                });
+            }
+        }
+    };
+
+    tokio::select! {
+        _ = server => Ok(()),
+        result = poll(args, opts, &shared, interval) => result,
+    }
+}
+
+async fn poll(args: &Args, opts: &RenderOptions, shared: &Mutex<Shared>, interval: Duration) -> Result<()> {
+    let client = http_client(args)?;
+    let mut estimator = CapacityEstimator::new();
+    let mut health = FetchHealth::new();
+    let mut heartbeat = Heartbeat::new(args)?;
+    loop {
+        let now = Utc::now();
+        let result = fetch_data(&client, args, &opts.config, |text, latency| {
+            health.record_response(latency, text.len());
+            Ok(())
+        })
+        .await;
+        let update = match result {
+            Ok(mut data) => {
+                health.record_success(data.wrapped);
+                if let Some(ref mut heartbeat) = heartbeat {
+                    heartbeat.beat();
+                }
+                apply_tag_filter(&mut data, opts);
+                estimator.observe(&mut data);
+                sort_accounts(&mut data, opts, now);
+                serde_json::to_string(&Snapshot::new(&data, now))
+                    .context("Failed to encode state")
+                    .map_err(|e| e.to_string())
+            }
+            Err(e) => {
+                health.record_failure();
+                Err(error::one_line(&e))
+            }
+        };
+
+        {
+            let mut shared = shared.lock().unwrap();
+            shared.health = health.clone();
+            match update {
+                Ok(json) => {
+                    shared.accounts_json = Some(json);
+                    shared.last_success = Some(now);
+                    shared.last_error = None;
+                }
+                Err(e) => shared.last_error = Some(e),
+            }
+        }
+
+        tokio::time::sleep(jittered(interval, args.jitter)).await;
+    }
+}
+
+async fn handle(
+    mut stream: TcpStream,
+    shared: &Mutex<Shared>,
+    dashboard: &str,
+    interval: Duration,
+) -> Result<()> {
+    // Only the request line matters; headers and bodies are ignored
+    let mut buf = [0u8; 4096];
+    let n = stream.read(&mut buf).await?;
+    let request = String::from_utf8_lossy(&buf[..n]);
+    let mut parts = request.split_whitespace();
+    let method = parts.next().unwrap_or("");
+    let path = parts.next().unwrap_or("").split('?').next().unwrap_or("");
+
+    let (status, content_type, body) = if method != "GET" {
+        ("405 Method Not Allowed", "text/plain", "only GET is supported\n".to_string())
+    } else if path == "/" {
+        ("200 OK", "text/html; charset=utf-8", dashboard.to_string())
+    } else {
+        route(path, &shared.lock().unwrap(), interval)
+    };
+
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nAccess-Control-Allow-Origin: *\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+fn route(path: &str, shared: &Shared, interval: Duration) -> (&'static str, &'static str, String) {
+    match path {
+        "/api/accounts" => match shared.accounts_json {
+            Some(ref json) => ("200 OK", "application/json", json.clone()),
+            None => (
+                "503 Service Unavailable",
+                "application/json",
+                serde_json::json!({ "error": shared.last_error.as_deref().unwrap_or("no data yet") })
+                    .to_string(),
+            ),
+        },
+        "/healthz" => {
+            // Healthy while the last success is within three polling intervals
+            let stale_after = chrono::Duration::from_std(interval * 3).unwrap_or_default();
+            let healthy = shared
+                .last_success
+                .map(|at| Utc::now() - at <= stale_after)
+                .unwrap_or(false);
+            let body = serde_json::json!({
+                "status": if healthy { "ok" } else { "unhealthy" },
+                "last_success": shared.last_success,
+                "last_error": shared.last_error,
+                "fetch": shared.health,
+            })
+            .to_string();
+            if healthy {
+                ("200 OK", "application/json", body)
+            } else {
+                ("503 Service Unavailable", "application/json", body)
+            }
+        }
+        _ => ("404 Not Found", "text/plain", "not found\n".to_string()),
+    }
+}
@@ -0,0 +1,103 @@
+use crate::{intern::Name, get_account_status, locale::Percent, ApiResponse, HumanDuration, BOLD, RESET};
+use std::{collections::HashMap, fmt, time::Instant};
+
+/// Running totals for one watch session, printed when the watcher exits
+pub struct SessionStats {
+    started: Instant,
+    refreshes: usize,
+    errors: usize,
+    resets: usize,
+    status_changes: usize,
+    /// Last seen remaining fraction per (email, model)
+    fractions: HashMap<(Name, Name), f64>,
+    /// Last seen status per email
+    statuses: HashMap<Name, &'static str>,
+}
+
+impl SessionStats {
+    pub fn new() -> Self {
+        SessionStats {
+            started: Instant::now(),
+            refreshes: 0,
+            errors: 0,
+            resets: 0,
+            status_changes: 0,
+            fractions: HashMap::new(),
+            statuses: HashMap::new(),
+        }
+    }
+
+    pub fn record_error(&mut self) {
+        self.refreshes += 1;
+        self.errors += 1;
+    }
+
+    /// Counts a successful refresh, noting quotas that went back up (a reset)
+    /// and accounts whose status changed since the previous refresh
+    pub fn observe(&mut self, data: &ApiResponse) {
+        self.refreshes += 1;
+
+        for account in &data.accounts {
+            let (status, _) = get_account_status(account);
+            if let Some(previous) = self.statuses.insert(account.email.clone(), status) {
+                if previous != status {
+                    self.status_changes += 1;
+                }
+            }
+
+            let Some(ref limits) = account.limits else {
+                continue;
+            };
+            for (model, quota) in limits {
+                let key = (account.email.clone(), model.clone());
+                if let Some(previous) = self.fractions.insert(key, quota.remaining_fraction) {
+                    if quota.remaining_fraction > previous {
+                        self.resets += 1;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// One-line footer form of the stats, for a pane that stays open:
+/// `Session: 2h14m, 1608 refreshes, 1% failed, 3 status changes, 5 resets`
+pub struct Footer<'a>(pub &'a SessionStats);
+
+impl fmt::Display for Footer<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let stats = self.0;
+        let watched = chrono::Duration::from_std(stats.started.elapsed()).unwrap_or_default();
+        let failed = if stats.refreshes == 0 {
+            0.0
+        } else {
+            stats.errors as f64 / stats.refreshes as f64
+        };
+        write!(
+            f,
+            "Session: {}, {} refreshes, {} failed, {} status changes, {} resets",
+            HumanDuration(watched),
+            stats.refreshes,
+            Percent(failed),
+            stats.status_changes,
+            stats.resets
+        )
+    }
+}
+
+impl fmt::Display for SessionStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let watched = chrono::Duration::from_std(self.started.elapsed()).unwrap_or_default();
+        write!(
+            f,
+            "{}Session:{} watched {}, {} refreshes, {} errors, {} resets observed, {} status changes",
+            BOLD,
+            RESET,
+            HumanDuration(watched),
+            self.refreshes,
+            self.errors,
+            self.resets,
+            self.status_changes
+        )
+    }
+}
@@ -0,0 +1,171 @@
+//! Slack Block Kit messages for the notifier: a section for what happened,
+//! then one attachment per event or model, colored by how bad it is
+
+use crate::{
+    config::{Config, StatusLabels},
+    count_stats, events::Event, get_short_email, locale::Percent, model_available, notify::Level,
+    parse_reset, ApiResponse, HumanDuration,
+};
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+
+/// Slack shows at most this many fields in a section
+const MAX_FIELDS: usize = 10;
+
+fn color(level: Level) -> &'static str {
+    match level {
+        Level::Good => "good",
+        Level::Warning => "warning",
+        Level::Danger => "danger",
+    }
+}
+
+/// `text` with the characters mrkdwn treats as markup escaped
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+fn mrkdwn(text: String) -> Value {
+    json!({ "type": "mrkdwn", "text": text })
+}
+
+fn section(text: String) -> Value {
+    json!({ "type": "section", "text": mrkdwn(text) })
+}
+
+/// `*Name*` over `value`, as one cell of a section's two-column fields
+fn field(name: &str, value: String) -> Value {
+    mrkdwn(format!("*{}*\n{}", name, value))
+}
+
+/// Sections holding `fields`, split where Slack's per-section limit falls
+fn field_sections(fields: Vec<Value>) -> Vec<Value> {
+    fields
+        .chunks(MAX_FIELDS)
+        .map(|chunk| json!({ "type": "section", "fields": chunk }))
+        .collect()
+}
+
+/// `in 2h 5m (<!date^…>)`: Slack shows the time in each reader's own zone
+fn reset_text(at: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let when = format!("<!date^{}^{{date_short_pretty}} {{time}}|{}>", at.timestamp(), at.format("%Y-%m-%d %H:%M UTC"));
+    if at > now {
+        format!("in {} ({})", HumanDuration(at - now), when)
+    } else {
+        format!("{} ago ({})", HumanDuration(now - at), when)
+    }
+}
+
+fn attachment(level: Level, blocks: Vec<Value>) -> Value {
+    json!({ "color": color(level), "blocks": blocks })
+}
+
+/// One attachment per event: the change as a line, then the account, model
+/// and where its quota stands now as fields
+pub fn events_message(events: &[Event], data: &ApiResponse, labels: &StatusLabels, now: DateTime<Utc>) -> Value {
+    let (available, _, _, _) = count_stats(&data.accounts);
+    let changes = match events.len() {
+        1 => "1 change".to_string(),
+        n => format!("{} changes", n),
+    };
+    let headline = format!("AG quota: {}, {}/{} accounts available", changes, available, data.accounts.len());
+
+    let attachments: Vec<Value> = events
+        .iter()
+        .map(|event| {
+            let name = escape(get_short_email(&event.account));
+            let (from, to) = (escape(labels.label(&event.from)), escape(labels.label(&event.to)));
+            let what = match event.model {
+                Some(ref model) => format!("*{}* {}: {} → *{}*", name, escape(model), from, to),
+                None => format!("*{}*: {} → *{}*", name, from, to),
+            };
+            let mut fields = vec![field("Account", escape(&event.account))];
+            if let Some(ref model) = event.model {
+                fields.push(field("Model", escape(model)));
+                let quota = data
+                    .accounts
+                    .iter()
+                    .find(|a| a.email == event.account)
+                    .and_then(|a| a.limits.as_ref()?.get(model.as_str()));
+                if let Some(quota) = quota {
+                    fields.push(field("Remaining", Percent(quota.remaining_fraction).to_string()));
+                    if let Some(at) = parse_reset(quota) {
+                        fields.push(field("Resets", reset_text(at, now)));
+                    }
+                }
+            }
+            let mut blocks = vec![section(what)];
+            blocks.extend(field_sections(fields));
+            attachment(Level::of(&event.to), blocks)
+        })
+        .collect();
+
+    json!({
+        "text": headline,
+        "blocks": [section(format!("*{}*", headline))],
+        "attachments": attachments,
+    })
+}
+
+/// The fleet's account counts, then an attachment per model with how many
+/// accounts can serve it, the one with the most left and the next reset
+pub fn summary_message(data: &ApiResponse, config: &Config, now: DateTime<Utc>) -> Value {
+    let (available, rate_limited, invalid, disabled) = count_stats(&data.accounts);
+    let headline = format!("AG quota daily summary: {}/{} accounts available", available, data.accounts.len());
+    let mut counts = vec![field("Available", format!("{} of {}", available, data.accounts.len()))];
+    for (name, n) in [("Rate-limited", rate_limited), ("Invalid", invalid), ("Disabled", disabled)] {
+        if n > 0 {
+            counts.push(field(name, n.to_string()));
+        }
+    }
+    let mut blocks = vec![json!({
+        "type": "header",
+        "text": { "type": "plain_text", "text": "AG quota daily summary" },
+    })];
+    blocks.extend(field_sections(counts));
+
+    let thresholds = &config.thresholds;
+    let attachments: Vec<Value> = data
+        .models
+        .iter()
+        .map(|model| {
+            let serving = data.accounts.iter().filter(|a| model_available(a, model)).count();
+            let level = if serving <= thresholds.available_critical {
+                Level::Danger
+            } else if serving <= thresholds.available_warning {
+                Level::Warning
+            } else {
+                Level::Good
+            };
+            let quotas = data
+                .accounts
+                .iter()
+                .filter_map(|a| a.limits.as_ref()?.get(model).map(|q| (a, q)));
+            let mut fields = vec![field("Available", format!("{} of {}", serving, data.accounts.len()))];
+            let most = quotas.clone().max_by(|x, y| x.1.remaining_fraction.total_cmp(&y.1.remaining_fraction));
+            if let Some((account, quota)) = most {
+                fields.push(field(
+                    "Most left",
+                    format!("{} ({})", Percent(quota.remaining_fraction), escape(get_short_email(&account.email))),
+                ));
+            }
+            let next = quotas
+                .filter(|(_, q)| q.remaining_fraction < 1.0)
+                .filter_map(|(_, q)| parse_reset(q))
+                .filter(|at| *at > now)
+                .min();
+            if let Some(at) = next {
+                fields.push(field("Next reset", reset_text(at, now)));
+            }
+            let mut blocks = vec![section(format!("*{}*", escape(model)))];
+            blocks.extend(field_sections(fields));
+            attachment(level, blocks)
+        })
+        .collect();
+
+    json!({
+        "text": headline,
+        "blocks": blocks,
+        "attachments": attachments,
+    })
+}
@@ -0,0 +1,174 @@
+use crate::{
+    intern::Name,
+    count_stats, fleet_next_reset, get_account_status, model_available, parse_reset, worst_account, ApiResponse,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Machine-readable view of one refresh: the same numbers the tables show
+#[derive(Debug, Serialize)]
+pub struct Snapshot<'a> {
+    pub timestamp: DateTime<Utc>,
+    pub summary: Summary,
+    pub next_reset: Option<NextReset<'a>>,
+    pub models: &'a [Name],
+    pub accounts: Vec<AccountState<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Summary {
+    pub total: usize,
+    pub available: usize,
+    pub rate_limited: usize,
+    pub invalid: usize,
+    pub disabled: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct NextReset<'a> {
+    pub account: &'a str,
+    pub model: &'a str,
+    pub at: DateTime<Utc>,
+    pub in_seconds: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AccountState<'a> {
+    pub email: &'a str,
+    pub status: &'static str,
+    pub last_used: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub credentials_expire_at: Option<DateTime<Utc>>,
+    /// Time with any model rate-limited over the last 24 hours, when history is recorded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limited_seconds_24h: Option<i64>,
+    /// Endpoint label, when several were merged
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub origin: Option<&'a str>,
+    pub models: BTreeMap<&'a str, ModelState<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ModelState<'a> {
+    pub remaining_fraction: f64,
+    pub remaining: Option<u64>,
+    pub limit: Option<u64>,
+    /// Inferred requests left when the proxy reports no `remaining` count
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub estimated_remaining: Option<u64>,
+    pub reset_time: Option<DateTime<Utc>>,
+    pub rate_limited: bool,
+    pub available: bool,
+    pub reason: Option<&'a str>,
+}
+
+/// One-line digest printed by `--summary-json-stderr`
+#[derive(Debug, Serialize)]
+pub struct Digest<'a> {
+    pub timestamp: DateTime<Utc>,
+    pub summary: Summary,
+    pub worst: Option<Worst<'a>>,
+    pub next_reset: Option<NextReset<'a>>,
+}
+
+/// The usable account closest to running out, on its emptiest model
+#[derive(Debug, Serialize)]
+pub struct Worst<'a> {
+    pub account: &'a str,
+    pub model: &'a str,
+    pub remaining_fraction: f64,
+}
+
+impl Summary {
+    pub fn new(data: &ApiResponse) -> Self {
+        let (available, rate_limited, invalid, disabled) = count_stats(&data.accounts);
+        Summary {
+            total: data.accounts.len(),
+            available,
+            rate_limited,
+            invalid,
+            disabled,
+        }
+    }
+}
+
+impl<'a> NextReset<'a> {
+    fn new(data: &'a ApiResponse, now: DateTime<Utc>) -> Option<Self> {
+        fleet_next_reset(data, now).map(|(account, model, at)| NextReset {
+            account: &account.email,
+            model,
+            at,
+            in_seconds: (at - now).num_seconds(),
+        })
+    }
+}
+
+impl<'a> Digest<'a> {
+    pub fn new(data: &'a ApiResponse, now: DateTime<Utc>) -> Self {
+        Digest {
+            timestamp: now,
+            summary: Summary::new(data),
+            worst: worst_account(data).map(|(account, model, remaining_fraction)| Worst {
+                account: &account.email,
+                model,
+                remaining_fraction,
+            }),
+            next_reset: NextReset::new(data, now),
+        }
+    }
+}
+
+impl<'a> Snapshot<'a> {
+    /// Computes the snapshot for the listed models of `data` as of `now`
+    pub fn new(data: &'a ApiResponse, now: DateTime<Utc>) -> Self {
+
+        let accounts = data
+            .accounts
+            .iter()
+            .map(|account| {
+                let mut models = BTreeMap::new();
+                if let Some(ref limits) = account.limits {
+                    for model in &data.models {
+                        let Some(quota) = limits.get(model) else {
+                            continue;
+                        };
+                        let rate_limit = account.model_rate_limits.as_ref().and_then(|r| r.get(model));
+                        models.insert(
+                            model.as_str(),
+                            ModelState {
+                                remaining_fraction: quota.remaining_fraction,
+                                remaining: quota.remaining,
+                                limit: quota.limit,
+                                estimated_remaining: quota.estimated_remaining,
+                                reset_time: parse_reset(quota),
+                                rate_limited: rate_limit.map(|r| r.is_rate_limited).unwrap_or(false),
+                                available: model_available(account, model),
+                                reason: rate_limit.and_then(|r| r.reason.as_deref()),
+                            },
+                        );
+                    }
+                }
+                AccountState {
+                    email: &account.email,
+                    status: get_account_status(account).0,
+                    last_used: account
+                        .last_used
+                        .and_then(|ts| DateTime::from_timestamp_millis(ts as i64)),
+                    credentials_expire_at: account.credentials_expiry(),
+                    limited_seconds_24h: account.limited_secs,
+                    origin: account.origin.as_deref(),
+                    models,
+                }
+            })
+            .collect();
+
+        Snapshot {
+            timestamp: now,
+            summary: Summary::new(data),
+            next_reset: NextReset::new(data, now),
+            models: &data.models,
+            accounts,
+        }
+    }
+}
@@ -0,0 +1,193 @@
+//! Telegram bot for the notifier: state changes as HTML messages, and with
+//! `commands` set, `/status` answered with the watch header in monospace
+
+use crate::{
+    config::{ChatId, StatusLabels, TelegramConfig},
+    count_stats,
+    events::Event,
+    get_short_email,
+    notify::Level,
+    parse_reset, strip_ansi, ApiResponse, HumanDuration,
+};
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::{
+    atomic::{AtomicI64, Ordering},
+    Arc,
+};
+
+/// What the Bot API wraps every answer in
+#[derive(Debug, Deserialize)]
+struct Reply<T> {
+    ok: bool,
+    #[serde(default)]
+    description: Option<String>,
+    result: Option<T>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Update {
+    update_id: i64,
+    message: Option<Message>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Message {
+    chat: Chat,
+    #[serde(default)]
+    text: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Chat {
+    id: i64,
+    #[serde(default)]
+    username: Option<String>,
+}
+
+impl Chat {
+    fn is(&self, wanted: &ChatId) -> bool {
+        match wanted {
+            ChatId::Id(id) => self.id == *id,
+            ChatId::Name(name) => self.username.as_deref() == Some(name.trim_start_matches('@')),
+        }
+    }
+}
+
+/// `text` safe inside Telegram's HTML
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+fn marker(level: Level) -> &'static str {
+    match level {
+        Level::Good => "🟢",
+        Level::Warning => "🟡",
+        Level::Danger => "🔴",
+    }
+}
+
+/// Cheap to clone; the clones share the update offset
+#[derive(Clone)]
+pub struct Telegram {
+    config: TelegramConfig,
+    /// The update after the last one read, so each command is answered once;
+    /// `-1` until the backlog from before the start has been skipped
+    offset: Arc<AtomicI64>,
+}
+
+impl Telegram {
+    pub fn new(config: &TelegramConfig) -> Telegram {
+        Telegram {
+            config: config.clone(),
+            offset: Arc::new(AtomicI64::new(-1)),
+        }
+    }
+
+    pub fn answers_commands(&self) -> bool {
+        self.config.commands
+    }
+
+    fn method(&self, name: &str) -> String {
+        format!("{}/bot{}/{}", self.config.api_url.trim_end_matches('/'), self.config.bot_token, name)
+    }
+
+    async fn call<T>(&self, client: &reqwest::Client, name: &str, body: Value) -> Result<T>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let response = client
+            .post(self.method(name))
+            .json(&body)
+            // The URL holds the token, so it stays out of the error
+            .send()
+            .await
+            .map_err(|e| e.without_url())
+            .context("Failed to reach the Bot API")?;
+        let status = response.status();
+        let reply: Reply<T> = response.json().await.map_err(|e| e.without_url()).context("Bot API answer is not JSON")?;
+        match reply.result {
+            Some(result) if reply.ok => Ok(result),
+            _ => bail!("Bot API answered {}: {}", status, reply.description.unwrap_or_default()),
+        }
+    }
+
+    /// Sends `text`, HTML-formatted, to the configured chat
+    pub async fn send(&self, client: &reqwest::Client, text: String) -> Result<()> {
+        let body = json!({
+            "chat_id": self.config.chat_id,
+            "text": text,
+            "parse_mode": "HTML",
+            "disable_web_page_preview": true,
+        });
+        self.call::<Value>(client, "sendMessage", body).await.map(|_| ())
+    }
+
+    /// Answers the `/status` commands sent to the chat since the last call
+    /// with `header`, the watch header. Polls without waiting, so it is only
+    /// as quick as the refresh interval. Calls must not overlap.
+    pub async fn answer(&self, client: &reqwest::Client, header: String) -> Result<()> {
+        if !self.config.commands {
+            return Ok(());
+        }
+        let offset = self.offset.load(Ordering::Relaxed);
+        let updates: Vec<Update> = self
+            .call(client, "getUpdates", json!({ "offset": offset, "timeout": 0, "allowed_updates": ["message"] }))
+            .await?;
+        if offset < 0 {
+            // An offset of -1 returns only the newest update and drops the
+            // ones before it, so commands queued while not watching are
+            // skipped instead of answered all at once
+            let last = updates.last().map_or(0, |update| update.update_id + 1);
+            self.offset.store(last, Ordering::Relaxed);
+            return Ok(());
+        }
+        let mut asked = false;
+        for update in updates {
+            self.offset.fetch_max(update.update_id + 1, Ordering::Relaxed);
+            let Some(message) = update.message else {
+                continue;
+            };
+            // Groups send `/status@botname`
+            let command = message.text.as_deref().and_then(|t| t.split_whitespace().next()).unwrap_or("");
+            if message.chat.is(&self.config.chat_id) && command.split('@').next() == Some("/status") {
+                asked = true;
+            }
+        }
+        if asked {
+            self.send(client, format!("<pre>{}</pre>", escape(strip_ansi(&header).trim_end()))).await?;
+        }
+        Ok(())
+    }
+}
+
+/// One line per event under a count of the available accounts
+pub fn events_message(events: &[Event], data: &ApiResponse, labels: &StatusLabels, now: DateTime<Utc>) -> String {
+    let (available, _, _, _) = count_stats(&data.accounts);
+    let mut text = format!("<b>AG quota</b>: {}/{} accounts available", available, data.accounts.len());
+    for event in events {
+        let name = escape(get_short_email(&event.account));
+        let (from, to) = (escape(labels.label(&event.from)), escape(labels.label(&event.to)));
+        text.push('\n');
+        text.push_str(marker(Level::of(&event.to)));
+        match event.model {
+            Some(ref model) => {
+                text.push_str(&format!(" <b>{}</b> {}: {} → <b>{}</b>", name, escape(model), from, to));
+                let reset = data
+                    .accounts
+                    .iter()
+                    .find(|a| a.email == event.account)
+                    .and_then(|a| a.limits.as_ref()?.get(model.as_str()))
+                    .and_then(parse_reset)
+                    .filter(|at| *at > now);
+                if let Some(at) = reset {
+                    text.push_str(&format!(", resets in {}", HumanDuration(at - now)));
+                }
+            }
+            None => text.push_str(&format!(" <b>{}</b>: {} → <b>{}</b>", name, from, to)),
+        }
+    }
+    text
+}
@@ -0,0 +1,39 @@
+use crate::{config::StatusLabels, state::Snapshot};
+use anyhow::{Context, Result};
+use minijinja::Environment;
+use std::{fs, path::Path};
+
+const NAME: &str = "template";
+
+/// A user-supplied `--template` file, rendered against each refresh's
+/// [`Snapshot`]. `{{ account.status | label }}` gives the `[status]` label.
+pub struct Template {
+    env: Environment<'static>,
+}
+
+impl Template {
+    /// Reads and compiles the template so syntax errors surface before the first fetch
+    pub fn load(path: &Path, labels: &StatusLabels) -> Result<Template> {
+        let source = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read template {}", path.display()))?;
+        let mut env = Environment::new();
+        let labels = labels.clone();
+        env.add_filter("label", move |status: String| labels.label(&status).to_string());
+        env.add_template_owned(NAME, source)
+            .with_context(|| format!("Invalid template {}", path.display()))?;
+        Ok(Template { env })
+    }
+
+    pub fn render(&self, out: &mut String, snapshot: &Snapshot) -> Result<()> {
+        let rendered = self
+            .env
+            .get_template(NAME)
+            .and_then(|t| t.render(snapshot))
+            .context("Failed to render template")?;
+        out.push_str(&rendered);
+        if !rendered.ends_with('\n') {
+            out.push('\n');
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,71 @@
+use anyhow::{bail, Context, Result};
+use std::{
+    net::{Ipv4Addr, TcpListener},
+    process::{Child, Command, Stdio},
+    time::{Duration, Instant},
+};
+
+/// Long enough to type a password or touch a security key
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// An `ssh -L` port-forward to the proxy, closed when dropped
+pub struct Tunnel {
+    child: Child,
+}
+
+impl Tunnel {
+    /// Forwards a free local port through `destination` (anything `ssh`
+    /// accepts, e.g. `user@host`) to the host and port of `url` as seen from
+    /// the remote end, and points `url` at the local side
+    pub async fn open(destination: &str, url: &mut String) -> Result<Tunnel> {
+        let mut target = reqwest::Url::parse(url).with_context(|| format!("Invalid URL {}", url))?;
+        let host = target.host_str().context("--ssh needs a URL with a host")?.to_string();
+        let port = target
+            .port_or_known_default()
+            .context("--ssh needs a URL with a port")?;
+        let local = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+            .and_then(|l| l.local_addr())
+            .context("Failed to find a free local port")?
+            .port();
+
+        let child = Command::new("ssh")
+            .args(["-N", "-o", "ExitOnForwardFailure=yes", "-L"])
+            .arg(format!("127.0.0.1:{}:{}:{}", local, host, port))
+            .arg(destination)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .spawn()
+            .context("Failed to run ssh")?;
+        let mut tunnel = Tunnel { child };
+
+        // ssh listens only after authenticating, so the first successful
+        // connect means the forward is up
+        let started = Instant::now();
+        loop {
+            if let Some(status) = tunnel.child.try_wait().context("Failed to check on ssh")? {
+                bail!("ssh to {} exited ({}) before the tunnel was up", destination, status);
+            }
+            if tokio::net::TcpStream::connect((Ipv4Addr::LOCALHOST, local)).await.is_ok() {
+                break;
+            }
+            if started.elapsed() >= CONNECT_TIMEOUT {
+                bail!("Timed out waiting for the ssh tunnel through {}", destination);
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+
+        target.set_host(Some("127.0.0.1")).context("Failed to point the URL at the tunnel")?;
+        if target.set_port(Some(local)).is_err() {
+            bail!("Failed to point the URL at the tunnel");
+        }
+        *url = target.to_string();
+        Ok(tunnel)
+    }
+}
+
+impl Drop for Tunnel {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
@@ -0,0 +1,165 @@
+use crate::{
+    intern::Name,
+    apply_tag_filter, endpoints::fetch_data, error, http_client, locale, model_available, parse_reset, Account, ApiResponse,
+    Args, HumanDuration, RenderOptions,
+};
+use anyhow::{bail, Result};
+use chrono::{DateTime, Local, Utc};
+use clap::Args as ClapArgs;
+use std::time::{Duration, Instant};
+
+#[derive(ClapArgs, Debug)]
+pub struct WaitArgs {
+    /// Only count accounts that can serve this model [default: any listed model]
+    #[arg(short, long)]
+    pub model: Option<String>,
+
+    /// Unblock once this many accounts qualify
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    pub min_accounts: usize,
+
+    /// Remaining fraction an account needs to qualify, e.g. 0.2 [default: anything left]
+    #[arg(long, value_name = "FRACTION", value_parser = parse_fraction)]
+    pub min_quota: Option<f64>,
+
+    /// Give up with exit code 1 after this long, e.g. 30m
+    #[arg(long, value_parser = humantime::parse_duration)]
+    pub timeout: Option<Duration>,
+}
+
+fn parse_fraction(s: &str) -> Result<f64, String> {
+    let value: f64 = s.parse().map_err(|e| format!("{}", e))?;
+    if !(0.0..=1.0).contains(&value) {
+        return Err("must be between 0 and 1".to_string());
+    }
+    Ok(value)
+}
+
+/// Polling faster than this gains nothing while waiting
+const MIN_POLL: Duration = Duration::from_secs(1);
+
+impl WaitArgs {
+    fn models<'a>(&'a self, data: &'a ApiResponse) -> Vec<&'a str> {
+        match self.model {
+            Some(ref model) => vec![model.as_str()],
+            None => data.models.iter().map(Name::as_str).collect(),
+        }
+    }
+
+    /// Whether `account` can serve `model` with at least `min_quota` left
+    fn qualifies_for(&self, account: &Account, model: &str) -> bool {
+        model_available(account, model)
+            && account
+                .limits
+                .as_ref()
+                .and_then(|l| l.get(model))
+                .map(|q| q.remaining_fraction >= self.min_quota.unwrap_or(0.0))
+                .unwrap_or(false)
+    }
+
+    fn qualifies(&self, account: &Account, models: &[&str]) -> bool {
+        models.iter().any(|m| self.qualifies_for(account, m))
+    }
+
+    fn describe(&self) -> String {
+        let mut text = format!(
+            "{} account{}",
+            self.min_accounts,
+            if self.min_accounts == 1 { "" } else { "s" }
+        );
+        if let Some(fraction) = self.min_quota {
+            text.push_str(&format!(" with {} left", locale::Percent(fraction)));
+        }
+        if let Some(ref model) = self.model {
+            text.push_str(&format!(" on {}", model));
+        }
+        text
+    }
+}
+
+/// When an account that doesn't qualify for `model` yet should: at the
+/// quota's reset, or when the rate limit on a quota that is already enough
+/// runs out. `None` if neither is known.
+fn recovery(wait_args: &WaitArgs, account: &Account, model: &str, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    if account.is_invalid.unwrap_or(false) || !account.enabled.unwrap_or(true) {
+        return None;
+    }
+    let quota = account.limits.as_ref()?.get(model)?;
+    let reset = parse_reset(quota).filter(|at| *at > now);
+    if quota.remaining_fraction <= 0.0 || quota.remaining_fraction < wait_args.min_quota.unwrap_or(0.0) {
+        return reset;
+    }
+    account
+        .model_rate_limits
+        .as_ref()
+        .and_then(|r| r.get(model))
+        .and_then(|l| l.retry_after)
+        .map(|secs| now + chrono::Duration::seconds(secs as i64))
+        .or(reset)
+}
+
+/// When the condition should hold, taking each waiting account's soonest
+/// recovery and assuming nobody drains a qualifying account in the meantime
+fn eta(wait_args: &WaitArgs, data: &ApiResponse, ready: usize, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let missing = wait_args.min_accounts.checked_sub(ready).filter(|n| *n > 0)?;
+    let models = wait_args.models(data);
+    let mut recoveries: Vec<DateTime<Utc>> = data
+        .accounts
+        .iter()
+        .filter(|a| !wait_args.qualifies(a, &models))
+        .filter_map(|a| models.iter().filter_map(|m| recovery(wait_args, a, m, now)).min())
+        .collect();
+    recoveries.sort();
+    recoveries.get(missing - 1).copied()
+}
+
+/// `wait` subcommand: blocks until enough accounts have enough quota, then
+/// prints their emails. Progress goes to stderr whenever it changes.
+pub async fn run(args: &Args, opts: &RenderOptions, wait_args: &WaitArgs) -> Result<()> {
+    let client = http_client(args)?;
+    let poll = args.interval.max(MIN_POLL);
+    let started = Instant::now();
+    let target = wait_args.describe();
+    let mut last_status = String::new();
+
+    loop {
+        let now = Utc::now();
+        let status = match fetch_data(&client, args, &opts.config, |_, _| Ok(())).await {
+            Ok(mut data) => {
+                apply_tag_filter(&mut data, opts);
+                let models = wait_args.models(&data);
+                let ready: Vec<&Account> = data.accounts.iter().filter(|a| wait_args.qualifies(a, &models)).collect();
+                if ready.len() >= wait_args.min_accounts {
+                    for account in ready {
+                        println!("{}", account.email);
+                    }
+                    return Ok(());
+                }
+                match eta(wait_args, &data, ready.len(), now) {
+                    Some(at) => format!(
+                        "Waiting for {}: {} now, ETA {} (in {})",
+                        target,
+                        ready.len(),
+                        at.with_timezone(&Local).format(locale::current().clock),
+                        HumanDuration(at - now)
+                    ),
+                    None => format!("Waiting for {}: {} now, no known reset brings enough back", target, ready.len()),
+                }
+            }
+            Err(e) => format!("Waiting for {}: {}", target, error::one_line(&e)),
+        };
+        // The countdown moves every poll; only the rest is worth repeating
+        let key = status.split(" (in ").next().unwrap_or(&status);
+        if key != last_status {
+            eprintln!("{}", status);
+            last_status = key.to_string();
+        }
+
+        if let Some(timeout) = wait_args.timeout {
+            if started.elapsed() >= timeout {
+                bail!("Gave up after {} waiting for {}", humantime::format_duration(timeout), target);
+            }
+        }
+        tokio::time::sleep(poll).await;
+    }
+}
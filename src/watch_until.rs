@@ -0,0 +1,85 @@
+use crate::{
+    apply_tag_filter,
+    endpoints::fetch_data,
+    error,
+    expr::{self, Expr, FleetScope},
+    http_client, Args, RenderOptions,
+};
+use anyhow::{bail, Result};
+use clap::{Args as ClapArgs, ValueEnum};
+use std::time::{Duration, Instant};
+
+/// Exit status when the condition is true at exit
+pub const EXIT_TRUE: i32 = 0;
+/// Exit status when the condition is false at exit
+pub const EXIT_FALSE: i32 = 3;
+/// Exit status when `--timeout` ran out first
+pub const EXIT_TIMEOUT: i32 = 4;
+
+#[derive(ClapArgs, Debug)]
+pub struct WatchUntilArgs {
+    /// Expression over the fleet, e.g. "available == 0" or "quota('gemini-3-flash') < 0.2"
+    #[arg(long, value_parser = expr::parse_fleet)]
+    pub condition: Expr,
+
+    /// Exit once the condition is true, once it is false, or once it flips from its first value
+    #[arg(long, value_enum, default_value_t = Trigger::True)]
+    pub on: Trigger,
+
+    /// Give up after this long, e.g. 2h
+    #[arg(long, value_parser = humantime::parse_duration)]
+    pub timeout: Option<Duration>,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trigger {
+    True,
+    False,
+    Change,
+}
+
+/// `watch-until` subcommand: polls without output and returns the exit
+/// status once the condition reaches the state `--on` asks for. Fetch errors
+/// are reported once per outage, since a flaky network shouldn't end the watch.
+pub async fn run(args: &Args, opts: &RenderOptions, until: &WatchUntilArgs) -> Result<i32> {
+    let client = http_client(args)?;
+    let poll = args.interval.max(Duration::from_secs(1));
+    let started = Instant::now();
+    let mut first = None;
+    let mut failing = false;
+
+    loop {
+        match fetch_data(&client, args, &opts.config, |_, _| Ok(())).await {
+            Ok(mut data) => {
+                failing = false;
+                apply_tag_filter(&mut data, opts);
+                let holds = until.condition.eval(&FleetScope { data: &data }).truthy();
+                let initial = *first.get_or_insert(holds);
+                let done = match until.on {
+                    Trigger::True => holds,
+                    Trigger::False => !holds,
+                    Trigger::Change => holds != initial,
+                };
+                if done {
+                    return Ok(if holds { EXIT_TRUE } else { EXIT_FALSE });
+                }
+            }
+            // Once per outage, so a wrong URL doesn't go unnoticed
+            Err(e) if !failing => {
+                failing = true;
+                eprintln!("Error: {} (still watching)", error::one_line(&e));
+            }
+            Err(_) => {}
+        }
+
+        if let Some(timeout) = until.timeout {
+            if started.elapsed() >= timeout {
+                if first.is_none() {
+                    bail!("No successful fetch in {}", humantime::format_duration(timeout));
+                }
+                return Ok(EXIT_TIMEOUT);
+            }
+        }
+        tokio::time::sleep(poll).await;
+    }
+}
@@ -0,0 +1,43 @@
+use crate::config::XmppConfig;
+use anyhow::{anyhow, Context, Result};
+use std::time::Duration;
+use tokio_xmpp::{
+    jid::Jid,
+    parsers::message::{Body, Message, MessageType},
+    SimpleClient,
+};
+
+/// Logging in and sending one message must fit in this
+const SEND_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The account's password: from the config, else with the `keyring`
+/// feature from the entry `auth set --entry xmpp:JID` stored
+fn password(config: &XmppConfig) -> Result<String> {
+    if let Some(ref password) = config.password {
+        return Ok(password.clone());
+    }
+    #[cfg(feature = "keyring")]
+    if let Some(password) = crate::auth::stored_secret(&format!("xmpp:{}", config.jid)) {
+        return Ok(password);
+    }
+    Err(anyhow!("No password for {} in [xmpp] or the system keyring", config.jid))
+}
+
+/// Logs in as `config.jid`, sends `text` as a chat message to the
+/// recipient and logs out again; alerts are too rare to hold a session open
+pub async fn send(config: &XmppConfig, text: String) -> Result<()> {
+    let recipient: Jid = config.recipient.parse().with_context(|| format!("Invalid recipient {}", config.recipient))?;
+    let password = password(config)?;
+    let session = async {
+        let mut client = SimpleClient::new(&config.jid, password).await?;
+        let mut message = Message::new(Some(recipient));
+        message.type_ = MessageType::Chat;
+        message.bodies.insert(String::new(), Body(text));
+        client.send_stanza(message).await?;
+        client.end().await
+    };
+    tokio::time::timeout(SEND_TIMEOUT, session)
+        .await
+        .map_err(|_| anyhow!("Timed out after {}s", SEND_TIMEOUT.as_secs()))?
+        .with_context(|| format!("Failed to send as {}", config.jid))
+}
@@ -0,0 +1,223 @@
+use crate::{
+    apply_tag_filter, count_stats, endpoints::fetch_data, error, get_account_status, http_client, jittered,
+    model_available, watching, ApiResponse, Args, RenderOptions,
+};
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use clap::Args as ClapArgs;
+use serde::{Deserialize, Serialize};
+use std::{
+    net::{IpAddr, SocketAddr},
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+/// Where Zabbix servers and proxies listen for trapper data
+const DEFAULT_PORT: u16 = 10051;
+/// Connecting, sending and reading the answer must fit in this
+const SEND_TIMEOUT: Duration = Duration::from_secs(10);
+/// Zabbix 4.0+ header: protocol magic, then the flags byte (plain JSON)
+const HEADER: &[u8; 5] = b"ZBXD\x01";
+/// Answers are a short status line; anything this big is not a Zabbix server
+const MAX_ANSWER: u64 = 1 << 20;
+
+#[derive(ClapArgs, Debug)]
+pub struct ZabbixArgs {
+    /// Zabbix server or proxy, e.g. zabbix.example.com, 10.0.0.5:10051 or [fe80::5]:10051
+    #[arg(long, value_name = "HOST[:PORT]")]
+    pub server: String,
+
+    /// Zabbix host the items belong to, as configured in the frontend
+    #[arg(long)]
+    pub host: String,
+
+    /// Start of every item key
+    #[arg(long, default_value = "ag")]
+    pub prefix: String,
+}
+
+/// One trapper item value
+#[derive(Debug, Serialize)]
+struct Item<'a> {
+    host: &'a str,
+    key: String,
+    value: String,
+    clock: i64,
+}
+
+#[derive(Debug, Serialize)]
+struct Request<'a> {
+    request: &'static str,
+    data: &'a [Item<'a>],
+    clock: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct Answer {
+    response: String,
+    #[serde(default)]
+    info: String,
+}
+
+/// `key[param, ...]` with each parameter quoted, since emails and model
+/// names may hold characters keys reserve
+fn key(prefix: &str, name: &str, params: &[&str]) -> String {
+    let params: Vec<String> = params.iter().map(|p| format!("\"{}\"", p.replace('"', "\\\""))).collect();
+    format!("{}.{}[{}]", prefix, name, params.join(","))
+}
+
+/// Every item for one response: fleet counts, available accounts per model,
+/// and each account's status and remaining fraction per model
+fn items<'a>(data: &ApiResponse, host: &'a str, prefix: &str, now: DateTime<Utc>) -> Vec<Item<'a>> {
+    let clock = now.timestamp();
+    let item = |key: String, value: String| Item { host, key, value, clock };
+    let (available, rate_limited, invalid, disabled) = count_stats(&data.accounts);
+    let mut items = vec![
+        item(format!("{}.accounts.total", prefix), data.accounts.len().to_string()),
+        item(format!("{}.accounts.available", prefix), available.to_string()),
+        item(format!("{}.accounts.rate_limited", prefix), rate_limited.to_string()),
+        item(format!("{}.accounts.invalid", prefix), invalid.to_string()),
+        item(format!("{}.accounts.disabled", prefix), disabled.to_string()),
+    ];
+    for model in &data.models {
+        let serving = data.accounts.iter().filter(|a| model_available(a, model)).count();
+        items.push(item(key(prefix, "model.available", &[model]), serving.to_string()));
+    }
+    for account in &data.accounts {
+        let (status, _) = get_account_status(account);
+        items.push(item(key(prefix, "account.status", &[&account.email]), status.to_string()));
+        for (model, quota) in account.limits.iter().flatten() {
+            if data.models.contains(model) {
+                items.push(item(
+                    key(prefix, "quota", &[&account.email, model]),
+                    quota.remaining_fraction.to_string(),
+                ));
+            }
+        }
+    }
+    items
+}
+
+/// `--server` as a host and port, [`DEFAULT_PORT`] unless it names one. An
+/// IPv6 address takes a port in brackets, `[fe80::5]:10051`
+fn address(server: &str) -> Result<(String, u16)> {
+    if let Ok(address) = server.parse::<SocketAddr>() {
+        return Ok((address.ip().to_string(), address.port()));
+    }
+    if let Ok(ip) = server.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>() {
+        return Ok((ip.to_string(), DEFAULT_PORT));
+    }
+    match server.rsplit_once(':') {
+        Some((host, port)) => {
+            let port = port.parse().with_context(|| format!("Bad port in --server {}", server))?;
+            Ok((host.to_string(), port))
+        }
+        None => Ok((server.to_string(), DEFAULT_PORT)),
+    }
+}
+
+/// Sends `items` with the sender protocol and returns the server's `info`,
+/// e.g. `processed: 12; failed: 0; total: 12; seconds spent: 0.000123`
+async fn send(host: &str, port: u16, items: &[Item<'_>], now: DateTime<Utc>) -> Result<String> {
+    let address = if host.contains(':') { format!("[{}]:{}", host, port) } else { format!("{}:{}", host, port) };
+    let body = serde_json::to_vec(&Request { request: "sender data", data: items, clock: now.timestamp() })
+        .context("Failed to encode items")?;
+    let exchange = async {
+        let mut stream = TcpStream::connect((host, port)).await?;
+        let mut packet = Vec::with_capacity(13 + body.len());
+        packet.extend_from_slice(HEADER);
+        // Data length, then four reserved bytes
+        packet.extend_from_slice(&(body.len() as u64).to_le_bytes());
+        packet.extend_from_slice(&body);
+        stream.write_all(&packet).await?;
+
+        let mut header = [0u8; 13];
+        stream.read_exact(&mut header).await?;
+        if &header[..4] != b"ZBXD" {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "not a Zabbix answer"));
+        }
+        let length = u32::from_le_bytes([header[5], header[6], header[7], header[8]]) as u64;
+        let mut answer = Vec::new();
+        stream.take(length.min(MAX_ANSWER)).read_to_end(&mut answer).await?;
+        Ok(answer)
+    };
+    let answer = tokio::time::timeout(SEND_TIMEOUT, exchange)
+        .await
+        .map_err(|_| anyhow::anyhow!("No answer from {} within {}s", address, SEND_TIMEOUT.as_secs()))?
+        .with_context(|| format!("Failed to send to {}", address))?;
+    let answer: Answer = serde_json::from_slice(&answer).with_context(|| format!("Unreadable answer from {}", address))?;
+    if answer.response != "success" {
+        bail!("{} answered {}: {}", address, answer.response, answer.info);
+    }
+    Ok(answer.info)
+}
+
+/// Items the server couldn't take, which is what `info` reports for keys
+/// no trapper item on the host is configured for
+fn failed(info: &str) -> usize {
+    info.split(';')
+        .find_map(|part| part.trim().strip_prefix("failed:"))
+        .and_then(|n| n.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+/// `export zabbix`: sends the items after every refresh, or once with `--once`.
+/// While watching, failed fetches and sends are reported on stderr and the
+/// next refresh tries again.
+pub async fn run(args: &Args, opts: &RenderOptions, zabbix_args: &ZabbixArgs) -> Result<()> {
+    let (host, port) = address(&zabbix_args.server)?;
+    let client = http_client(args)?;
+    loop {
+        let now = Utc::now();
+        let result = async {
+            let mut data = fetch_data(&client, args, &opts.config, |_, _| Ok(())).await?;
+            apply_tag_filter(&mut data, opts);
+            let items = items(&data, &zabbix_args.host, &zabbix_args.prefix, now);
+            let info = send(&host, port, &items, now).await?;
+            Ok::<_, anyhow::Error>((items.len(), info))
+        }
+        .await;
+        match result {
+            Ok((sent, info)) => {
+                let failed = failed(&info);
+                if failed > 0 {
+                    eprintln!(
+                        "Warning: Zabbix took {} of {} items; are trapper items for the rest set up on host {}? ({})",
+                        sent - failed.min(sent),
+                        sent,
+                        zabbix_args.host,
+                        info
+                    );
+                }
+            }
+            Err(e) if watching(args) => eprintln!("Error: {}", error::one_line(&e)),
+            Err(e) => return Err(e),
+        }
+        if !watching(args) {
+            return Ok(());
+        }
+        tokio::time::sleep(jittered(args.interval, args.jitter)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adds_the_default_port_unless_given() {
+        let parsed = |server: &str| address(server).unwrap();
+        assert_eq!(parsed("zabbix.example.com"), ("zabbix.example.com".to_string(), DEFAULT_PORT));
+        assert_eq!(parsed("zabbix.example.com:10052"), ("zabbix.example.com".to_string(), 10052));
+        assert_eq!(parsed("10.0.0.5"), ("10.0.0.5".to_string(), DEFAULT_PORT));
+        assert_eq!(parsed("10.0.0.5:10052"), ("10.0.0.5".to_string(), 10052));
+        assert_eq!(parsed("::1"), ("::1".to_string(), DEFAULT_PORT));
+        assert_eq!(parsed("fe80::5"), ("fe80::5".to_string(), DEFAULT_PORT));
+        assert_eq!(parsed("[fe80::5]"), ("fe80::5".to_string(), DEFAULT_PORT));
+        assert_eq!(parsed("[fe80::5]:10052"), ("fe80::5".to_string(), 10052));
+        assert!(address("zabbix.example.com:zabbix").is_err());
+    }
+}